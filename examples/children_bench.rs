@@ -0,0 +1,94 @@
+
+extern crate id_tree;
+extern crate parser;
+
+use id_tree::NodeId;
+use parser::lexer::*;
+use parser::parser::*;
+use parser::parser::recursive_descent::*;
+use parser::parser::syntax_node::SyntaxTree;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+// counts every allocation made by the program.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn synthetic_program(functions: usize) -> String {
+    let mut src = String::new();
+    for i in 0..functions {
+        src.push_str(&format!("
+int f{}(int x, int y)
+{{
+    int a = x, b = y, c;
+    int i;
+    for (i = 0; i < x; i = i + 1) {{
+        int t = a + b;
+        a = b;
+        b = t;
+    }}
+    c = a * b + x - y;
+    return c;
+}}
+", i));
+    }
+
+    src
+}
+
+// visits every node the way the generator does, copying the child ids of each
+// node into a `Vec` first, like `children_ids`.
+fn walk_collected(tree: &SyntaxTree, id: &NodeId) -> usize {
+    let childs: Vec<NodeId> = tree.children_ids(id).unwrap().map(|x| x.clone()).collect();
+    1 + childs.iter().map(|x| walk_collected(tree, x)).sum::<usize>()
+}
+
+// the same walk borrowing the ids from the tree, like `children`.
+fn walk_borrowed(tree: &SyntaxTree, id: &NodeId) -> usize {
+    1 + tree.children_ids(id).unwrap().map(|x| walk_borrowed(tree, x)).sum::<usize>()
+}
+
+// returns the number of allocations made by `f`.
+fn measure<F: FnOnce() -> usize>(name: &str, f: F) -> usize {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let nodes = f();
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!("{:<14} {:>8} nodes {:>10} allocations {:>8.2} ms", name, nodes, allocations,
+             elapsed.as_secs() as f64 * 1000.0 + elapsed.subsec_nanos() as f64 / 1e6);
+
+    allocations
+}
+
+fn main() {
+    let src = synthetic_program(2000);
+    let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+    parser.run().unwrap();
+
+    let tree = parser.syntax_tree();
+    let root = tree.root_node_id().unwrap();
+
+    let collected = measure("children_ids", || walk_collected(tree, root));
+    let borrowed = measure("children", || walk_borrowed(tree, root));
+
+    println!("{} fewer allocations per walk", collected - borrowed);
+}
@@ -42,6 +42,6 @@ int f(int a, int b)
 
     let ee = generater.execution_engine().unwrap();
 
-    let f: Symbol<unsafe extern "C" fn(u64, u64) -> u64> = unsafe { ee.get_function("f").unwrap() };
+    let f: Symbol<unsafe extern "C" fn(u32, u32) -> u32> = unsafe { ee.get_function("f").unwrap() };
     assert_eq!(unsafe { f(2, 3) }, 5);
 }
\ No newline at end of file
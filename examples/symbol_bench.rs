@@ -0,0 +1,174 @@
+
+extern crate parser;
+
+use parser::lexer::*;
+use parser::parser::*;
+use parser::parser::recursive_descent::*;
+use parser::parser::symbol_manager::*;
+use parser::parser::type_analyzer::*;
+use parser::token::*;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+// counts every allocation made by the program.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+// many functions declaring the same local names, the way real programs do.
+fn synthetic_program(functions: usize) -> String {
+    let mut src = String::new();
+    for i in 0..functions {
+        src.push_str(&format!("
+int f{}(int x, int y)
+{{
+    int a = x, b = y, c;
+    int i;
+    for (i = 0; i < x; i = i + 1) {{
+        int t = a + b;
+        a = b;
+        b = t;
+    }}
+    c = a * b + x - y;
+    return c;
+}}
+", i));
+    }
+
+    src
+}
+
+trait Scopes {
+    fn push(&mut self);
+    fn pop(&mut self);
+    fn declare(&mut self, name: &str, value: usize);
+    fn lookup(&self, name: &str) -> Option<usize>;
+}
+
+// scopes keyed by the name itself, every declaration stores a copy of it.
+struct StringScopes {
+    tables: Vec<HashMap<String, usize>>,
+}
+
+impl Scopes for StringScopes {
+    fn push(&mut self) { self.tables.push(HashMap::new()); }
+    fn pop(&mut self) { self.tables.pop(); }
+
+    fn declare(&mut self, name: &str, value: usize) {
+        self.tables.last_mut().unwrap().insert(name.to_owned(), value);
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.tables.iter().rev().filter_map(|t| t.get(name)).next().cloned()
+    }
+}
+
+// the symbol manager of the crate, its scopes are keyed by interned names.
+struct ManagedScopes {
+    manager: Rc<RefCell<SymbolManager<usize, ()>>>,
+    guards: Vec<ScopeGuard<usize, ()>>,
+}
+
+impl Scopes for ManagedScopes {
+    fn push(&mut self) { self.guards.push(ScopeGuard::new(self.manager.clone(), ())); }
+    fn pop(&mut self) { self.guards.pop(); }
+
+    fn declare(&mut self, name: &str, value: usize) {
+        self.manager.borrow_mut().replace_symbol(name, value);
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.manager.borrow().lookup(name).cloned()
+    }
+}
+
+// declares and looks up the identifiers of `tokens` the way a checker walking
+// the program would, a `{` opens a scope. returns the number of names found.
+fn replay<S: Scopes>(scopes: &mut S, tokens: &[Token]) -> usize {
+    let mut found = 0;
+    // inside a declaration, and before the `=` of its current declarator
+    let mut in_declaration = false;
+    let mut declaring = false;
+
+    for (i, tok) in tokens.iter().enumerate() {
+        match *tok {
+            Token::KeyWord(ref k) if k.is_type() => {
+                in_declaration = true;
+                declaring = true;
+            },
+            Token::Operator(Operators::Assign) => declaring = false,
+            Token::Comma => declaring = in_declaration,
+            Token::Identifier(ref name, _) if declaring => scopes.declare(name, i),
+            Token::Identifier(ref name, _) => found += scopes.lookup(name).is_some() as usize,
+            Token::Semicolon | Token::Bracket(_) => {
+                in_declaration = false;
+                declaring = false;
+
+                match *tok {
+                    Token::Bracket(Brackets::LeftCurlyBracket) => scopes.push(),
+                    Token::Bracket(Brackets::RightCurlyBracket) => scopes.pop(),
+                    _ => {},
+                }
+            },
+            _ => {},
+        }
+    }
+
+    found
+}
+
+// returns the number of allocations made by `f`.
+fn measure<F: FnOnce()>(name: &str, f: F) -> usize {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    f();
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!("{:<12} {:>10} allocations {:>8.2} ms", name, allocations,
+             elapsed.as_secs() as f64 * 1000.0 + elapsed.subsec_nanos() as f64 / 1e6);
+
+    allocations
+}
+
+fn main() {
+    let src = synthetic_program(2000);
+    let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+
+    // parsing includes the symbol checker.
+    measure("parse", || parser.run().unwrap());
+    measure("type check", || assert!(TypeAnalyzer::new(parser.syntax_tree_mut()).run().is_empty()));
+
+    // the symbol table alone, keyed by `String` as before interning, and the
+    // symbol manager. the tokens still own their names.
+    let tokens: Vec<Token> = SimpleLexer::new(src.as_bytes()).tokens().collect();
+    let mut string_scopes = StringScopes { tables: vec![HashMap::new()] };
+    let mut managed_scopes = ManagedScopes { manager: Rc::new(RefCell::new(SymbolManager::new())), guards: vec![] };
+
+    let (mut string_found, mut managed_found) = (0, 0);
+    let string = measure("String keys", || string_found = replay(&mut string_scopes, &tokens));
+    let interned = measure("interned", || managed_found = replay(&mut managed_scopes, &tokens));
+    assert_eq!(string_found, managed_found);
+
+    println!("interning saves {} allocations ({:.1}%)", string - interned,
+             (string - interned) as f64 * 100.0 / string as f64);
+}
@@ -5,6 +5,7 @@ extern crate env_logger;
 use parser::lexer::*;
 use parser::parser::*;
 use parser::parser::recursive_descent::*;
+use parser::parser::type_analyzer::*;
 
 fn main() {
 
@@ -12,6 +13,14 @@ fn main() {
 
     let src = "
 struct S { int a, b; char c; };
+
+int f(int a)
+{
+    int b;
+    b = a * 1.5;
+
+    return b;
+}
     ";
     let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
 
@@ -20,7 +29,8 @@ struct S { int a, b; char c; };
     println!("result: {:?}\n", parser.run());
     parser.dump();
 
-    // let mut type_analyzer = TypeAnalyzer::new(parser.syntax_tree());
-    // println!();
-    // println!();
+    let spans = parser.spans().clone();
+    let mut type_analyzer = TypeAnalyzer::with_spans(parser.syntax_tree_mut(), spans);
+    println!();
+    println!("type check: {:?}", type_analyzer.run());
 }
\ No newline at end of file
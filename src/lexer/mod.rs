@@ -11,13 +11,65 @@ use token::Token;
 
 use std::iter::Iterator;
 
-type LexerResult = Result<Token, LexerError>;
+pub type LexerResult = Result<Token, LexerError>;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum LexerError {
     Success,
     UnexpectEnd,
     UnexpectedChar(char, Vec<char>),
+    InvalidNumber(String),
+    UnterminatedString,
+    UnterminatedComment,
+    /// a `\x` escape above 0x7f in a string, which is kept as UTF-8 and
+    /// can't hold the single byte.
+    EscapeOutOfRange(u8),
 }
 
-pub trait Lexer : Iterator<Item=Token> { }
+/// Source position of a token, `line` and `column` start from 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+pub trait Lexer : Iterator<Item=LexerResult> {
+    /// span of the token returned by the last `next()` call.
+    fn span(&self) -> Span;
+
+    fn tokens(self) -> Tokens<Self> where Self: Sized {
+        Tokens {
+            lexer: self,
+            error: None,
+        }
+    }
+}
+
+/// Iterator over plain tokens of a lexer, it stops at the first lexer error.
+pub struct Tokens<L: Lexer> {
+    lexer: L,
+    error: Option<LexerError>,
+}
+
+impl<L: Lexer> Tokens<L> {
+    /// the error which stopped the iteration, if any.
+    pub fn error(&self) -> Option<&LexerError> {
+        self.error.as_ref()
+    }
+}
+
+impl<L: Lexer> Iterator for Tokens<L> {
+    type Item=Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.lexer.next() {
+            Some(Ok(tok)) => Some(tok),
+            Some(Err(e)) => {
+                self.error = Some(e);
+                None
+            },
+            None => None,
+        }
+    }
+}
@@ -5,51 +5,96 @@ use lexer::*;
 
 use std::io::{Read, Bytes};
 use std::iter::{Iterator, Peekable};
+use std::collections::VecDeque;
 
 pub struct SimpleLexer<I: Read> {
     row: usize,
     column: usize,
+    offset: usize,
+    span: Span,
+    start: Span,
+    finished: bool,
+    lookahead: VecDeque<(LexerResult, Span)>,
     peeker: Peekable<Bytes<I>>,
 }
 
 impl<I: Read> Iterator for SimpleLexer<I> {
-    type Item=Token;
+    type Item=LexerResult;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.parse() {
-            Ok(tok) => Some(tok),
-            Err(LexerError::Success) => None,
-            Err(LexerError::UnexpectedChar(c, chars)) => {
-                println!("\nexpect one of [{}], but '{}'\nfounded at row {}, column {}",
-                    chars.iter().map(|c| format!("'{}'", c)).collect::<Vec<String>>().join(", "),
-                    c,
-                    self.row,
-                    self.column);
-                panic!("UnexpectedCharacter");
-            },
-            Err(e) => {
-                panic!("lexer panic at row = {}, column = {}, error = {:?}",
-                    self.row,
-                    self.column,
-                    e);
-            }
-        }
+        let (tok, span) = match self.lookahead.pop_front() {
+            Some(item) => item,
+            None => self.lex()?,
+        };
+
+        self.span = span;
+        Some(tok)
     }
 }
 
-impl<I: Read> Lexer for SimpleLexer<I> {}
+impl<I: Read> Lexer for SimpleLexer<I> {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
 
 impl<I: Read> SimpleLexer<I> {
     pub fn new(r: I) -> SimpleLexer<I> {
         SimpleLexer {
             row: 0,
             column: 0,
+            offset: 0,
+            span: Span { line: 1, column: 1, offset: 0 },
+            start: Span { line: 1, column: 1, offset: 0 },
+            finished: false,
+            lookahead: VecDeque::new(),
             peeker: r.bytes().peekable(),
         }
     }
 
+    /// Returns the `n`-th upcoming token without consuming it, `peek(0)` is the
+    /// token the next call of `next()` will return.
+    pub fn peek(&mut self, n: usize) -> Option<&Token> {
+        while self.lookahead.len() <= n {
+            match self.lex() {
+                Some(item) => self.lookahead.push_back(item),
+                None => return None,
+            }
+        }
+
+        match self.lookahead[n] {
+            (Ok(ref tok), _) => Some(tok),
+            _ => None,
+        }
+    }
+
+    fn lex(&mut self) -> Option<(LexerResult, Span)> {
+        if self.finished {
+            return None;
+        }
+
+        match self.parse() {
+            Err(LexerError::Success) => {
+                self.finished = true;
+                None
+            },
+            Err(e) => {
+                // stop at the first error
+                self.finished = true;
+                Some((Err(e), self.start))
+            },
+            tok => Some((tok, self.start)),
+        }
+    }
+
     fn parse(&mut self) -> LexerResult {
-        while let Some(c) = self.peek() {
+        while let Some(c) = self.peek_byte() {
+            self.start = Span {
+                line: self.row + 1,
+                column: self.column + 1,
+                offset: self.offset,
+            };
+
             return match c {
                 b'a'...b'z' | b'A'...b'Z' | b'_' => self.parse_string(),
                 b'0'...b'9' => self.parse_number(),
@@ -59,16 +104,20 @@ impl<I: Read> SimpleLexer<I> {
                 b'#' => self.parse_preprocessor(),
                 b'=' => self.parse_equal(),
                 b'"' => self.parse_literal_str(),
+                b'\'' => self.parse_literal_char(),
                 b'&' => self.parse_and(),
                 b'|' => self.parse_or(),
                 b'>' => self.parse_greater(),
                 b'<' => self.parse_less(),
                 b'!' => self.parse_not(),
                 b';' => self.convert_char(Token::Semicolon),
-                b'*' => self.convert_char(Token::Asterisk),
+                b'*' => self.parse_asterisk(),
                 b',' => self.convert_char(Token::Comma),
+                b'?' => self.convert_char(Token::Question),
+                b':' => self.convert_char(Token::Colon),
                 b'.' => self.convert_char(Token::Dot),
-                b'^' => self.convert_char(Token::Operator(Operators::Xor)),
+                b'%' => self.parse_mod(),
+                b'^' => self.parse_xor(),
                 b'~' => self.convert_char(Token::Operator(Operators::Not)),
                 b'(' => self.convert_char(Token::Bracket(Brackets::LeftParenthesis)),
                 b')' => self.convert_char(Token::Bracket(Brackets::RightParenthesis)),
@@ -93,8 +142,16 @@ impl<I: Read> SimpleLexer<I> {
     fn parse_greater(&mut self) -> LexerResult {
         self.bump();
 
-        return match self.peek() {
+        return match self.peek_byte() {
             Some(b'=') => self.convert_char(Token::Operator(Operators::GreaterEqual)),
+            Some(b'>') => {
+                self.bump();
+
+                match self.peek_byte() {
+                    Some(b'=') => self.convert_char(Token::Operator(Operators::ShiftRightEqual)),
+                    _ => Ok(Token::Operator(Operators::ShiftRight)),
+                }
+            },
             _ => Ok(Token::Operator(Operators::Greater)),
         }
     }
@@ -102,8 +159,16 @@ impl<I: Read> SimpleLexer<I> {
     fn parse_less(&mut self) -> LexerResult {
         self.bump();
 
-        return match self.peek() {
+        return match self.peek_byte() {
             Some(b'=') => self.convert_char(Token::Operator(Operators::LessEqual)),
+            Some(b'<') => {
+                self.bump();
+
+                match self.peek_byte() {
+                    Some(b'=') => self.convert_char(Token::Operator(Operators::ShiftLeftEqual)),
+                    _ => Ok(Token::Operator(Operators::ShiftLeft)),
+                }
+            },
             _ => Ok(Token::Operator(Operators::Less)),
         }
     }
@@ -111,7 +176,7 @@ impl<I: Read> SimpleLexer<I> {
     fn parse_not(&mut self) -> LexerResult {
         self.bump();
 
-        return match self.peek() {
+        return match self.peek_byte() {
             Some(b'=') => self.convert_char(Token::Operator(Operators::NotEqual)),
             _ => Ok(Token::Operator(Operators::LogicNot)),
         }
@@ -120,8 +185,9 @@ impl<I: Read> SimpleLexer<I> {
     fn parse_and(&mut self) -> LexerResult {
         self.bump();
 
-        match self.peek() {
+        match self.peek_byte() {
             Some(b'&') => return self.convert_char(Token::Operator(Operators::LogicAnd)),
+            Some(b'=') => return self.convert_char(Token::Operator(Operators::AndEqual)),
             _ => {},
         }
 
@@ -131,44 +197,118 @@ impl<I: Read> SimpleLexer<I> {
     fn parse_or(&mut self) -> LexerResult {
         self.bump();
 
-        match self.peek() {
+        match self.peek_byte() {
             Some(b'|') => return self.convert_char(Token::Operator(Operators::LogicOr)),
+            Some(b'=') => return self.convert_char(Token::Operator(Operators::OrEqual)),
             _ => {},
         }
 
         Ok(Token::Operator(Operators::Or))
     }
 
+    fn parse_asterisk(&mut self) -> LexerResult {
+        self.bump();
+
+        match self.peek_byte() {
+            Some(b'=') => self.convert_char(Token::Operator(Operators::MulEqual)),
+            _ => Ok(Token::Asterisk),
+        }
+    }
+
+    fn parse_mod(&mut self) -> LexerResult {
+        self.bump();
+
+        match self.peek_byte() {
+            Some(b'=') => self.convert_char(Token::Operator(Operators::ModEqual)),
+            _ => Ok(Token::Operator(Operators::Mod)),
+        }
+    }
+
+    fn parse_xor(&mut self) -> LexerResult {
+        self.bump();
+
+        match self.peek_byte() {
+            Some(b'=') => self.convert_char(Token::Operator(Operators::XorEqual)),
+            _ => Ok(Token::Operator(Operators::Xor)),
+        }
+    }
+
     fn parse_literal_str(&mut self) -> LexerResult {
         self.bump();
         let mut buf = "\"".to_owned();
 
         while let Some(c) = self.next() {
             match c {
-                b'\\' => {
-                    match self.next() {
-                        Some(b'n') => buf.push('\n'),
-                        Some(b'"') => buf.push('"'),
-                        Some(b'\\') => buf.push('\\'),
-                        Some(c) => return Err(LexerError::UnexpectedChar(c as char, vec!['n', '"', '\\'])),
-                        _ => break,
-                    }
+                b'\\' => match self.parse_escape()? {
+                    c if c as u32 > 0x7f => return Err(LexerError::EscapeOutOfRange(c as u8)),
+                    c => buf.push(c),
                 },
                 b'"' => {
                     buf.push('"');
-                    return self.convert_char(Token::LiteralStr(buf));
+                    return Ok(Token::LiteralStr(buf));
                 },
                 _ => buf.push(c as char),
             }
         }
 
-        Err(LexerError::UnexpectEnd)
+        Err(LexerError::UnterminatedString)
+    }
+
+    fn parse_literal_char(&mut self) -> LexerResult {
+        self.bump();
+
+        let c = match self.next() {
+            Some(b'\\') => self.parse_escape()?,
+            Some(b'\'') => return Err(LexerError::UnexpectedChar('\'', vec![])),
+            Some(c) => c as char,
+            None => return Err(LexerError::UnexpectEnd),
+        };
+
+        match self.next() {
+            Some(b'\'') => Ok(Token::CharLiteral(c)),
+            Some(c) => Err(LexerError::UnexpectedChar(c as char, vec!['\''])),
+            None => Err(LexerError::UnexpectEnd),
+        }
+    }
+
+    // escape sequence, the leading `\` is already consumed.
+    fn parse_escape(&mut self) -> Result<char, LexerError> {
+        match self.next() {
+            Some(b'n') => Ok('\n'),
+            Some(b't') => Ok('\t'),
+            Some(b'r') => Ok('\r'),
+            Some(b'0') => Ok('\0'),
+            Some(b'"') => Ok('"'),
+            Some(b'\'') => Ok('\''),
+            Some(b'\\') => Ok('\\'),
+            Some(b'x') => {
+                let mut value = 0u8;
+                let mut digits = 0;
+
+                while digits < 2 {
+                    match self.peek_byte().and_then(|c| (c as char).to_digit(16)) {
+                        Some(d) => value = value * 16 + d as u8,
+                        None => break,
+                    }
+
+                    self.bump();
+                    digits += 1;
+                }
+
+                match digits {
+                    0 => Err(LexerError::UnexpectedChar('x', vec![])),
+                    _ => Ok(value as char),
+                }
+            },
+            Some(c) => Err(LexerError::UnexpectedChar(c as char, vec!['n', 't', 'r', '0', '"', '\'', '\\', 'x'])),
+            None => Err(LexerError::UnexpectEnd),
+        }
     }
 
     fn parse_minus(&mut self) -> LexerResult {
         self.bump();
 
-        match self.peek() {
+        match self.peek_byte() {
             Some(b'>') => self.convert_char(Token::Arrow),
             Some(b'-') => self.convert_char(Token::Operator(Operators::DoubleMinus)),
             Some(b'=') => self.convert_char(Token::Operator(Operators::MinusEqual)),
@@ -179,7 +319,7 @@ impl<I: Read> SimpleLexer<I> {
     fn parse_equal(&mut self) -> LexerResult {
         self.bump();
 
-        if let Some(b'=') = self.peek() {
+        if let Some(b'=') = self.peek_byte() {
             self.convert_char(Token::Operator(Operators::Equal))
         } else {
             Ok(Token::Operator(Operators::Assign))
@@ -201,7 +341,7 @@ impl<I: Read> SimpleLexer<I> {
 
     fn parse_string(&mut self) -> LexerResult {
         let mut buf = String::new();
-        while let Some(ch) = self.peek() {
+        while let Some(ch) = self.peek_byte() {
             if ch >= b'a' && ch <= b'z' || ch >= b'A' && ch <= b'Z' || ch >= b'0' && ch <= b'9' || ch == b'_' {
                 buf.push(ch as char);
                 self.bump();
@@ -222,8 +362,59 @@ impl<I: Read> SimpleLexer<I> {
     fn parse_number(&mut self) -> LexerResult {
         let mut buf = String::new();
 
-        while let Some(ch) = self.peek() {
-            if ch >= b'0' && ch <= b'9' {
+        if let Some(b'0') = self.peek_byte() {
+            buf.push('0');
+            self.bump();
+
+            match self.peek_byte() {
+                Some(b'x') | Some(b'X') => return self.parse_radix_number(16),
+                Some(b'b') | Some(b'B') => return self.parse_radix_number(2),
+                _ => {},
+            }
+        }
+
+        while let Some(ch) = self.peek_byte() {
+            match ch {
+                b'0'...b'9' | b'.' => {},
+                b'e' | b'E' => {
+                    buf.push(ch as char);
+                    self.bump();
+
+                    // exponent sign
+                    match self.peek_byte() {
+                        Some(c @ b'+') | Some(c @ b'-') => {
+                            buf.push(c as char);
+                            self.bump();
+                        },
+                        _ => {},
+                    }
+                    continue;
+                },
+                b'f' | b'F' => {
+                    buf.push(ch as char);
+                    self.bump();
+                    break;
+                },
+                _ => break,
+            }
+
+            buf.push(ch as char);
+            self.bump();
+        }
+
+        match Numbers::try_from_str(&buf) {
+            Some(n) => Ok(Token::Number(n)),
+            None => Err(LexerError::InvalidNumber(buf)),
+        }
+    }
+
+    // `0x` or `0b` prefixed number, the leading `0` is already consumed.
+    fn parse_radix_number(&mut self, radix: u32) -> LexerResult {
+        let mut buf = "0".to_owned();
+        buf.push(self.next().unwrap() as char);
+
+        while let Some(ch) = self.peek_byte() {
+            if (ch as char).is_digit(radix) {
                 buf.push(ch as char);
                 self.bump();
             } else {
@@ -231,18 +422,27 @@ impl<I: Read> SimpleLexer<I> {
             }
         }
 
-        Ok(Token::Number(Numbers::SignedInt(buf.parse::<isize>().unwrap())))
+        // prefix without any digits
+        if buf.len() == 2 {
+            return Err(LexerError::InvalidNumber(buf));
+        }
+
+        match Numbers::try_from_str(&buf) {
+            Some(n) => Ok(Token::Number(n)),
+            None => Err(LexerError::InvalidNumber(buf)),
+        }
     }
 
     fn parse_add(&mut self) -> LexerResult {
         self.bump();
 
-        match self.peek() {
+        match self.peek_byte() {
             Some(c) => match c {
                 b'+' => {
                     self.bump();
                     Ok(Token::Operator(Operators::DoubleAdd))
-                }
+                },
+                b'=' => self.convert_char(Token::Operator(Operators::AddEqual)),
                 _ => Ok(Token::Operator(Operators::Add)),
             },
             None => Ok(Token::Operator(Operators::Add)),
@@ -252,21 +452,20 @@ impl<I: Read> SimpleLexer<I> {
     fn parse_other(&mut self) -> LexerResult {
         let ch = self.next().unwrap() as char;
 
-        println!("not handled character at r = {}, c = {}: {}", self.row, self.column, ch);
-
-        Ok(Token::comment(&ch.to_string()))
+        Err(LexerError::UnexpectedChar(ch, vec![]))
     }
 
     fn parse_slash(&mut self) -> LexerResult {
         self.bump();
 
-        match self.peek() {
+        match self.peek_byte() {
             Some(c) => match c {
                 b'*' => self.parse_block_comment(),
                 b'/' => self.parse_line_comment(),
+                b'=' => self.convert_char(Token::Operator(Operators::DivisionEqual)),
                 _ => Ok(Token::Operator(Operators::Division)),
             },
-            None => return Err(LexerError::UnexpectEnd),
+            None => Ok(Token::Operator(Operators::Division)),
         }
     }
 
@@ -278,7 +477,7 @@ impl<I: Read> SimpleLexer<I> {
             match c {
                 b'*' => {
                     buf.push('*');
-                    match self.peek() {
+                    match self.peek_byte() {
                         Some(b'/') => {
                             buf.push('/');
                             return self.convert_char(Token::Comment(buf));
@@ -290,17 +489,18 @@ impl<I: Read> SimpleLexer<I> {
             }
         }
 
-        Err(LexerError::UnexpectEnd)
+        Err(LexerError::UnterminatedComment)
     }
 
     fn parse_line_comment(&mut self) -> LexerResult {
         let mut buf = "/".to_owned();
         while let Some(ch) = self.next() {
-            if ch == b'\n' {
-                break;
+            match ch {
+                b'\n' => break,
+                // CRLF line ending
+                b'\r' if self.peek_byte() == Some(b'\n') => continue,
+                _ => buf.push(ch as char),
             }
-
-            buf.push(ch as char);
         }
 
         return Ok(Token::Comment(buf));
@@ -313,11 +513,13 @@ impl<I: Read> SimpleLexer<I> {
     fn next(&mut self) -> Option<u8> {
         match self.peeker.next() {
             Some(Ok(b'\n')) => {
+                self.offset += 1;
                 self.row += 1;
                 self.column = 0;
                 Some(b'\n')
             }
             Some(Ok(ch)) => {
+                self.offset += 1;
                 self.column += 1;
                 Some(ch)
             },
@@ -325,7 +527,7 @@ impl<I: Read> SimpleLexer<I> {
         }
     }
 
-    fn peek(&mut self) -> Option<u8> {
+    fn peek_byte(&mut self) -> Option<u8> {
         match self.peeker.peek() {
             Some(&Ok(ch)) => Some(ch),
             _ => None,
@@ -345,48 +547,207 @@ mod test {
 
         let mut lexer = SimpleLexer::new(source.as_bytes());
         assert_eq!(
-            Iterator::next(&mut lexer).unwrap(),
-            Token::KeyWord(KeyWords::If)
+            Iterator::next(&mut lexer),
+            Some(Ok(Token::KeyWord(KeyWords::If)))
         );
         assert_eq!(
-            Iterator::next(&mut lexer).unwrap(),
-            Token::KeyWord(KeyWords::Else)
+            Iterator::next(&mut lexer),
+            Some(Ok(Token::KeyWord(KeyWords::Else)))
         );
         assert_eq!(Iterator::next(&mut lexer), None);
     }
 
+    #[test]
+    fn test_span() {
+        let source = "int a;\n\n  /* c */ return a;";
+
+        let mut lexer = SimpleLexer::new(source.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::KeyWord(KeyWords::Int))));
+        assert_eq!(lexer.span(), Span { line: 1, column: 1, offset: 0 });
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("a"))));
+        assert_eq!(lexer.span(), Span { line: 1, column: 5, offset: 4 });
+
+        let _ = Iterator::next(&mut lexer);
+        let _ = Iterator::next(&mut lexer);
+        assert_eq!(lexer.span(), Span { line: 3, column: 3, offset: 10 });
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::KeyWord(KeyWords::Return))));
+        assert_eq!(lexer.span(), Span { line: 3, column: 11, offset: 18 });
+    }
+
+    #[test]
+    fn test_tokens() {
+        let tokens: Vec<Token> = SimpleLexer::new("int a = 1;".as_bytes()).tokens().collect();
+
+        assert_eq!(tokens, vec![Token::KeyWord(KeyWords::Int),
+                                Token::ident("a"),
+                                Token::Operator(Operators::Assign),
+                                Token::Number(Numbers::SignedInt(1)),
+                                Token::Semicolon]);
+
+        let mut tokens = SimpleLexer::new("a @".as_bytes()).tokens();
+        assert_eq!(tokens.next(), Some(Token::ident("a")));
+        assert_eq!(tokens.next(), None);
+        assert_eq!(tokens.error(), Some(&LexerError::UnexpectedChar('@', vec![])));
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut lexer = SimpleLexer::new("int a;\nb".as_bytes());
+
+        assert_eq!(lexer.peek(1), Some(&Token::ident("a")));
+        assert_eq!(lexer.peek(0), Some(&Token::KeyWord(KeyWords::Int)));
+        assert_eq!(lexer.peek(0), Some(&Token::KeyWord(KeyWords::Int)));
+        assert_eq!(lexer.peek(4), None);
+        assert_eq!(lexer.span().column, 1);
+
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::KeyWord(KeyWords::Int))));
+        assert_eq!(lexer.span().column, 1);
+        assert_eq!(lexer.peek(0), Some(&Token::ident("a")));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("a"))));
+        assert_eq!(lexer.span().column, 5);
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Semicolon)));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("b"))));
+        assert_eq!(lexer.span().line, 2);
+        assert_eq!(Iterator::next(&mut lexer), None);
+    }
+
     #[test]
     fn test_division() {
         let source = "2/3";
 
         let mut lexer = SimpleLexer::new(source.as_bytes());
         assert_eq!(
-            Iterator::next(&mut lexer).unwrap(),
-            Token::Number(Numbers::from_str("2"))
+            Iterator::next(&mut lexer),
+            Some(Ok(Token::Number(Numbers::from_str("2"))))
         );
         assert_eq!(
-            Iterator::next(&mut lexer).unwrap(),
-            Token::Operator(Operators::Division)
+            Iterator::next(&mut lexer),
+            Some(Ok(Token::Operator(Operators::Division)))
         );
         assert_eq!(
-            Iterator::next(&mut lexer).unwrap(),
-            Token::Number(Numbers::from_str("3"))
+            Iterator::next(&mut lexer),
+            Some(Ok(Token::Number(Numbers::from_str("3"))))
         );
         assert_eq!(Iterator::next(&mut lexer), None);
     }
 
+    #[test]
+    fn test_float_number() {
+        let source = "3.14 2.0f 1e9 42 1.5e-3";
+
+        let mut lexer = SimpleLexer::new(source.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::Double(3.14)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::Float(2.0)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::Double(1e9)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(42)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::Double(1.5e-3)))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+    }
+
+    #[test]
+    fn test_hex_number() {
+        let source = "0xFF 0xff 0XaB 0x0 0";
+
+        let mut lexer = SimpleLexer::new(source.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(255)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(255)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(171)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(0)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(0)))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+
+        let mut lexer = SimpleLexer::new("0x;".as_bytes());
+        assert!(matches!(lexer.parse(), Err(LexerError::InvalidNumber(_))));
+    }
+
+    #[test]
+    fn test_octal_binary_number() {
+        let source = "0755 0b1010 0B11 0 0.5";
+
+        let mut lexer = SimpleLexer::new(source.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(493)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(10)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(3)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(0)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::Double(0.5)))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+
+        let mut lexer = SimpleLexer::new("0b".as_bytes());
+        assert!(matches!(lexer.parse(), Err(LexerError::InvalidNumber(_))));
+    }
+
     #[test]
     fn test_cmp_op() {
         let source = "> >= < <= == !=";
         let s = source.clone();
 
         let mut lexer = SimpleLexer::new(s.as_bytes());
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Operator(Operators::Greater));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Operator(Operators::GreaterEqual));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Operator(Operators::Less));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Operator(Operators::LessEqual));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Operator(Operators::Equal));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Operator(Operators::NotEqual));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Greater))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::GreaterEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Less))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::LessEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Equal))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::NotEqual))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+    }
+
+    #[test]
+    fn test_mod_shift_op() {
+        let source = "a % b x << 3 y >> 1 < <";
+
+        let mut lexer = SimpleLexer::new(source.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("a"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Mod))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("b"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("x"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::ShiftLeft))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(3)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("y"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::ShiftRight))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(1)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Less))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Less))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+    }
+
+    #[test]
+    fn test_compound_assign_op() {
+        let source = "+= -= *= /= %= <<= >>= &= |= ^= * = << =";
+
+        let mut lexer = SimpleLexer::new(source.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::AddEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::MinusEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::MulEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::DivisionEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::ModEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::ShiftLeftEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::ShiftRightEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::AndEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::OrEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::XorEqual))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Asterisk)));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Assign))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::ShiftLeft))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Assign))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+
+        let lexer = SimpleLexer::new("a *= 2".as_bytes());
+        let tokens: Vec<_> = lexer.map(|x| x.unwrap()).collect();
+        assert_eq!(tokens, vec![Token::ident("a"),
+                                Token::Operator(Operators::MulEqual),
+                                Token::Number(Numbers::SignedInt(2))]);
+    }
+
+    #[test]
+    fn test_conditional_op() {
+        let source = "x ? 1 : 0";
+
+        let mut lexer = SimpleLexer::new(source.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("x"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Question)));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(1)))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Colon)));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Number(Numbers::SignedInt(0)))));
         assert_eq!(Iterator::next(&mut lexer), None);
     }
 
@@ -396,7 +757,44 @@ mod test {
         let s = source.clone();
 
         let mut lexer = SimpleLexer::new(s.as_bytes());
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Comment(source.to_owned()));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Comment(source.to_owned()))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+    }
+
+    #[test]
+    fn test_block_comment() {
+        let source = "a /* first line\n * second line\n a * b **/ b";
+
+        let mut lexer = SimpleLexer::new(source.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("a"))));
+        assert_eq!(Iterator::next(&mut lexer),
+                   Some(Ok(Token::comment("/* first line\n * second line\n a * b **/"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("b"))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+
+        let mut lexer = SimpleLexer::new("/* never closed *".as_bytes());
+        assert!(matches!(lexer.parse(), Err(LexerError::UnterminatedComment)));
+    }
+
+    #[test]
+    fn test_line_comment() {
+        let source = "a = b / c; // trailing\r\n// own line\nd";
+
+        let mut lexer = SimpleLexer::new(source.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("a"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Assign))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("b"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Division))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("c"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Semicolon)));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::comment("// trailing"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::comment("// own line"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("d"))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+
+        let mut lexer = SimpleLexer::new("a /".as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("a"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Division))));
         assert_eq!(Iterator::next(&mut lexer), None);
     }
 
@@ -406,15 +804,75 @@ mod test {
         let dst = "\"this is literal \"String\".\"".to_owned();
 
         let mut lexer = SimpleLexer::new(src.as_bytes());
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::LiteralStr(dst));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::LiteralStr(dst))));
         assert_eq!(Iterator::next(&mut lexer), None);
 
         let src = r#""with escape \n character \\""#;
         let dst = "\"with escape \n character \\\"".to_owned();
 
         let mut lexer = SimpleLexer::new(src.as_bytes());
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::LiteralStr(dst));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::LiteralStr(dst))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+    }
+
+    #[test]
+    fn test_literal_str_escape() {
+        let src = r#""a\tb\r\n\0""#;
+
+        let mut lexer = SimpleLexer::new(src.as_bytes());
+        match Iterator::next(&mut lexer).unwrap().unwrap() {
+            Token::LiteralStr(s) => {
+                assert_eq!(s.len(), 8);
+                assert_eq!(s.as_bytes(), b"\"a\tb\r\n\0\"");
+            },
+            _ => panic!(),
+        }
+
+        let src = r#""\x41\x4a\x7\"\'""#;
+
+        let mut lexer = SimpleLexer::new(src.as_bytes());
+        match Iterator::next(&mut lexer).unwrap().unwrap() {
+            Token::LiteralStr(s) => {
+                assert_eq!(s.len(), 7);
+                assert_eq!(s.as_bytes(), b"\"AJ\x07\"'\"");
+            },
+            _ => panic!(),
+        }
+
+        let mut lexer = SimpleLexer::new(r#""abc"#.as_bytes());
+        assert!(matches!(lexer.parse(), Err(LexerError::UnterminatedString)));
+
+        let mut lexer = SimpleLexer::new(r#""\q""#.as_bytes());
+        assert!(matches!(lexer.parse(), Err(LexerError::UnexpectedChar('q', _))));
+
+        let mut lexer = SimpleLexer::new(r#""\x7f""#.as_bytes());
+        assert_eq!(lexer.parse(), Ok(Token::LiteralStr("\"\x7f\"".to_owned())));
+
+        let mut lexer = SimpleLexer::new(r#""\xff""#.as_bytes());
+        assert_eq!(lexer.parse(), Err(LexerError::EscapeOutOfRange(0xff)));
+
+        let mut lexer = SimpleLexer::new(r#""a\x80b""#.as_bytes());
+        assert_eq!(lexer.parse(), Err(LexerError::EscapeOutOfRange(0x80)));
+    }
+
+    #[test]
+    fn test_literal_char() {
+        let src = r"'a' '\n' '\t' '\0' '\\' '\''";
+
+        let mut lexer = SimpleLexer::new(src.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::CharLiteral('a'))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::CharLiteral('\n'))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::CharLiteral('\t'))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::CharLiteral('\0'))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::CharLiteral('\\'))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::CharLiteral('\''))));
         assert_eq!(Iterator::next(&mut lexer), None);
+
+        let mut lexer = SimpleLexer::new("'a".as_bytes());
+        assert!(matches!(lexer.parse(), Err(LexerError::UnexpectEnd)));
+
+        let mut lexer = SimpleLexer::new("'ab'".as_bytes());
+        assert!(matches!(lexer.parse(), Err(LexerError::UnexpectedChar('b', _))));
     }
 
     #[test]
@@ -429,19 +887,19 @@ mod test {
     ";
 
         let mut lexer = SimpleLexer::new(src.as_bytes());
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::KeyWord(KeyWords::Struct));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Bracket(Brackets::LeftCurlyBracket));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Comment("/* field a */".to_owned()));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::KeyWord(KeyWords::Int));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Identifier("a_".to_owned(), Type::NoType));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Semicolon);
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Comment("// field b".to_owned()));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::KeyWord(KeyWords::Unsigned));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::KeyWord(KeyWords::Int));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Identifier("b0".to_owned(), Type::NoType));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Semicolon);
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Bracket(Brackets::RightCurlyBracket));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Semicolon);
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::KeyWord(KeyWords::Struct))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Bracket(Brackets::LeftCurlyBracket))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Comment("/* field a */".to_owned()))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::KeyWord(KeyWords::Int))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Identifier("a_".to_owned(), Type::NoType))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Semicolon)));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Comment("// field b".to_owned()))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::KeyWord(KeyWords::Unsigned))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::KeyWord(KeyWords::Int))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Identifier("b0".to_owned(), Type::NoType))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Semicolon)));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Bracket(Brackets::RightCurlyBracket))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Semicolon)));
         assert_eq!(Iterator::next(&mut lexer), None);
     }
 
@@ -453,23 +911,37 @@ mod test {
     ";
 
         let mut lexer = SimpleLexer::new(src.as_bytes());
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Identifier("point".to_owned(), Type::NoType));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Arrow);
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Identifier("x".to_owned(), Type::NoType));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Operator(Operators::DoubleMinus));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Semicolon);
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Operator(Operators::DoubleMinus));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Identifier("i".to_owned(), Type::NoType));
-        assert_eq!(Iterator::next(&mut lexer).unwrap(), Token::Semicolon);
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Identifier("point".to_owned(), Type::NoType))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Arrow)));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Identifier("x".to_owned(), Type::NoType))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::DoubleMinus))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Semicolon)));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::DoubleMinus))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Identifier("i".to_owned(), Type::NoType))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Semicolon)));
         assert_eq!(Iterator::next(&mut lexer), None);
     }
 
     #[test]
-    #[should_panic]
-    fn test_lexer_panic() {
+    fn test_lexer_error() {
         let src = "/*asd";
 
         let lexer = SimpleLexer::new(src.as_bytes());
-        let _ = lexer.count();
+        assert_eq!(lexer.last(), Some(Err(LexerError::UnterminatedComment)));
+
+        let src = "int a = @;";
+
+        let mut lexer = SimpleLexer::new(src.as_bytes());
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::KeyWord(KeyWords::Int))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::ident("a"))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Ok(Token::Operator(Operators::Assign))));
+        assert_eq!(Iterator::next(&mut lexer), Some(Err(LexerError::UnexpectedChar('@', vec![]))));
+        assert_eq!(Iterator::next(&mut lexer), None);
+
+        let lexer = SimpleLexer::new("a = 09;".as_bytes());
+        assert_eq!(lexer.last(), Some(Err(LexerError::InvalidNumber("09".to_owned()))));
+
+        let lexer = SimpleLexer::new("a = \"abc".as_bytes());
+        assert_eq!(lexer.last(), Some(Err(LexerError::UnterminatedString)));
     }
 }
\ No newline at end of file
@@ -0,0 +1,66 @@
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Index of a name in an `Interner`, equal names have equal ids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NameId(u32);
+
+/// Stores every distinct name once, so tables keyed by names hash and compare
+/// integers, and a name seen before costs no allocation.
+pub struct Interner {
+    ids: HashMap<Rc<str>, NameId>,
+    names: Vec<Rc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Interner {
+            ids: HashMap::new(),
+            names: vec![],
+        }
+    }
+
+    /// Id of `name`, which is stored if it's new.
+    pub fn intern(&mut self, name: &str) -> NameId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+
+        let id = NameId(self.names.len() as u32);
+        let name: Rc<str> = Rc::from(name);
+        self.names.push(name.clone());
+        self.ids.insert(name, id);
+        id
+    }
+
+    /// Id of `name` if it's been interned, a name which hasn't can't be in any
+    /// table.
+    pub fn get(&self, name: &str) -> Option<NameId> {
+        self.ids.get(name).cloned()
+    }
+
+    pub fn resolve(&self, id: NameId) -> &str {
+        &self.names[id.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use parser::interner::*;
+
+    #[test]
+    fn test_intern() {
+        let mut names = Interner::new();
+        let a = names.intern("a");
+        let b = names.intern("b");
+
+        assert!(a != b);
+        assert_eq!(names.intern("a"), a);
+        assert_eq!(names.get("b"), Some(b));
+        assert_eq!(names.get("c"), None);
+        assert_eq!(names.resolve(a), "a");
+        assert_eq!(names.resolve(b), "b");
+    }
+}
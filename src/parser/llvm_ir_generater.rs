@@ -1,4 +1,5 @@
 
+use parser::{CodegenError, CodegenResult};
 use parser::syntax_node::SyntaxTree;
 use parser::syntax_node::*;
 use parser::symbol_manager::*;
@@ -8,18 +9,25 @@ use token::Operators;
 use token::Numbers;
 
 use id_tree::*;
-use inkwell::IntPredicate;
-use inkwell::support::LLVMString;
+use inkwell::AddressSpace;
+use inkwell::{FloatPredicate, IntPredicate};
 use inkwell::OptimizationLevel;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
-use inkwell::execution_engine::{ExecutionEngine};
-use inkwell::module::Module;
-use inkwell::types::{BasicTypeEnum, BasicType};
-use inkwell::values::{BasicValue, BasicValueEnum, AnyValue, AnyValueEnum, FunctionValue, PointerValue};
+use inkwell::execution_engine::{ExecutionEngine, Symbol as JitSymbol};
+use inkwell::module::{Module, Linkage};
+use inkwell::passes::{PassManager, PassManagerBuilder};
+use inkwell::basic_block::BasicBlock;
+use inkwell::targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetData, TargetMachine};
+use inkwell::types::{AnyTypeEnum, BasicTypeEnum, BasicType, FloatType, FunctionType, IntType, StructType};
+use inkwell::values::{BasicValue, BasicValueEnum, AnyValue, AnyValueEnum, FloatValue, FunctionValue, IntValue, PointerValue};
 
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::Path;
 
 ///
 /// # JIT Examples.
@@ -57,7 +65,7 @@ use std::cell::RefCell;
 ///
 /// let ee = generater.execution_engine().unwrap();
 ///
-/// let f: Symbol<unsafe extern "C" fn(i64, i64) -> i64> = unsafe {
+/// let f: Symbol<unsafe extern "C" fn(i32, i32) -> i32> = unsafe {
 ///     ee.get_function("f").unwrap()
 /// };
 ///
@@ -70,20 +78,6 @@ use std::cell::RefCell;
 /// ```
 ///
 
-impl SymbolManager<AnyValueEnum, String> {
-    fn current_function(&self) -> FunctionValue {
-        for table in self.symbols().iter().rev() {
-            for (_, value) in table {
-                if value.is_function_value() {
-                    return value.into_function_value();
-                }
-            }
-        }
-
-        unimplemented!()
-    }
-}
-
 fn any_value_into_basic_value(any_value: AnyValueEnum) -> Option<BasicValueEnum> {
     match any_value {
         AnyValueEnum::ArrayValue(v) => Some(v.into()),
@@ -96,19 +90,118 @@ fn any_value_into_basic_value(any_value: AnyValueEnum) -> Option<BasicValueEnum>
     }
 }
 
+fn any_type_into_basic_type(any_type: AnyTypeEnum) -> Option<BasicTypeEnum> {
+    match any_type {
+        AnyTypeEnum::ArrayType(t) => Some(t.into()),
+        AnyTypeEnum::FloatType(t) => Some(t.into()),
+        AnyTypeEnum::IntType(t) => Some(t.into()),
+        AnyTypeEnum::PointerType(t) => Some(t.into()),
+        AnyTypeEnum::StructType(t) => Some(t.into()),
+        AnyTypeEnum::VectorType(t) => Some(t.into()),
+        _ => None,
+    }
+}
+
+// whether the usual arithmetic conversions make a pair of integers unsigned.
+// both are promoted to `int` first, which turns a narrower unsigned one signed,
+// then the wider one wins and unsigned wins a tie.
+fn int_unsigned(lhs_bits: u32, lhs_unsigned: bool, rhs_bits: u32, rhs_unsigned: bool) -> bool {
+    let lhs_unsigned = lhs_unsigned && lhs_bits >= 32;
+    let rhs_unsigned = rhs_unsigned && rhs_bits >= 32;
+
+    match lhs_bits.max(32).cmp(&rhs_bits.max(32)) {
+        Ordering::Greater => lhs_unsigned,
+        Ordering::Less => rhs_unsigned,
+        Ordering::Equal => lhs_unsigned || rhs_unsigned,
+    }
+}
+
+// a shift has the type of its promoted left operand, the other operations the
+// common type of both.
+fn int_result_unsigned(op: &Token, lhs_bits: u32, lhs_unsigned: bool, rhs_bits: u32, rhs_unsigned: bool) -> bool {
+    match *op {
+        Token::Operator(Operators::ShiftLeft) |
+        Token::Operator(Operators::ShiftRight) => lhs_unsigned && lhs_bits >= 32,
+        _ => int_unsigned(lhs_bits, lhs_unsigned, rhs_bits, rhs_unsigned),
+    }
+}
+
+fn is_number(value: &BasicValueEnum) -> bool {
+    match *value {
+        BasicValueEnum::IntValue(_) | BasicValueEnum::FloatValue(_) => true,
+        _ => false,
+    }
+}
+
+// a value in the symbol table. LLVM integers have no sign, so it's kept here:
+// for a variable it's the sign of its type, or of what it points to, for a
+// function the sign of its return type.
+#[derive(Clone, Copy, Debug)]
+struct Symbol {
+    value: AnyValueEnum,
+    unsigned: bool,
+}
+
+// the generater's own context, or one shared with other generaters.
+enum GeneraterContext<'c> {
+    Owned(Context),
+    Shared(&'c Context),
+}
+
+impl<'c> Deref for GeneraterContext<'c> {
+    type Target = Context;
+
+    fn deref(&self) -> &Context {
+        match *self {
+            GeneraterContext::Owned(ref context) => context,
+            GeneraterContext::Shared(context) => context,
+        }
+    }
+}
+
 pub struct LLVMIRGenerater<'t> {
     ast: &'t SyntaxTree,
-    context: Context,
+    context: GeneraterContext<'t>,
     module: Module,
     builder: Builder,
-    symbols: Rc<RefCell<SymbolManager<AnyValueEnum, String>>>,
+    symbols: Rc<RefCell<SymbolManager<Symbol, String>>>,
+    // LLVM type of every struct, the field names are in `symbols`.
+    struct_types: HashMap<String, StructType>,
+    // the type every typedef name stands for, and whether it's unsigned.
+    typedefs: HashMap<String, (BasicTypeEnum, bool)>,
+    current_function: Option<FunctionValue>,
+    // (continue, break) targets of the enclosing loops and switches, innermost
+    // last. a switch continues the loop around it, if there's one.
+    loop_blocks: Vec<(Option<BasicBlock>, BasicBlock)>,
+    // the block of every case of the switches being generated.
+    case_blocks: HashMap<NodeId, BasicBlock>,
+    // the block of every label of the current function, and whether the label
+    // has been seen yet, a `goto` may come first.
+    labels: HashMap<String, (BasicBlock, bool)>,
+    // the engine running the module, created when it's first asked for, and
+    // the optimization level it was created with.
+    engine: RefCell<Option<(ExecutionEngine, OptimizationLevel)>>,
 }
 
 impl<'t> LLVMIRGenerater<'t> {
     pub fn new(ast: &'t SyntaxTree) -> LLVMIRGenerater<'t> {
+        LLVMIRGenerater::with_module_name(ast, "main")
+    }
 
-        let context = Context::create();
-        let module = context.create_module("main");
+    /// Name the module, so modules are told apart when linked together.
+    pub fn with_module_name(ast: &'t SyntaxTree, name: &str) -> LLVMIRGenerater<'t> {
+        LLVMIRGenerater::from_context(ast, GeneraterContext::Owned(Context::create()), name)
+    }
+
+    /// Generate into a context owned by the caller, which may be shared by the
+    /// modules that will be linked together.
+    pub fn with_context(ast: &'t SyntaxTree, context: &'t Context, name: &str) -> LLVMIRGenerater<'t> {
+        LLVMIRGenerater::from_context(ast, GeneraterContext::Shared(context), name)
+    }
+
+    fn from_context(ast: &'t SyntaxTree, context: GeneraterContext<'t>, name: &str) -> LLVMIRGenerater<'t> {
+
+        let module = context.create_module(name);
         let builder = context.create_builder();
 
         LLVMIRGenerater {
@@ -117,6 +210,13 @@ impl<'t> LLVMIRGenerater<'t> {
             module,
             builder,
             symbols: Rc::new(RefCell::new(SymbolManager::new())),
+            struct_types: HashMap::new(),
+            typedefs: HashMap::new(),
+            current_function: None,
+            loop_blocks: vec![],
+            labels: HashMap::new(),
+            case_blocks: HashMap::new(),
+            engine: RefCell::new(None),
         }
     }
 
@@ -124,95 +224,696 @@ impl<'t> LLVMIRGenerater<'t> {
         self.module.print_to_stderr();
     }
 
-    pub fn execution_engine(&self) -> Result<ExecutionEngine, LLVMString> {
-        self.module.create_jit_execution_engine(OptimizationLevel::None)
+    /// Textual LLVM IR of the module.
+    pub fn ir_string(&self) -> String {
+        self.module.print_to_string().to_string()
     }
 
-    pub fn ir_gen(&mut self) -> Result<(), ()> {
+    /// The module generated into, e.g. to run passes of one's own on it.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
 
-        let ids = self.children_ids(self.ast.root_node_id().unwrap());
-        for id in ids {
-            self.dispatch_node(&id);
+    pub fn execution_engine(&self) -> CodegenResult<Ref<ExecutionEngine>> {
+        self.execution_engine_with_opt(OptimizationLevel::None)
+    }
+
+    /// The JIT engine running the module. It's created by the first call, the
+    /// next ones return the same engine, so they have to ask for the same `level`.
+    pub fn execution_engine_with_opt(&self, level: OptimizationLevel) -> CodegenResult<Ref<ExecutionEngine>> {
+        if let Some((_, created)) = *self.engine.borrow() {
+            if created != level {
+                return Err(CodegenError::EngineOptLevel(created));
+            }
+        }
+
+        if self.engine.borrow().is_none() {
+            let ee = self.module.create_jit_execution_engine(level)
+                .map_err(|e| CodegenError::Engine(e.to_string()))?;
+            *self.engine.borrow_mut() = Some((ee, level));
+        }
+
+        Ok(Ref::map(self.engine.borrow(), |x| &x.as_ref().unwrap().0))
+    }
+
+    /// JIT compiled function `name`, `None` if there's no such function or the
+    /// engine can't be created.
+    ///
+    /// It's unsafe as `F` has to match the function's signature.
+    pub unsafe fn jit_function<F>(&self, name: &str) -> Option<JitSymbol<F>> {
+        self.execution_engine().ok()?.get_function(name).ok()
+    }
+
+    /// Run the standard cleanup passes over the module, after `ir_gen` and before
+    /// JIT or emission. Returns whether the module changed.
+    pub fn optimize(&self, level: OptimizationLevel) -> bool {
+        let pass_manager = PassManager::create_for_module();
+
+        // variables are allocas, promote them first so the rest can see the values.
+        pass_manager.add_promote_memory_to_register_pass();
+        pass_manager.add_instruction_combining_pass();
+        pass_manager.add_reassociate_pass();
+        pass_manager.add_gvn_pass();
+        pass_manager.add_cfg_simplification_pass();
+
+        let builder = PassManagerBuilder::create();
+        builder.set_optimization_level(Some(&level));
+        builder.populate_module_pass_manager(&pass_manager);
+
+        pass_manager.run_on_module(&self.module)
+    }
+
+    /// Compile the module to a relocatable object file. When `target_triple` is
+    /// `None` it's for the module's triple, see `set_target_triple`, or else for
+    /// the native target.
+    pub fn write_object_file<P: AsRef<Path>>(&self, path: P, target_triple: Option<&str>) -> CodegenResult<()> {
+        let machine = self.target_machine(target_triple)?;
+
+        machine.write_to_file(&self.module, FileType::Object, path.as_ref())
+            .map_err(|e| CodegenError::Target(e.to_string()))
+    }
+
+    /// Link the module of `other`, generated in the same context (see
+    /// `with_context`), into this one, so calls between the two resolve. It's
+    /// done before the engine is created, the engine wouldn't see the linked
+    /// code. A function or a global defined in both modules is a DuplicateSymbol.
+    pub fn link(&self, other: LLVMIRGenerater) -> CodegenResult<()> {
+        if *self.context != *other.context {
+            return Err(CodegenError::Link("modules of different contexts".to_owned()));
+        }
+
+        if self.engine.borrow().is_some() {
+            return Err(CodegenError::Link("the execution engine was already created".to_owned()));
+        }
+
+        let mut function = other.module.get_first_function();
+        while let Some(f) = function {
+            let name = f.get_name().to_string_lossy().into_owned();
+            let defined = self.module.get_function(&name).map_or(false, |x| x.count_basic_blocks() > 0);
+            if defined && f.count_basic_blocks() > 0 {
+                return Err(CodegenError::DuplicateSymbol(name));
+            }
+
+            function = f.get_next_function();
         }
 
-        self.module.verify().unwrap();
+        // string literals are private globals, the linker renames them.
+        let mut global = other.module.get_first_global();
+        while let Some(g) = global {
+            let name = g.get_name().to_string_lossy().into_owned();
+            let defined = self.module.get_global(&name).map_or(false, |x| x.get_linkage() == Linkage::External);
+            if defined && g.get_linkage() == Linkage::External {
+                return Err(CodegenError::DuplicateSymbol(name));
+            }
+
+            global = g.get_next_global();
+        }
+
+        self.module.link_in_module(other.module).map_err(|e| CodegenError::Link(e.to_string()))
+    }
+
+    /// Write the module as LLVM bitcode, for other LLVM tools to read.
+    pub fn write_bitcode<P: AsRef<Path>>(&self, path: P) -> CodegenResult<()> {
+        let path = path.as_ref();
+        if !self.module.write_bitcode_to_path(path) {
+            return Err(CodegenError::WriteFile(path.display().to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Generate for `triple` instead of the native target, e.g. to cross compile.
+    /// The module takes the triple and the target's data layout, which gives the
+    /// type sizes, so it's set before `ir_gen`.
+    pub fn set_target_triple(&self, triple: &str) -> CodegenResult<()> {
+        let machine = self.target_machine(Some(triple))?;
+
+        self.module.set_triple(triple);
+        self.module.set_data_layout(&machine.get_target_data().get_data_layout());
+        Ok(())
+    }
+
+    fn target_machine(&self, target_triple: Option<&str>) -> CodegenResult<TargetMachine> {
+        let config = InitializationConfig::default();
+        let module_triple = self.module.get_triple().to_string();
+        let triple = match target_triple {
+            Some(triple) => {
+                Target::initialize_all(&config);
+                triple.to_owned()
+            },
+            None if !module_triple.is_empty() => {
+                Target::initialize_all(&config);
+                module_triple
+            },
+            None => {
+                Target::initialize_native(&config).map_err(CodegenError::Target)?;
+                TargetMachine::get_default_triple().to_string()
+            },
+        };
+
+        let target = Target::from_triple(&triple)
+            .map_err(|e| CodegenError::Target(e.to_string()))?;
+        target.create_target_machine(&triple,
+                                     "generic",
+                                     "",
+                                     OptimizationLevel::Default,
+                                     RelocMode::PIC,
+                                     CodeModel::Default)
+            .ok_or_else(|| CodegenError::Target(format!("no target machine for `{}`", triple)))
+    }
+
+    /// Declare a function defined outside of the source, e.g. `printf` from libc,
+    /// so calls to it resolve. `ret` is `None` for `void` functions.
+    pub fn declare_external(&mut self,
+                            name: &str,
+                            arg_types: &[BasicTypeEnum],
+                            ret: Option<BasicTypeEnum>,
+                            is_var_args: bool) -> CodegenResult<FunctionValue> {
+
+        let arguments: Vec<&BasicType> = arg_types.iter().map(|x| x as &BasicType).collect();
+        let fn_type = match ret {
+            Some(t) => t.fn_type(&arguments[..], is_var_args),
+            None => self.context.void_type().fn_type(&arguments[..], is_var_args),
+        };
+
+        let function = self.module.add_function(name, &fn_type, Some(&Linkage::External));
+        self.push_identifier(name, function.into(), false)?;
+
+        Ok(function)
+    }
+
+    pub fn ir_gen(&mut self) -> CodegenResult<()> {
+        let root = self.ast.root_node_id().unwrap();
+
+        // structs, globals and function signatures first, so they're visible to
+        // the functions defined before them. the bodies follow in a second pass.
+        for id in self.children(root) {
+            match *self.data(id) {
+                SyntaxType::StructDefine => self.struct_define(id)?,
+                SyntaxType::EnumDefine => self.enum_define(id)?,
+                SyntaxType::TypeDefine => self.type_define(id)?,
+                SyntaxType::VariableDefine => self.global_define(id)?,
+                SyntaxType::FuncDefine |
+                SyntaxType::FuncDeclare => { self.function_value(id, &self.func_params(id)?)?; },
+                _ => {},
+            }
+        }
+
+        for id in self.children(root) {
+            match *self.data(id) {
+                SyntaxType::StructDefine | SyntaxType::EnumDefine | SyntaxType::TypeDefine |
+                SyntaxType::VariableDefine | SyntaxType::FuncDeclare => {},
+                _ => self.dispatch_node(id)?,
+            }
+        }
+
+        self.module.verify().map_err(|e| CodegenError::Verify(e.to_string()))?;
 
         Ok(())
     }
 
-    fn dispatch_node(&mut self, id: &NodeId) {
+    fn dispatch_node(&mut self, id: &NodeId) -> CodegenResult<()> {
         info!("DISPATCH {:?}", self.data(&id));
 
         match self.data(id) {
             &SyntaxType::FuncDefine => self.function_gen(id),
+            &SyntaxType::FuncDeclare => self.function_value(id, &self.func_params(id)?).map(|_| ()),
             &SyntaxType::ReturnStmt => self.return_stmt_gen(id),
             &SyntaxType::IfStmt => self.if_stmt_gen(id),
             &SyntaxType::VariableDefine => self.variable_define(id),
-            &SyntaxType::AssignStmt => self.assign_stmt(id),
-            _ => unimplemented!(),
+            &SyntaxType::AssignStmt => self.assign_stmt(id).map(|_| ()),
+            &SyntaxType::StmtBlock => self.stmt_block_gen(id),
+            &SyntaxType::EmptyStmt => Ok(()),
+            &SyntaxType::WhileLoop => self.while_loop_gen(id),
+            &SyntaxType::ForLoop => self.for_loop_gen(id),
+            &SyntaxType::DoWhileStmt => self.do_while_stmt_gen(id),
+            &SyntaxType::BreakStmt => self.break_stmt_gen(),
+            &SyntaxType::ContinueStmt => self.continue_stmt_gen(),
+            &SyntaxType::LabeledStmt => self.labeled_stmt_gen(id),
+            &SyntaxType::GotoStmt => self.goto_stmt_gen(id),
+            &SyntaxType::SwitchStmt => self.switch_stmt_gen(id),
+            &SyntaxType::CaseStmt |
+            &SyntaxType::DefaultStmt => self.case_stmt_gen(id),
+            &SyntaxType::FuncCall => self.func_call_gen(id).map(|_| ()),
+            &SyntaxType::PrefixIncDec |
+            &SyntaxType::PostfixIncDec => self.inc_dec_gen(id).map(|_| ()),
+            node @ _ => Err(CodegenError::Unsupported(node.clone())),
         }
     }
 
-    fn assign_stmt(&mut self, id: &NodeId) {
+    // an assignment is an expression, its value is the stored one.
+    fn assign_stmt(&self, id: &NodeId) -> CodegenResult<AnyValueEnum> {
+        let ids = self.children_ids(id);
+        let ptr = self.llvm_value(&ids[0])?.into_pointer_value();
+        let val = self.basic_value(&ids[1])?;
+        let val = self.convert_to_pointee(val, self.is_unsigned(&ids[1]), ptr);
+
+        self.builder.build_store(&ptr, &val);
+        Ok(val.into())
+    }
+
+    // `++a` is the value after the increment and `a++` the one before, both
+    // store the incremented value. a pointer steps by one element.
+    fn inc_dec_gen(&self, id: &NodeId) -> CodegenResult<AnyValueEnum> {
         let ids = self.children_ids(id);
-        let ptr = self.llvm_value(&ids[0]);
-        let val = self.llvm_value(&ids[1]);
+        let prefix = match self.data(id) {
+            &SyntaxType::PrefixIncDec => true,
+            _ => false,
+        };
+        let (op, operand) = if prefix { (&ids[0], &ids[1]) } else { (&ids[1], &ids[0]) };
+        let increment = *self.token(op).unwrap() == Token::Operator(Operators::DoubleAdd);
 
-        self.builder.build_store(&ptr.into_pointer_value(), &any_value_into_basic_value(val).unwrap());
+        let ptr = self.llvm_value(operand)?.into_pointer_value();
+        let old = self.dereference_ptr(ptr);
+        let new: BasicValueEnum = match old {
+            BasicValueEnum::IntValue(v) => {
+                let one = v.get_type().const_int(1, false);
+                match increment {
+                    true => self.builder.build_int_add(v, one, "inc").into(),
+                    false => self.builder.build_int_sub(v, one, "dec").into(),
+                }
+            },
+            BasicValueEnum::FloatValue(v) => {
+                let one = v.get_type().const_float(1.0);
+                match increment {
+                    true => self.builder.build_float_add(v, one, "inc").into(),
+                    false => self.builder.build_float_sub(v, one, "dec").into(),
+                }
+            },
+            BasicValueEnum::PointerValue(p) => {
+                let step = self.context.i64_type().const_int(if increment { 1 } else { -1i64 as u64 }, true);
+                self.builder.build_gep(&p, &[step], "step").into()
+            },
+            _ => return Err(CodegenError::Unsupported(self.data(id).clone())),
+        };
+
+        self.builder.build_store(&ptr, &new);
+        Ok(if prefix { new } else { old }.into())
     }
 
-    fn variable_define(&mut self, id: &NodeId) {
+    fn variable_define(&mut self, id: &NodeId) -> CodegenResult<()> {
 
         let ids = self.children_ids(id);
-        let var_type = self.llvm_basic_type(&ids[0]);
+        let var_type = self.llvm_basic_type(&ids[0])?;
+        let unsigned = self.is_unsigned_type(&ids[0]);
 
         for var in ids.iter().skip(1) {
-            let name = &self.ident_name(var).unwrap();
-            let ptr = self.builder.build_alloca(var_type, name);
+            let (ident, init, ty) = self.declarator(var, var_type)?;
+
+            let name = &self.ident_name(&ident).unwrap();
+            let ptr = self.builder.build_alloca(ty, name);
 
             // store symbol
-            self.push_identifier(name, ptr.into());
+            self.push_identifier(name, ptr.into(), unsigned)?;
+
+            if let Some(init) = init {
+                let value = self.basic_value(&init)?;
+                self.builder.build_store(&ptr, &self.convert(value, self.is_unsigned(&init), ty));
+            }
         }
+
+        Ok(())
     }
 
-    fn function_gen(&mut self, node: &NodeId) {
+    // globals live in the outermost scope, a function loads and stores them like locals.
+    fn global_define(&mut self, id: &NodeId) -> CodegenResult<()> {
 
-        let ids = self.children_ids(node);
-        let fn_name = self.ident_name(&ids[1]).unwrap();
+        let ids = self.children_ids(id);
+        let var_type = self.llvm_basic_type(&ids[0])?;
+        let unsigned = self.is_unsigned_type(&ids[0]);
+
+        for var in ids.iter().skip(1) {
+            let (ident, init, ty) = self.declarator(var, var_type)?;
+            if let BasicTypeEnum::ArrayType(_) = ty {
+                return Err(CodegenError::Unsupported(SyntaxType::ArrayDeclare));
+            }
+
+            let name = &self.ident_name(&ident).unwrap();
+            let global = self.module.add_global(&ty, None, name);
+            global.set_initializer(&self.global_initializer(ty, init.as_ref())?);
+
+            self.push_identifier(name, global.as_pointer_value().into(), unsigned)?;
+        }
+
+        Ok(())
+    }
 
-        let mut args_type = vec![];
-        let mut args_name = vec![];
-        for id in ids.iter().skip(2) {
+    // a named struct type, its field names go to the symbol manager. an anonymous
+    // struct can't be referred to, so there's nothing to generate for it.
+    fn struct_define(&mut self, id: &NodeId) -> CodegenResult<()> {
+        let ids = self.children_ids(id);
+        let name = match ids.first().and_then(|x| self.ident_name(x)) {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+
+        // known before the fields, which may point to the struct itself. a
+        // typedef may have named it already.
+        let struct_type = match self.struct_types.get(&name) {
+            Some(&t) => t,
+            None => self.context.opaque_struct_type(&name),
+        };
+        self.struct_types.insert(name.clone(), struct_type);
+
+        let mut names = vec![];
+        let mut types = vec![];
+        for field in ids.iter().skip(1) {
+            let childs = self.children_ids(field);
+            let field_type = self.llvm_basic_type(&childs[0])?;
+
+            for var in childs.iter().skip(1) {
+                let (ident, _, ty) = self.declarator(var, field_type)?;
+                names.push(self.ident_name(&ident).unwrap());
+                types.push(ty);
+            }
+        }
+
+        let fields: Vec<&BasicType> = types.iter().map(|x| x as &BasicType).collect();
+        struct_type.set_body(&fields[..], false);
+
+        self.symbols.borrow_mut().push_struct(&name, names)
+            .map_err(|_| CodegenError::DuplicateSymbol(name.clone()))
+    }
+
+    // a typedef name stands for the type its declarator gives. `struct S` may be
+    // named before it's defined, it's an opaque struct until then.
+    fn type_define(&mut self, id: &NodeId) -> CodegenResult<()> {
+        let ids = self.children_ids(id);
+        if let Some(name) = self.ident_name(&ids[0]) {
+            if !self.struct_types.contains_key(&name) {
+                let struct_type = self.context.opaque_struct_type(&name);
+                self.struct_types.insert(name, struct_type);
+            }
+        }
+
+        let (ident, _, ty) = self.declarator(&ids[1], self.llvm_basic_type(&ids[0])?)?;
+        let unsigned = self.is_unsigned_type(&ids[0]);
+        self.typedefs.insert(self.ident_name(&ident).unwrap(), (ty, unsigned));
+
+        Ok(())
+    }
+
+    // an enumerator is an `int` constant in the symbol manager, one more than
+    // the previous enumerator unless it's given a value, which must be a literal.
+    fn enum_define(&mut self, id: &NodeId) -> CodegenResult<()> {
+        let mut next = 0;
+
+        for enumerator in self.children(id) {
+            if *self.data(enumerator) != SyntaxType::Enumerator { continue; }
+
+            let childs = self.children_ids(enumerator);
+            if let Some(value) = childs.get(1) {
+                next = match self.number_literal(value) {
+                    Some(Numbers::SignedInt(n)) => n,
+                    _ => return Err(CodegenError::Unsupported(self.data(value).clone())),
+                };
+            }
+
+            let name = self.ident_name(&childs[0]).unwrap();
+            let value = self.context.i32_type().const_int(next as u64, true);
+            self.push_identifier(&name, value.into(), false)?;
+
+            next += 1;
+        }
+
+        Ok(())
+    }
+
+    // `int a = expr` is an AssignStmt holding the identifier and the initializer,
+    // `int a[n]` an ArrayDeclare holding the identifier and the size, and `int *a`
+    // a PointerDeclare holding the rest of the declarator.
+    fn declarator(&self, var: &NodeId, ty: BasicTypeEnum) -> CodegenResult<(NodeId, Option<NodeId>, BasicTypeEnum)> {
+        let childs = self.children_ids(var);
+
+        match self.data(var) {
+            &SyntaxType::AssignStmt => Ok((childs[0].clone(), Some(childs[1].clone()), ty)),
+            &SyntaxType::ArrayDeclare => Ok((childs[0].clone(), None, self.array_type(ty, &childs[1])?)),
+            &SyntaxType::PointerDeclare => self.declarator(&childs[0], ty.ptr_type(AddressSpace::Generic).into()),
+            _ => Ok((var.clone(), None, ty)),
+        }
+    }
+
+    // a global is initialized with a number literal, or zero without an initializer.
+    fn global_initializer(&self, var_type: BasicTypeEnum, init: Option<&NodeId>) -> CodegenResult<BasicValueEnum> {
+        let literal = match init {
+            Some(id) => match self.number_literal(id) {
+                Some(n) => n,
+                None => return Err(CodegenError::Unsupported(self.data(id).clone())),
+            },
+            None => Numbers::SignedInt(0),
+        };
+
+        let value = match (var_type, literal) {
+            (BasicTypeEnum::IntType(t), Numbers::SignedInt(n)) => t.const_int(n as u64, true).into(),
+            (BasicTypeEnum::FloatType(t), Numbers::SignedInt(n)) => t.const_float(n as f64).into(),
+            (BasicTypeEnum::FloatType(t), Numbers::Float(n)) => t.const_float(n as f64).into(),
+            (BasicTypeEnum::FloatType(t), Numbers::Double(n)) => t.const_float(n).into(),
+            (BasicTypeEnum::PointerType(t), Numbers::SignedInt(0)) => t.const_null().into(),
+            _ => return Err(CodegenError::Unsupported(self.data(init.unwrap()).clone())),
+        };
+
+        Ok(value)
+    }
+
+    // `elem[size]`, the size must be a positive number literal.
+    fn array_type(&self, elem: BasicTypeEnum, size: &NodeId) -> CodegenResult<BasicTypeEnum> {
+        match self.number_literal(size) {
+            Some(Numbers::SignedInt(n)) if n > 0 => Ok(elem.array_type(n as u32).into()),
+            _ => Err(CodegenError::Unsupported(self.data(size).clone())),
+        }
+    }
+
+    // pointer to the member, children are the struct, `.` or `->` and the member name.
+    // `s.a` indexes the struct's slot, `p->a` where `p` points.
+    fn member_access_gen(&self, node_id: &NodeId) -> CodegenResult<AnyValueEnum> {
+        let childs = self.children_ids(node_id);
+        let unsupported = || CodegenError::Unsupported(SyntaxType::MemberAccess);
+
+        let ptr = match *self.token(&childs[1]).unwrap() {
+            Token::Arrow => match self.basic_value(&childs[0])? {
+                BasicValueEnum::PointerValue(ptr) => ptr,
+                _ => return Err(unsupported()),
+            },
+            _ => match self.llvm_value(&childs[0])? {
+                AnyValueEnum::PointerValue(ptr) => ptr,
+                _ => return Err(unsupported()),
+            },
+        };
+
+        let struct_type = match ptr.get_type().get_element_type() {
+            AnyTypeEnum::StructType(t) => t,
+            _ => return Err(unsupported()),
+        };
+
+        let name = self.struct_types.iter()
+            .find(|&(_, t)| *t == struct_type)
+            .map(|(name, _)| name)
+            .ok_or_else(unsupported)?;
+
+        let field = self.ident_name(&childs[2]).unwrap();
+        let index = self.symbols.borrow().field_index(name, &field)
+            .ok_or_else(|| CodegenError::UnknownMember(name.clone(), field.clone()))?;
+
+        Ok(self.builder.build_struct_gep(&ptr, index as u32, &field).into())
+    }
+
+    // pointer to the element, children are the array and the index.
+    fn array_index_gen(&self, node_id: &NodeId) -> CodegenResult<AnyValueEnum> {
+        let childs = self.children_ids(node_id);
+
+        if let Some(Numbers::SignedInt(n)) = self.number_literal(&childs[1]) {
+            if n < 0 { return Err(CodegenError::NegativeIndex(n)); }
+        }
+
+        let slot = self.llvm_value(&childs[0])?.into_pointer_value();
+        let index = match self.basic_value(&childs[1])? {
+            BasicValueEnum::IntValue(v) => v,
+            _ => return Err(CodegenError::Unsupported(self.data(&childs[1]).clone())),
+        };
+
+        // a pointer variable is indexed from where it points.
+        if let AnyTypeEnum::PointerType(_) = slot.get_type().get_element_type() {
+            let ptr = self.dereference_ptr(slot).into_pointer_value();
+            return Ok(self.builder.build_gep(&ptr, &[index], "index").into());
+        }
+
+        let zero = self.context.i64_type().const_int(0, false);
+        Ok(self.builder.build_gep(&slot, &[zero, index], "index").into())
+    }
+
+    // a number, or a negated one.
+    fn number_literal(&self, node_id: &NodeId) -> Option<Numbers> {
+        if let Some(tok) = self.token(node_id) {
+            return match *tok {
+                Token::Number(ref n) => Some(n.clone()),
+                _ => None,
+            };
+        }
+
+        let childs = self.children_ids(node_id);
+        if *self.data(node_id) != SyntaxType::Expr || childs.len() != 2 {
+            return None;
+        }
+
+        match self.number_literal(&childs[1]) {
+            Some(Numbers::SignedInt(n)) => Some(Numbers::SignedInt(-n)),
+            Some(Numbers::Float(n)) => Some(Numbers::Float(-n)),
+            Some(Numbers::Double(n)) => Some(Numbers::Double(-n)),
+            None => None,
+        }
+    }
+
+    fn stmt_block_gen(&mut self, id: &NodeId) -> CodegenResult<()> {
+        let __scope_guard = self.scope_guard("");
+        self.stmts_gen(self.children(id))
+    }
+
+    // a terminator ends the block, the statements after it can't run and aren't
+    // generated, e.g. after a `return` or a `break`. unless a `goto` or a switch
+    // may jump to a label in them, they start a block without predecessors then.
+    fn stmts_gen<'a, I: Iterator<Item = &'a NodeId>>(&mut self, ids: I) -> CodegenResult<()> {
+        for id in ids {
+            if self.block_terminated() {
+                if !self.has_label(id) {
+                    warn!("unreachable statement {:?}", self.data(id));
+                    continue;
+                }
+
+                let dead_bb = self.context.append_basic_block(&self.current_function.unwrap(), "unreachable");
+                self.builder.position_at_end(&dead_bb);
+            }
+
+            self.dispatch_node(id)?;
+        }
+
+        Ok(())
+    }
+
+    fn has_label(&self, node_id: &NodeId) -> bool {
+        self.ast.traverse_pre_order(node_id).unwrap().any(|node| match node.data() {
+            &SyntaxType::LabeledStmt |
+            &SyntaxType::CaseStmt |
+            &SyntaxType::DefaultStmt => true,
+            _ => false,
+        })
+    }
+
+    // the block is appended by whichever of the label and its first `goto`
+    // comes first, so a `goto` may jump forward.
+    fn label_block(&mut self, name: &str) -> BasicBlock {
+        if let Some(&(ref bb, _)) = self.labels.get(name) {
+            return bb.clone();
+        }
+
+        let bb = self.context.append_basic_block(&self.current_function.unwrap(), name);
+        self.labels.insert(name.to_owned(), (bb.clone(), false));
+        bb
+    }
+
+    fn labeled_stmt_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
+        let ids = self.children_ids(node_id);
+        let name = self.ident_name(&ids[0]).unwrap();
+
+        let bb = self.label_block(&name);
+        if self.labels[&name].1 {
+            return Err(CodegenError::DuplicateSymbol(name));
+        }
+        self.labels.get_mut(&name).unwrap().1 = true;
+
+        self.enter_block(&bb);
+        self.dispatch_node(&ids[1])
+    }
+
+    // the statement before falls through to a label.
+    fn enter_block(&self, bb: &BasicBlock) {
+        if !self.block_terminated() {
+            self.builder.build_unconditional_branch(bb);
+        }
+
+        self.builder.position_at_end(bb);
+    }
+
+    fn goto_stmt_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
+        let name = self.ident_name(&self.children_ids(node_id)[0]).unwrap();
+        let bb = self.label_block(&name);
+
+        self.builder.build_unconditional_branch(&bb);
+        Ok(())
+    }
+
+    // (name, type, unsigned) of each FuncParam of a prototype or a definition.
+    fn func_params(&self, node: &NodeId) -> CodegenResult<Vec<(String, BasicTypeEnum, bool)>> {
+        let mut params = vec![];
+        for id in self.children(node).skip(2) {
             match self.data(id) {
                 &SyntaxType::FuncParam => {
                     let childs = self.children_ids(id);
-                    let arg_type = self.llvm_basic_type(&childs[0]);
+                    let arg_type = self.llvm_basic_type(&childs[0])?;
                     let arg_name = self.ident_name(&childs[1]).unwrap();
 
-                    args_type.push(arg_type);
-                    args_name.push(arg_name);
+                    params.push((arg_name, arg_type, self.is_unsigned_type(&childs[0])));
                 },
                 _ => break,
             };
         }
 
+        Ok(params)
+    }
+
+    // a prototype and the definition share one function, added to the module by
+    // whichever comes first. a prototype has no basic blocks, calls to it are
+    // resolved when the definition, or the linker, provides them.
+    fn function_value(&self, node: &NodeId, params: &[(String, BasicTypeEnum, bool)]) -> CodegenResult<FunctionValue> {
+        let ids = self.children_ids(node);
+        let fn_name = self.ident_name(&ids[1]).unwrap();
+
         // convert to trait objects.
-        let arguments: Vec<&BasicType> = args_type.iter().map(|x| x as &BasicType).collect();
-        let fn_type = self.context.i64_type().fn_type(&arguments[..], false);
-        let function = self.module.add_function(&fn_name, &fn_type, None);
+        let arguments: Vec<&BasicType> = params.iter().map(|x| &x.1 as &BasicType).collect();
+        let fn_type = self.llvm_fn_type(&ids[0], &arguments[..])?;
 
-        self.push_identifier(&fn_name, function.into());
+        let prev = self.symbols.borrow().lookup_current_scope(&fn_name).map(|x| x.value);
+        match prev {
+            Some(AnyValueEnum::FunctionValue(function)) if function.get_type() == fn_type => Ok(function),
+            Some(_) => Err(CodegenError::DuplicateSymbol(fn_name)),
+            None => {
+                let function = self.module.add_function(&fn_name, &fn_type, None);
+                self.push_identifier(&fn_name, function.into(), self.is_unsigned_type(&ids[0]))?;
+
+                Ok(function)
+            },
+        }
+    }
+
+    fn function_gen(&mut self, node: &NodeId) -> CodegenResult<()> {
+
+        let ids = self.children_ids(node);
+        let fn_name = self.ident_name(&ids[1]).unwrap();
+        let params = self.func_params(node)?;
+
+        // already added by the first pass of `ir_gen`.
+        let function = self.function_value(node, &params)?;
+        if function.count_basic_blocks() > 0 {
+            return Err(CodegenError::DuplicateSymbol(fn_name));
+        }
+        self.current_function = Some(function);
+        self.labels.clear();
 
         let __scope_guard = self.scope_guard(&fn_name);
         let bb = self.context.append_basic_block(&function, &fn_name);
         self.builder.position_at_end(&bb);
 
         let param_count = function.count_params();
-        assert_eq!(param_count, args_name.len() as u32);
+        assert_eq!(param_count, params.len() as u32);
 
-        for (idx, param) in function.params().enumerate() {
-            self.push_identifier(&args_name[idx], param.into());
+        // parameters get a slot like any local, so they can be assigned.
+        // the parameter is named too, so the IR reads like the source.
+        for (param, &(ref name, ty, unsigned)) in function.params().zip(params.iter()) {
+            param.set_name(name);
+            let ptr = self.builder.build_alloca(ty, name);
+            self.builder.build_store(&ptr, &param);
+            self.push_identifier(name, ptr.into(), unsigned)?;
         }
+        trace!("symbols of `{}`:\n{:?}", fn_name, self.symbols.borrow());
 
         // argument types
         // let mut arg_types: Vec<&Type> = vec![];
@@ -250,334 +951,3903 @@ impl<'t> LLVMIRGenerater<'t> {
         // }
 
         // start to build basic blocks
-        for id in ids[arguments.len() + 2..].iter() {
-            self.dispatch_node(id);
+        self.stmts_gen(ids[params.len() + 2..].iter())?;
+
+        let undefined = self.labels.iter().find(|&(_, &(_, seen))| !seen).map(|(name, _)| name.clone());
+        if let Some(name) = undefined {
+            return Err(CodegenError::UndefinedLabel(name));
+        }
+
+        // falling off the end returns from a `void` function, otherwise the end
+        // can't be reached, e.g. after an endless loop.
+        if !self.block_terminated() {
+            match self.token(&ids[0]).as_ref().map(|x| &**x) {
+                Some(&Token::KeyWord(KeyWords::Void)) => { self.builder.build_return(None); },
+                _ => { self.builder.build_unreachable(); },
+            }
         }
 
         // self.module.print_to_stderr();
+        Ok(())
     }
 
-    fn return_stmt_gen(&mut self, node_id: &NodeId) {
+    fn return_stmt_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
         info!("GEN {:?}", self.data(&node_id));
 
         let ids = self.children_ids(node_id);
 
         if ids.len() == 0 {
             self.builder.build_return(None);
-            return;
+            return Ok(());
         }
 
         assert_eq!(ids.len(), 1);
 
-        match self.data(&ids[0]) {
-            &SyntaxType::Terminal(ref token) => {
-                match **token {
-                    Token::Number(Numbers::SignedInt(v)) => {
-                        let r_type = self.context.i64_type();
-                        let r_value = r_type.const_int(v as u64, false);
-                        self.builder.build_return(Some(&r_value as &BasicValue));
-                    },
-                    Token::Identifier(ref name, _) => {
-                        let value = self.ident_value(name);
-                        self.builder.build_return(Some(value.as_int_value() as &BasicValue));
-                    },
-                    _ => unimplemented!()
-                }
-            },
-            &SyntaxType::Expr => {
-                let r = any_value_into_basic_value(self.expr_gen(&ids[0])).unwrap();
-                self.builder.build_return(Some(&r as &BasicValue));
-            }
-            _ => unimplemented!()
-        }
+        let r = self.basic_value(&ids[0])?;
+        let r = match self.current_function.unwrap().get_type().get_return_type() {
+            Some(t) => self.convert(r, self.is_unsigned(&ids[0]), t),
+            None => r,
+        };
+        self.builder.build_return(Some(&r as &BasicValue));
+
+        Ok(())
     }
 
-    // fn func_call_gen(&mut self, context: &mut GeneraterContext, node_id: &NodeId) -> *mut LLVMValue {
-    // }
+    // children are the callee name, then one FuncArg per argument.
+    // calling a `void` function gives no value.
+    fn func_call_gen(&self, node_id: &NodeId) -> CodegenResult<Option<BasicValueEnum>> {
+        info!("GEN {:?}", self.data(&node_id));
+
+        let childs = self.children_ids(node_id);
+        let name = self.ident_name(&childs[0]).unwrap();
+        let function = match self.ident_value(&name)? {
+            AnyValueEnum::FunctionValue(function) => function,
+            _ => return Err(CodegenError::UndefinedSymbol(name)),
+        };
+
+        let param_count = function.count_params() as usize;
+        let param_types = function.get_type().get_param_types();
+        let is_var_arg = function.get_type().is_var_arg();
+
+        let mut args = vec![];
+        for (idx, arg) in childs.iter().skip(1).enumerate() {
+            let expr = &self.children_ids(arg)[0];
+            let value = self.basic_value(expr)?;
+            let unsigned = self.is_unsigned(expr);
+
+            // C passes a variadic float as double
+            let value = match value {
+                _ if idx < param_count => self.convert(value, unsigned, param_types[idx]),
+                BasicValueEnum::FloatValue(v) =>
+                    self.to_float(v.into(), false, self.context.f64_type()).into(),
+                _ => value,
+            };
+
+            args.push(value);
+        }
+
+        if param_count > args.len() || !is_var_arg && param_count != args.len() {
+            return Err(CodegenError::ArgumentCount(name, param_count, args.len()));
+        }
+
+        let value = self.builder.build_call(&function, &args[..], "call", false);
+        Ok(value.left())
+    }
 
-    fn if_stmt_gen(&mut self, node_id: &NodeId) {
+    fn if_stmt_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
         info!("GEN {:?}", self.data(&node_id));
 
         let childs = self.children_ids(node_id);
+        let cond = self.condition_gen(&childs[0])?;
 
-        let lhs = match self.llvm_value(&childs[0]) {
-            AnyValueEnum::PointerValue(ptr) => self.dereference_ptr(ptr),
-            value @ _ => any_value_into_basic_value(value).unwrap(),
+        let func = self.current_function.unwrap();
+        let then_bb = self.context.append_basic_block(&func, "then");
+        let else_bb = if childs.len() > 2 {
+            Some(self.context.append_basic_block(&func, "else"))
+        } else {
+            None
         };
-        println!("aaa");
-        let lhs = lhs.into_int_value().into();
-        println!("bbb");
-        let rhs = self.llvm_value(&childs[2]).into_int_value().into();
-
-        // binary op
-        let if_result = match *self.token(&childs[1]).unwrap() {
-            Token::Operator(Operators::Equal) =>
-                self.builder.build_int_compare(IntPredicate::EQ, lhs, rhs, "icmp_eq"),
-            Token::Operator(Operators::NotEqual) =>
-                self.builder.build_int_compare(IntPredicate::NE, lhs, rhs, "icmp_ne"),
-            Token::Operator(Operators::Greater) =>
-                self.builder.build_int_compare(IntPredicate::SGT, lhs, rhs, "icmp_sgt"),
-            Token::Operator(Operators::GreaterEqual) =>
-                self.builder.build_int_compare(IntPredicate::SGE, lhs, rhs, "icmp_sge"),
-            Token::Operator(Operators::Less) =>
-                self.builder.build_int_compare(IntPredicate::SLT, lhs, rhs, "icmp_slt"),
-            Token::Operator(Operators::LessEqual) =>
-                self.builder.build_int_compare(IntPredicate::SLE, lhs, rhs, "icmp_sle"),
-            _ => unreachable!(),
+
+        // without an `else`, the false edge goes straight to the merge block.
+        let mut merge_bb = match else_bb {
+            Some(_) => None,
+            None => Some(self.context.append_basic_block(&func, "endif")),
         };
 
-        let (tb, fb) = {
-            let func = self.symbols.borrow().current_function();
-            let tb = self.context.append_basic_block(&func, "if");
-            let fb = self.context.append_basic_block(&func, "endif");
+        self.builder.build_conditional_branch(&cond,
+                                              &then_bb,
+                                              else_bb.as_ref().or(merge_bb.as_ref()).unwrap());
 
-            self.builder.build_conditional_branch(&if_result, &tb, &fb);
+        if let Some(ref else_bb) = else_bb {
+            if let Some((then_assign, else_assign)) = self.merged_assign(&childs[1], &childs[2]) {
+                return self.merged_assign_gen(&func, &then_assign, &then_bb, &else_assign, else_bb);
+            }
+        }
 
-            (tb, fb)
-        };
+        self.builder.position_at_end(&then_bb);
+        self.dispatch_node(&childs[1])?;
+        self.branch_to_merge(&func, &mut merge_bb);
+
+        if let Some(else_bb) = else_bb {
+            self.builder.position_at_end(&else_bb);
+            self.stmts_gen(self.children(&childs[2]))?;
+            self.branch_to_merge(&func, &mut merge_bb);
+        }
+
+        // when both arms return there's nothing to merge.
+        if let Some(merge_bb) = merge_bb {
+            self.builder.position_at_end(&merge_bb);
+        }
+
+        Ok(())
+    }
+
+    // `if (c) r = a; else r = b;` assigns one variable in both arms, the arms only
+    // compute the values and a phi at the merge block picks the one stored.
+    fn merged_assign(&self, then_arm: &NodeId, else_arm: &NodeId) -> Option<(NodeId, NodeId)> {
+        let then_assign = self.single_assign(vec![then_arm.clone()])?;
+        let else_assign = self.single_assign(self.children_ids(else_arm))?;
+
+        let then_name = self.ident_name(&self.children_ids(&then_assign)[0])?;
+        let else_name = self.ident_name(&self.children_ids(&else_assign)[0])?;
+        if then_name != else_name {
+            return None;
+        }
+
+        Some((then_assign, else_assign))
+    }
+
+    // the AssignStmt an arm consists of, it may be wrapped in a block.
+    fn single_assign(&self, mut stmts: Vec<NodeId>) -> Option<NodeId> {
+        if stmts.len() != 1 {
+            return None;
+        }
 
-        if childs.len() > 3 {
-            self.builder.position_at_end(&tb);
-            self.return_stmt_gen(&childs[3]);
+        let stmt = stmts.pop().unwrap();
+        match self.data(&stmt) {
+            &SyntaxType::AssignStmt => Some(stmt),
+            &SyntaxType::StmtBlock => self.single_assign(self.children_ids(&stmt)),
+            _ => None,
         }
+    }
+
+    fn merged_assign_gen(&mut self,
+                         func: &FunctionValue,
+                         then_assign: &NodeId,
+                         then_bb: &BasicBlock,
+                         else_assign: &NodeId,
+                         else_bb: &BasicBlock) -> CodegenResult<()> {
+        let then_ids = self.children_ids(then_assign);
+        let else_ids = self.children_ids(else_assign);
+        let ptr = self.llvm_value(&then_ids[0])?.into_pointer_value();
+        let merge_bb = self.context.append_basic_block(func, "endif");
+
+        // as in a ternary, an arm may end in another block than it started.
+        self.builder.position_at_end(then_bb);
+        let then_value = self.basic_value(&then_ids[1])?;
+        let then_value = self.convert_to_pointee(then_value, self.is_unsigned(&then_ids[1]), ptr);
+        let then_end = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(&merge_bb);
 
-        // move to end
-        self.builder.position_at_end(&fb);
+        self.builder.position_at_end(else_bb);
+        let else_value = self.basic_value(&else_ids[1])?;
+        let else_value = self.convert_to_pointee(else_value, self.is_unsigned(&else_ids[1]), ptr);
+        let else_end = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(&merge_bb);
+
+        self.builder.position_at_end(&merge_bb);
+        let phi = self.builder.build_phi(then_value.get_type(), "merge");
+        phi.add_incoming(&[(&then_value, &then_end), (&else_value, &else_end)]);
+        self.builder.build_store(&ptr, &phi.as_basic_value());
+
+        Ok(())
     }
 
-    fn expr_gen(&self, node_id: &NodeId) -> AnyValueEnum {
+    fn while_loop_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
         info!("GEN {:?}", self.data(&node_id));
 
         let childs = self.children_ids(node_id);
-        assert!(childs.len() >= 3);
 
-        let mut lhs = match self.llvm_value(&childs[0]) {
-            AnyValueEnum::PointerValue(ptr) => self.dereference_ptr(ptr).into_int_value(),
-            value @ _ =>  value.into_int_value(),
-        };
+        let func = self.current_function.unwrap();
+        let cond_bb = self.context.append_basic_block(&func, "while_cond");
+        let body_bb = self.context.append_basic_block(&func, "while_body");
+        let after_bb = self.context.append_basic_block(&func, "while_end");
 
-        let mut current_op = 1;
-        loop {
-            let rhs = match self.llvm_value(&childs[current_op + 1]) {
-                AnyValueEnum::PointerValue(ptr) => self.dereference_ptr(ptr).into_int_value(),
-                value @ _ =>  value.into_int_value(),
-            };
+        self.builder.build_unconditional_branch(&cond_bb);
 
-            lhs = match *self.token(&childs[current_op]).unwrap() {
-                Token::Operator(Operators::Add) =>
-                    self.builder.build_int_add(lhs, rhs, "add"),
-                // Token::Operator(Operators::Mul) =>
-                    // self.builder.build_int_mul(lhs, rhs, "mul"),
-                // Token::Operator(Operators::Minus) => self.builder.build_int_mul(lhs, rhs, "sub"),
-                // Token::Operator(Operators::Division) => self.builder.build_mul(lhs, rhs, "div"),
-                _ => unreachable!(),
-            };
+        self.builder.position_at_end(&cond_bb);
+        let cond = self.condition_gen(&childs[0])?;
+        self.builder.build_conditional_branch(&cond, &body_bb, &after_bb);
+
+        self.builder.position_at_end(&body_bb);
+        self.loop_blocks.push((Some(cond_bb.clone()), after_bb.clone()));
+        self.dispatch_node(&childs[1])?;
+        self.loop_blocks.pop();
+        if !self.block_terminated() {
+            self.builder.build_unconditional_branch(&cond_bb);
+        }
+
+        self.builder.position_at_end(&after_bb);
+        Ok(())
+    }
+
+    // the body comes first and always runs once, the condition branches back to it.
+    fn do_while_stmt_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
+        info!("GEN {:?}", self.data(&node_id));
+
+        let childs = self.children_ids(node_id);
+
+        let func = self.current_function.unwrap();
+        let body_bb = self.context.append_basic_block(&func, "do_body");
+        let cond_bb = self.context.append_basic_block(&func, "do_cond");
+        let after_bb = self.context.append_basic_block(&func, "do_end");
 
-            current_op += 2;
-            if current_op >= childs.len() { break; }
+        self.builder.build_unconditional_branch(&body_bb);
+
+        self.builder.position_at_end(&body_bb);
+        self.loop_blocks.push((Some(cond_bb.clone()), after_bb.clone()));
+        self.dispatch_node(&childs[0])?;
+        self.loop_blocks.pop();
+        if !self.block_terminated() {
+            self.builder.build_unconditional_branch(&cond_bb);
         }
 
-        lhs.as_any_value_enum()
+        self.builder.position_at_end(&cond_bb);
+        let cond = self.condition_gen(&childs[1])?;
+        self.builder.build_conditional_branch(&cond, &body_bb, &after_bb);
 
-        // unimplemented!()
+        self.builder.position_at_end(&after_bb);
+        Ok(())
     }
 
-    fn llvm_value(&self, node_id: &NodeId) -> AnyValueEnum {
+    // children are the init, condition and step ExprOpts, then the body.
+    fn for_loop_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
         info!("GEN {:?}", self.data(&node_id));
 
-        match self.data(&node_id) {
-            &SyntaxType::Terminal(ref term) => {
-                match term.as_ref() {
-                    &Token::Identifier(ref name, _) =>
-                        match self.symbols.borrow().lookup(name) {
-                            Some(v) => v.clone(),
-                            _ => unreachable!(),
-                        },
-                    &Token::Number(Numbers::SignedInt(n)) => {
-                        self.context.i64_type().const_int(n as u64, false).as_any_value_enum()
-                    },
-                    _ => unreachable!(),
-                }
+        let childs = self.children_ids(node_id);
+
+        let func = self.current_function.unwrap();
+        let cond_bb = self.context.append_basic_block(&func, "for_cond");
+        let body_bb = self.context.append_basic_block(&func, "for_body");
+        let step_bb = self.context.append_basic_block(&func, "for_step");
+        let after_bb = self.context.append_basic_block(&func, "for_end");
+
+        self.expr_opt_gen(&childs[0])?;
+        self.builder.build_unconditional_branch(&cond_bb);
+
+        // an empty condition is always true
+        self.builder.position_at_end(&cond_bb);
+        match self.children_ids(&childs[1]).first() {
+            Some(id) => {
+                let cond = self.condition_gen(id)?;
+                self.builder.build_conditional_branch(&cond, &body_bb, &after_bb);
+            },
+            None => { self.builder.build_unconditional_branch(&body_bb); },
+        }
+
+        self.builder.position_at_end(&body_bb);
+        self.loop_blocks.push((Some(step_bb.clone()), after_bb.clone()));
+        self.dispatch_node(&childs[3])?;
+        self.loop_blocks.pop();
+        if !self.block_terminated() {
+            self.builder.build_unconditional_branch(&step_bb);
+        }
+
+        self.builder.position_at_end(&step_bb);
+        self.expr_opt_gen(&childs[2])?;
+        self.builder.build_unconditional_branch(&cond_bb);
+
+        self.builder.position_at_end(&after_bb);
+        Ok(())
+    }
+
+    // the init or the step of a `for`, evaluated for its side effects.
+    fn expr_opt_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
+        for id in self.children(node_id) {
+            match self.data(id) {
+                &SyntaxType::AssignStmt |
+                &SyntaxType::FuncCall => self.dispatch_node(id)?,
+                _ => { self.llvm_value(id)?; },
             }
-            &SyntaxType::Expr => self.expr_gen(node_id),
-            _ => unreachable!(),
         }
+
+        Ok(())
     }
 
-    fn llvm_basic_type(&self, node_id: &NodeId) -> BasicTypeEnum {
-        match *self.token(node_id).unwrap() {
-            Token::KeyWord(KeyWords::Int) => self.context.i64_type().into(),
-            _ => unimplemented!(),
+    // an `unsigned long`, the size in bytes of the type in the module's data layout.
+    fn sizeof_gen(&self, node_id: &NodeId) -> CodegenResult<AnyValueEnum> {
+        let operand = &self.children_ids(node_id)[0];
+        let ty = match *self.data(operand) {
+            SyntaxType::Expr => self.unevaluated_type(&self.children_ids(operand)[0])?,
+            _ => self.llvm_basic_type(operand)?,
+        };
+
+        let target_data = TargetData::create(self.module.get_data_layout().as_str().to_str().unwrap());
+        let size = target_data.get_abi_size(&ty);
+
+        Ok(self.context.i64_type().const_int(size, false).as_any_value_enum())
+    }
+
+    // the type of an expression which must not be evaluated: it's generated in
+    // a block nothing branches to. an array keeps its type, like `sizeof` wants.
+    fn unevaluated_type(&self, node_id: &NodeId) -> CodegenResult<BasicTypeEnum> {
+        let func = match self.current_function {
+            Some(func) => func,
+            None => return Err(CodegenError::Unsupported(SyntaxType::Sizeof)),
+        };
+
+        let current_bb = self.builder.get_insert_block().unwrap();
+        let unevaluated_bb = self.context.append_basic_block(&func, "sizeof");
+        self.builder.position_at_end(&unevaluated_bb);
+
+        let value = self.llvm_value(node_id);
+        self.builder.build_unreachable();
+        self.builder.position_at_end(&current_bb);
+
+        let value = value?;
+        if self.is_place(node_id) {
+            let pointee = value.into_pointer_value().get_type().get_element_type();
+            return Ok(any_type_into_basic_type(pointee).unwrap());
         }
+
+        Ok(any_value_into_basic_value(value).unwrap().get_type())
     }
 
-    fn ident_value(&self, name: &str) -> AnyValueEnum {
-        self.symbols.borrow().lookup(name).unwrap().clone()
+    // each arm gets its own block, a phi in the merge block selects the result.
+    fn ternary_expr_gen(&self, node_id: &NodeId) -> CodegenResult<AnyValueEnum> {
+        info!("GEN {:?}", self.data(&node_id));
+
+        let childs = self.children_ids(node_id);
+        let cond = self.condition_gen(&childs[0])?;
+
+        let func = self.current_function.unwrap();
+        let then_bb = self.context.append_basic_block(&func, "cond_true");
+        let else_bb = self.context.append_basic_block(&func, "cond_false");
+        let merge_bb = self.context.append_basic_block(&func, "cond_end");
+
+        self.builder.build_conditional_branch(&cond, &then_bb, &else_bb);
+
+        // a nested ternary leaves an arm in another block, the phi needs that one.
+        self.builder.position_at_end(&then_bb);
+        let mut then_value = self.basic_value(&childs[1])?;
+        let then_end = self.builder.get_insert_block().unwrap();
+
+        self.builder.position_at_end(&else_bb);
+        let mut else_value = self.basic_value(&childs[2])?;
+        let else_end = self.builder.get_insert_block().unwrap();
+
+        // arms of different types are converted to a common one, still inside their own block.
+        if then_value.get_type() != else_value.get_type() {
+            let common_type = match (then_value, else_value) {
+                (BasicValueEnum::IntValue(l), BasicValueEnum::IntValue(r)) => self.common_int_type(l, r).into(),
+                _ => self.common_float_type(&then_value, &else_value).into(),
+            };
+
+            self.builder.position_at_end(&then_end);
+            then_value = self.convert(then_value, self.is_unsigned(&childs[1]), common_type);
+            self.builder.position_at_end(&else_end);
+            else_value = self.convert(else_value, self.is_unsigned(&childs[2]), common_type);
+        }
+
+        self.builder.position_at_end(&then_end);
+        self.builder.build_unconditional_branch(&merge_bb);
+        self.builder.position_at_end(&else_end);
+        self.builder.build_unconditional_branch(&merge_bb);
+
+        self.builder.position_at_end(&merge_bb);
+        let phi = self.builder.build_phi(then_value.get_type(), "cond");
+        phi.add_incoming(&[(&then_value, &then_end), (&else_value, &else_end)]);
+
+        Ok(phi.as_basic_value().into())
     }
 
-    fn dereference_basic(&self, value: BasicValueEnum) -> BasicValueEnum {
-        match value {
-            BasicValueEnum::PointerValue(ptr) => {
-                self.dereference_ptr(ptr)
-            },
-            _ => value,
+    // the switch jumps to the block of a case, which falls through to the next
+    // one unless it `break`s. without a match it jumps to `default`, or past
+    // the switch.
+    fn switch_stmt_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
+        info!("GEN {:?}", self.data(&node_id));
+
+        let childs = self.children_ids(node_id);
+        let value = match self.basic_value(&childs[0])? {
+            BasicValueEnum::IntValue(value) => value,
+            _ => return Err(CodegenError::Unsupported(self.data(node_id).clone())),
+        };
+
+        let func = self.current_function.unwrap();
+        let mut case_ids = vec![];
+        self.switch_cases(node_id, &mut case_ids);
+
+        let mut cases = vec![];
+        let mut default_bb = None;
+        for id in case_ids {
+            let bb = match self.data(&id) {
+                &SyntaxType::CaseStmt => {
+                    let n = self.case_value(&self.children_ids(&id)[0]).ok_or(CodegenError::CaseNotConstant)?;
+                    let bb = self.context.append_basic_block(&func, "case");
+                    cases.push((value.get_type().const_int(n as u64, true), bb.clone()));
+                    bb
+                },
+                _ => {
+                    let bb = self.context.append_basic_block(&func, "default");
+                    default_bb = Some(bb.clone());
+                    bb
+                },
+            };
+            self.case_blocks.insert(id, bb);
+        }
+
+        let after_bb = self.context.append_basic_block(&func, "switch_end");
+        let case_refs: Vec<(&IntValue, &BasicBlock)> = cases.iter().map(|&(ref n, ref bb)| (n, bb)).collect();
+        self.builder.build_switch(&value, default_bb.as_ref().unwrap_or(&after_bb), &case_refs);
+
+        let continue_bb = self.loop_blocks.last().and_then(|x| x.0.clone());
+        self.loop_blocks.push((continue_bb, after_bb.clone()));
+        self.stmts_gen(Some(&childs[1]).into_iter())?;
+        self.loop_blocks.pop();
+        if !self.block_terminated() {
+            self.builder.build_unconditional_branch(&after_bb);
         }
+
+        self.builder.position_at_end(&after_bb);
+        Ok(())
     }
 
-    fn dereference_ptr(&self, value: PointerValue) -> BasicValueEnum {
-        self.dereference_basic(self.builder.build_load(&value, "load"))
+    // the cases of a switch, not those of a switch inside it.
+    fn switch_cases(&self, node_id: &NodeId, cases: &mut Vec<NodeId>) {
+        for id in self.children(node_id) {
+            match self.data(id) {
+                &SyntaxType::SwitchStmt => continue,
+                &SyntaxType::CaseStmt |
+                &SyntaxType::DefaultStmt => cases.push(id.clone()),
+                _ => {},
+            }
+
+            self.switch_cases(id, cases);
+        }
     }
 
-    fn push_identifier(&self, ident: &str, value: AnyValueEnum) {
-        self.symbols.borrow_mut().push_symbol(ident, value).unwrap();
+    fn case_stmt_gen(&mut self, node_id: &NodeId) -> CodegenResult<()> {
+        let bb = match self.case_blocks.remove(node_id) {
+            Some(bb) => bb,
+            None => return Err(CodegenError::Unsupported(self.data(node_id).clone())),
+        };
+
+        self.enter_block(&bb);
+        let stmt = self.children_ids(node_id).pop().unwrap();
+        self.dispatch_node(&stmt)
     }
 
-    #[inline]
-    fn ident_name(&self, node_id: &NodeId) -> Option<String> {
-        self.data(node_id).symbol().map(|x| x.to_owned())
+    fn break_stmt_gen(&self) -> CodegenResult<()> {
+        match self.loop_blocks.last() {
+            Some(&(_, ref break_bb)) => {
+                self.builder.build_unconditional_branch(break_bb);
+                Ok(())
+            },
+            None => Err(CodegenError::BreakOutsideLoop),
+        }
     }
 
-    #[inline]
-    fn token(&self, node_id: &NodeId) -> Option<Rc<Token>> {
-        self.data(node_id).token()
+    fn continue_stmt_gen(&self) -> CodegenResult<()> {
+        match self.loop_blocks.last() {
+            Some(&(Some(ref continue_bb), _)) => {
+                self.builder.build_unconditional_branch(continue_bb);
+                Ok(())
+            },
+            _ => Err(CodegenError::ContinueOutsideLoop),
+        }
     }
 
-    #[inline]
-    fn data(&self, node_id: &NodeId) -> &SyntaxType {
-        self.ast.get(node_id).unwrap().data()
+    // fall through to the merge block, creating it on first use.
+    fn branch_to_merge(&self, func: &FunctionValue, merge_bb: &mut Option<BasicBlock>) {
+        if self.block_terminated() { return; }
+
+        if merge_bb.is_none() {
+            *merge_bb = Some(self.context.append_basic_block(func, "endif"));
+        }
+
+        self.builder.build_unconditional_branch(merge_bb.as_ref().unwrap());
     }
 
-    #[inline]
-    fn children_ids(&self, node_id: &NodeId) -> Vec<NodeId> {
-        self.ast.children_ids(&node_id).unwrap().map(|x| x.clone()).collect()
+    fn block_terminated(&self) -> bool {
+        match self.builder.get_insert_block() {
+            Some(bb) => bb.get_terminator().is_some(),
+            None => false,
+        }
     }
 
-    #[inline]
-    fn scope_guard<T: AsRef<str>>(&self, scope: T) -> ScopeGuard<AnyValueEnum, String> {
-        ScopeGuard::new(self.symbols.clone(), scope.as_ref().to_owned())
+    // i1 value of a condition, any number other than 0 is true.
+    fn condition_gen(&self, node_id: &NodeId) -> CodegenResult<IntValue> {
+        if let &SyntaxType::BooleanExpr = self.data(node_id) {
+            return self.bool_expr_gen(node_id);
+        }
+
+        match self.basic_value(node_id)? {
+            BasicValueEnum::FloatValue(value) => {
+                let zero = value.get_type().const_float(0.0);
+                Ok(self.builder.build_float_compare(FloatPredicate::ONE, value, zero, "fcmp_one"))
+            },
+            BasicValueEnum::IntValue(value) => {
+                let zero = value.get_type().const_int(0, false);
+                Ok(self.builder.build_int_compare(IntPredicate::NE, value, zero, "icmp_ne"))
+            },
+            _ => Err(CodegenError::Unsupported(self.data(node_id).clone())),
+        }
     }
-}
 
-#[cfg(test)]
-mod test {
+    // `!operand` or `lhs cmp_op rhs [cmp_op rhs ...]` as an i1 value. a chain is
+    // left-associative, the result of a comparison is an int compared with the
+    // next operand.
+    fn bool_expr_gen(&self, node_id: &NodeId) -> CodegenResult<IntValue> {
+        info!("GEN {:?}", self.data(&node_id));
 
-    use lexer::*;
-    use parser::*;
-    use parser::recursive_descent::*;
-    use parser::llvm_ir_generater::*;
+        let childs = self.children_ids(node_id);
 
-    use inkwell::targets::{Target, InitializationConfig};
-    use inkwell::execution_engine::Symbol;
+        if childs.len() == 2 {
+            assert_eq!(*self.token(&childs[0]).unwrap(), Token::Operator(Operators::LogicNot));
 
-    macro_rules! create_llvm_execution_engine {
-        ($src: ident, $ee: ident) => {
-            let mut parser = RecursiveDescentParser::new(SimpleLexer::new($src.as_bytes()));
-            parser.run().unwrap();
+            let operand = self.condition_gen(&childs[1])?;
+            return Ok(self.builder.build_not(operand, "not"));
+        }
 
-            Target::initialize_native(&InitializationConfig::default()).unwrap();
+        assert!(childs.len() >= 3 && childs.len() % 2 == 1);
 
-            let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
-            generater.ir_gen().ok();
+        let lhs = self.basic_value(&childs[0])?;
+        let mut cmp = self.compare_gen(lhs, self.is_unsigned(&childs[0]), &childs[1], &childs[2])?;
 
-            let $ee = generater.execution_engine().unwrap();
+        for pair in childs[3..].chunks(2) {
+            let lhs = self.builder.build_int_z_extend(cmp, self.context.i32_type(), "zext");
+            cmp = self.compare_gen(lhs.into(), false, &pair[0], &pair[1])?;
+        }
+
+        Ok(cmp)
+    }
+
+    // integers compare as integers, a float on either side makes it an ordered
+    // floating-point compare, false if either operand is a NaN.
+    fn compare_gen(&self, lhs: BasicValueEnum, lhs_unsigned: bool, op_id: &NodeId, rhs_id: &NodeId) -> CodegenResult<IntValue> {
+        let rhs_unsigned = self.is_unsigned(rhs_id);
+        let rhs = self.basic_value(rhs_id)?;
+
+        let (lhs, rhs, unsigned) = match (lhs, rhs) {
+            (BasicValueEnum::IntValue(lhs), BasicValueEnum::IntValue(rhs)) => {
+                let unsigned = int_unsigned(lhs.get_type().get_bit_width(), lhs_unsigned,
+                                            rhs.get_type().get_bit_width(), rhs_unsigned);
+                let (lhs, rhs) = self.int_operands(lhs, lhs_unsigned, rhs, rhs_unsigned);
+                (lhs, rhs, unsigned)
+            },
+            (lhs, rhs) if is_number(&lhs) && is_number(&rhs) => {
+                let (lhs, rhs) = self.float_operands(lhs, lhs_unsigned, rhs, rhs_unsigned);
+                return self.float_compare_gen(op_id, lhs, rhs);
+            },
+            _ => return Err(CodegenError::Unsupported(self.data(op_id).clone())),
+        };
+
+        let predicate = match *self.token(op_id).unwrap() {
+            Token::Operator(Operators::Equal) => IntPredicate::EQ,
+            Token::Operator(Operators::NotEqual) => IntPredicate::NE,
+            Token::Operator(Operators::Greater) if unsigned => IntPredicate::UGT,
+            Token::Operator(Operators::Greater) => IntPredicate::SGT,
+            Token::Operator(Operators::GreaterEqual) if unsigned => IntPredicate::UGE,
+            Token::Operator(Operators::GreaterEqual) => IntPredicate::SGE,
+            Token::Operator(Operators::Less) if unsigned => IntPredicate::ULT,
+            Token::Operator(Operators::Less) => IntPredicate::SLT,
+            Token::Operator(Operators::LessEqual) if unsigned => IntPredicate::ULE,
+            Token::Operator(Operators::LessEqual) => IntPredicate::SLE,
+            _ => return Err(CodegenError::Unsupported(self.data(op_id).clone())),
         };
+
+        Ok(self.builder.build_int_compare(predicate, lhs, rhs, "icmp"))
     }
 
-    macro_rules! func_addr_in_ee {
-        ($ee: ident, $name: expr, $type: ty) => {{
-            let f: Symbol<$type> = unsafe { $ee.get_function($name).unwrap() };
-            f
-        }}
+    fn float_compare_gen(&self, op_id: &NodeId, lhs: FloatValue, rhs: FloatValue) -> CodegenResult<IntValue> {
+        let predicate = match *self.token(op_id).unwrap() {
+            Token::Operator(Operators::Equal) => FloatPredicate::OEQ,
+            Token::Operator(Operators::NotEqual) => FloatPredicate::ONE,
+            Token::Operator(Operators::Greater) => FloatPredicate::OGT,
+            Token::Operator(Operators::GreaterEqual) => FloatPredicate::OGE,
+            Token::Operator(Operators::Less) => FloatPredicate::OLT,
+            Token::Operator(Operators::LessEqual) => FloatPredicate::OLE,
+            _ => return Err(CodegenError::Unsupported(self.data(op_id).clone())),
+        };
+
+        Ok(self.builder.build_float_compare(predicate, lhs, rhs, "fcmp"))
     }
 
-    #[test]
-    fn test_jit_expr()
-    {
-        let src = "
-int f(int a, int b)
-{
-    if (a >= 5)
-        return a;
+    // operators in one `Expr` share a precedence level, the parser nests tighter
+    // ones as child `Expr`s, so evaluating from left to right is enough.
+    fn expr_gen(&self, node_id: &NodeId) -> CodegenResult<AnyValueEnum> {
+        info!("GEN {:?}", self.data(&node_id));
 
-    if (a < b)
-        return b;
+        let mut childs = self.children(node_id);
+        let first = childs.next().unwrap();
 
-    return a + b;
-}
-        ";
+        // unary `-operand`
+        if let Some(&Token::Operator(_)) = self.token(first).as_ref().map(|x| &**x) {
+            let operand = childs.next().unwrap();
+            let unsigned = self.is_unsigned(operand);
+            let value = match self.basic_value(operand)? {
+                BasicValueEnum::IntValue(v) => {
+                    let (v, _) = self.int_operands(v, unsigned, v, unsigned);
+                    self.builder.build_int_neg(v, "neg").as_any_value_enum()
+                },
+                BasicValueEnum::FloatValue(v) => self.builder.build_float_neg(v, "fneg").as_any_value_enum(),
+                _ => return Err(CodegenError::Unsupported(self.data(first).clone())),
+            };
 
-        create_llvm_execution_engine!(src, ee);
-        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i64, i64) -> i64);
+            return Ok(value);
+        }
 
-        assert_eq!(3, unsafe { f(2, 3) });
-        assert_eq!(6, unsafe { f(6, 5) });
-        assert_eq!(7, unsafe { f(4, 3) });
-        assert_eq!(5, unsafe { f(5, 2) });
+        let mut lhs = self.basic_value(first)?;
+        let mut lhs_unsigned = self.is_unsigned(first);
+
+        while let Some(op_id) = childs.next() {
+            let rhs_id = childs.next().unwrap();
+            let rhs = self.basic_value(rhs_id)?;
+            let rhs_unsigned = self.is_unsigned(rhs_id);
+
+            let op = self.token(op_id).unwrap();
+            match *op {
+                Token::Operator(Operators::Division) |
+                Token::Operator(Operators::Mod) if self.is_zero_literal(rhs_id) =>
+                    return Err(CodegenError::DivisionByZero),
+                _ => {},
+            }
+
+            lhs = match (lhs, rhs) {
+                (BasicValueEnum::IntValue(lhs), BasicValueEnum::IntValue(rhs)) => {
+                    let unsigned = int_result_unsigned(&op, lhs.get_type().get_bit_width(), lhs_unsigned,
+                                                       rhs.get_type().get_bit_width(), rhs_unsigned);
+                    let (lhs, rhs) = self.int_operands(lhs, lhs_unsigned, rhs, rhs_unsigned);
+                    self.check_shift_amount(&op, lhs, rhs_id)?;
+                    lhs_unsigned = unsigned;
+                    self.int_arith_gen(&op, lhs, rhs, unsigned).into()
+                },
+                (BasicValueEnum::PointerValue(p), BasicValueEnum::IntValue(n)) => {
+                    lhs_unsigned = false;
+                    self.ptr_offset_gen(op_id, p, n, rhs_unsigned, true)?.into()
+                },
+                (BasicValueEnum::IntValue(n), BasicValueEnum::PointerValue(p)) => {
+                    let p = self.ptr_offset_gen(op_id, p, n, lhs_unsigned, false)?;
+                    lhs_unsigned = false;
+                    p.into()
+                },
+                (lhs, rhs) if is_number(&lhs) && is_number(&rhs) => {
+                    let (lhs, rhs) = self.float_operands(lhs, lhs_unsigned, rhs, rhs_unsigned);
+                    lhs_unsigned = false;
+                    self.float_arith_gen(op_id, lhs, rhs)?.into()
+                },
+                _ => return Err(CodegenError::Unsupported(self.data(op_id).clone())),
+            };
+        }
+
+        Ok(lhs.into())
     }
 
-    #[test]
-    fn test_stack_var()
-    {
-        let src = "
-int f()
-{
-    int a, b;
+    // `p + n`, `n + p` and `p - n` step over elements, like indexing does.
+    fn ptr_offset_gen(&self, op_id: &NodeId, ptr: PointerValue, index: IntValue, unsigned: bool,
+                      ptr_first: bool) -> CodegenResult<PointerValue> {
+        let index = self.convert(index.into(), unsigned, self.context.i64_type().into()).into_int_value();
+        let index = match *self.token(op_id).unwrap() {
+            Token::Operator(Operators::Add) => index,
+            Token::Operator(Operators::Minus) if ptr_first => self.builder.build_int_neg(index, "neg"),
+            _ => return Err(CodegenError::Unsupported(self.data(op_id).clone())),
+        };
 
-    a = 4;
-    b = 5;
+        Ok(self.builder.build_gep(&ptr, &[index], "ptr_add"))
+    }
 
-    return a + b;
-}
-        ";
+    // division, remainder and right shift depend on the sign, the rest doesn't.
+    fn int_arith_gen(&self, op: &Token, lhs: IntValue, rhs: IntValue, unsigned: bool) -> IntValue {
+        match *op {
+            Token::Operator(Operators::Add) =>
+                self.builder.build_int_add(lhs, rhs, "add"),
+            Token::Operator(Operators::Minus) =>
+                self.builder.build_int_sub(lhs, rhs, "sub"),
+            Token::Operator(Operators::Mul) =>
+                self.builder.build_int_mul(lhs, rhs, "mul"),
+            Token::Operator(Operators::Division) if unsigned =>
+                self.builder.build_int_unsigned_div(lhs, rhs, "udiv"),
+            Token::Operator(Operators::Division) =>
+                self.builder.build_int_signed_div(lhs, rhs, "div"),
+            Token::Operator(Operators::Mod) if unsigned =>
+                self.builder.build_int_unsigned_rem(lhs, rhs, "urem"),
+            Token::Operator(Operators::Mod) =>
+                self.builder.build_int_signed_rem(lhs, rhs, "rem"),
+            Token::Operator(Operators::And) =>
+                self.builder.build_and(lhs, rhs, "and"),
+            Token::Operator(Operators::Or) =>
+                self.builder.build_or(lhs, rhs, "or"),
+            Token::Operator(Operators::Xor) =>
+                self.builder.build_xor(lhs, rhs, "xor"),
+            Token::Operator(Operators::ShiftLeft) =>
+                self.builder.build_left_shift(lhs, rhs, "shl"),
+            Token::Operator(Operators::ShiftRight) if unsigned =>
+                self.builder.build_right_shift(lhs, rhs, false, "lshr"),
+            Token::Operator(Operators::ShiftRight) =>
+                self.builder.build_right_shift(lhs, rhs, true, "ashr"),
+            _ => unreachable!(),
+        }
+    }
 
-        create_llvm_execution_engine!(src, ee);
-        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn() -> i64);
+    // shifting by the bit width or more gives a poison value in LLVM.
+    fn check_shift_amount(&self, op: &Token, lhs: IntValue, rhs_id: &NodeId) -> CodegenResult<()> {
+        match *op {
+            Token::Operator(Operators::ShiftLeft) |
+            Token::Operator(Operators::ShiftRight) => {},
+            _ => return Ok(()),
+        }
 
-        assert_eq!(9, unsafe { f() });
+        if let Some(n) = self.case_value(rhs_id) {
+            if n < 0 || n as i64 >= lhs.get_type().get_bit_width() as i64 {
+                return Err(CodegenError::ShiftOutOfRange(n));
+            }
+        }
+
+        Ok(())
     }
 
-//     #[test]
-//     fn test_local_variable()
-//     {
-//         let src = "
-// int f(int a, int b)
-// {
-//     int c;
-//     c = a + b;
-
-//     return c;
-// }";
-
-//         create_llvm_execution_engine!(src, ee);
-//         let f = func_addr_in_ee!(ee, "f", extern "C" fn(i64, i64) -> i64);
-
-//         assert_eq!(5, f(2, 3));
-//         assert_eq!(7, f(3, 4));
-//         assert_eq!(9, f(4, 5));
-//     }
-
-//     #[ignore]
-//     #[test]
-//     fn test_func_call()
-//     {
-//         let src = "
-// int f(int a, int b)
-// {
-//     int c;
-//     c = a + b;
-
-//     return c;
-// }
-
-// int f1(int a)
-// {
-//     return f(a, a + 1);
-// }
-// ";
-
-//         create_llvm_execution_engine!(src, ee);
-//         let f = func_addr_in_ee!(ee, "f1", extern "C" fn(i64) -> i64);
-
-//         assert_eq!(5, f(2));
-//     }
+    // bitwise operators and shifts take integers only.
+    fn float_arith_gen(&self, op_id: &NodeId, lhs: FloatValue, rhs: FloatValue) -> CodegenResult<FloatValue> {
+        let value = match *self.token(op_id).unwrap() {
+            Token::Operator(Operators::Add) =>
+                self.builder.build_float_add(lhs, rhs, "fadd"),
+            Token::Operator(Operators::Minus) =>
+                self.builder.build_float_sub(lhs, rhs, "fsub"),
+            Token::Operator(Operators::Mul) =>
+                self.builder.build_float_mul(lhs, rhs, "fmul"),
+            Token::Operator(Operators::Division) =>
+                self.builder.build_float_div(lhs, rhs, "fdiv"),
+            Token::Operator(Operators::Mod) =>
+                self.builder.build_float_rem(lhs, rhs, "frem"),
+            _ => return Err(CodegenError::Unsupported(self.data(op_id).clone())),
+        };
+
+        Ok(value)
+    }
+
+    // integers narrower than `int` are promoted to it, then the narrower side is
+    // extended to the wider one, by its own sign.
+    fn int_operands(&self, lhs: IntValue, lhs_unsigned: bool, rhs: IntValue, rhs_unsigned: bool) -> (IntValue, IntValue) {
+        let int_type = self.common_int_type(lhs, rhs).into();
+
+        (self.convert(lhs.into(), lhs_unsigned, int_type).into_int_value(),
+         self.convert(rhs.into(), rhs_unsigned, int_type).into_int_value())
+    }
+
+    fn common_int_type(&self, lhs: IntValue, rhs: IntValue) -> IntType {
+        let mut int_type = self.context.i32_type();
+        for t in &[lhs.get_type(), rhs.get_type()] {
+            if t.get_bit_width() > int_type.get_bit_width() {
+                int_type = *t;
+            }
+        }
+
+        int_type
+    }
+
+    // promotes a mixed pair of operands to the wider floating-point type,
+    // `int` converts to the float type, `float` to `double`.
+    fn float_operands(&self, lhs: BasicValueEnum, lhs_unsigned: bool,
+                      rhs: BasicValueEnum, rhs_unsigned: bool) -> (FloatValue, FloatValue) {
+        let float_type = self.common_float_type(&lhs, &rhs);
+
+        (self.to_float(lhs, lhs_unsigned, float_type), self.to_float(rhs, rhs_unsigned, float_type))
+    }
+
+    // double if either side is a double, float otherwise.
+    fn common_float_type(&self, lhs: &BasicValueEnum, rhs: &BasicValueEnum) -> FloatType {
+        let f64_type = self.context.f64_type();
+        let is_double = |v: &BasicValueEnum| match *v {
+            BasicValueEnum::FloatValue(f) => f.get_type() == f64_type,
+            _ => false,
+        };
+
+        if is_double(lhs) || is_double(rhs) {
+            f64_type
+        } else {
+            self.context.f32_type()
+        }
+    }
+
+    // `value` is an integer or a float, the callers check it's a number.
+    fn to_float(&self, value: BasicValueEnum, unsigned: bool, float_type: FloatType) -> FloatValue {
+        match value {
+            BasicValueEnum::IntValue(v) if unsigned =>
+                self.builder.build_unsigned_int_to_float(v, float_type, "uitofp"),
+            BasicValueEnum::IntValue(v) =>
+                self.builder.build_signed_int_to_float(v, float_type, "sitofp"),
+            BasicValueEnum::FloatValue(v) if v.get_type() == float_type => v,
+            BasicValueEnum::FloatValue(v) =>
+                self.builder.build_float_ext(v, float_type, "fpext"),
+            _ => unreachable!(),
+        }
+    }
+
+    // the implicit conversions of C, and explicit casts: between numbers, and
+    // between pointers. anything else is left alone. `unsigned` is the sign of
+    // `value`, an unsigned integer is zero extended.
+    fn convert(&self, value: BasicValueEnum, unsigned: bool, to: BasicTypeEnum) -> BasicValueEnum {
+        match (value, to) {
+            (BasicValueEnum::IntValue(v), BasicTypeEnum::IntType(t)) => {
+                let (from_width, to_width) = (v.get_type().get_bit_width(), t.get_bit_width());
+                if from_width < to_width && unsigned {
+                    self.builder.build_int_z_extend(v, t, "zext").into()
+                } else if from_width < to_width {
+                    self.builder.build_int_s_extend(v, t, "sext").into()
+                } else if from_width > to_width {
+                    self.builder.build_int_truncate(v, t, "trunc").into()
+                } else {
+                    value
+                }
+            },
+            (BasicValueEnum::IntValue(_), BasicTypeEnum::FloatType(t)) => self.to_float(value, unsigned, t).into(),
+            (BasicValueEnum::FloatValue(v), BasicTypeEnum::IntType(t)) =>
+                self.builder.build_float_to_signed_int(v, t, "fptosi").into(),
+            (BasicValueEnum::FloatValue(v), BasicTypeEnum::FloatType(t)) => {
+                if v.get_type() == t {
+                    value
+                } else if t == self.context.f64_type() {
+                    self.builder.build_float_ext(v, t, "fpext").into()
+                } else {
+                    self.builder.build_float_trunc(v, t, "fptrunc").into()
+                }
+            },
+            (BasicValueEnum::PointerValue(v), BasicTypeEnum::PointerType(t)) if v.get_type() != t =>
+                self.builder.build_pointer_cast(v, t, "ptrcast").into(),
+            _ => value,
+        }
+    }
+
+    // `value` as the type stored at `ptr`.
+    fn convert_to_pointee(&self, value: BasicValueEnum, unsigned: bool, ptr: PointerValue) -> BasicValueEnum {
+        match any_type_into_basic_type(ptr.get_type().get_element_type()) {
+            Some(t) => self.convert(value, unsigned, t),
+            None => value,
+        }
+    }
+
+    fn llvm_value(&self, node_id: &NodeId) -> CodegenResult<AnyValueEnum> {
+        info!("GEN {:?}", self.data(&node_id));
+
+        let value = match self.data(&node_id) {
+            &SyntaxType::Terminal(ref term) => {
+                match term.as_ref() {
+                    &Token::Identifier(ref name, _) =>
+                        self.ident_value(name)?,
+                    // an `int`, or a `long` if it doesn't fit. it's converted to
+                    // the type it's stored as, or operated with.
+                    &Token::Number(Numbers::SignedInt(n)) => {
+                        let int_type = if n as i32 as isize == n {
+                            self.context.i32_type()
+                        } else {
+                            self.context.i64_type()
+                        };
+
+                        int_type.const_int(n as u64, true).as_any_value_enum()
+                    },
+                    &Token::Number(Numbers::Float(n)) => {
+                        self.context.f32_type().const_float(n as f64).as_any_value_enum()
+                    },
+                    &Token::Number(Numbers::Double(n)) => {
+                        self.context.f64_type().const_float(n).as_any_value_enum()
+                    },
+                    // the lexer keeps the quotes
+                    &Token::LiteralStr(ref s) => {
+                        let s = &s[1..s.len() - 1];
+                        self.builder.build_global_string_ptr(s, "str").as_pointer_value().as_any_value_enum()
+                    },
+                    _ => return Err(CodegenError::Unsupported(self.data(node_id).clone())),
+                }
+            }
+            &SyntaxType::Expr => self.expr_gen(node_id)?,
+            &SyntaxType::FuncCall => match self.func_call_gen(node_id)? {
+                Some(value) => value.into(),
+                None => {
+                    let name = self.ident_name(&self.children_ids(node_id)[0]).unwrap();
+                    return Err(CodegenError::VoidValue(name));
+                },
+            },
+            &SyntaxType::AssignStmt => self.assign_stmt(node_id)?,
+            &SyntaxType::TernaryExpr => self.ternary_expr_gen(node_id)?,
+            &SyntaxType::ArrayIndex => self.array_index_gen(node_id)?,
+            &SyntaxType::MemberAccess => self.member_access_gen(node_id)?,
+            &SyntaxType::Sizeof => self.sizeof_gen(node_id)?,
+            &SyntaxType::PrefixIncDec |
+            &SyntaxType::PostfixIncDec => self.inc_dec_gen(node_id)?,
+            &SyntaxType::Cast => {
+                let childs = self.children_ids(node_id);
+                let to = self.llvm_basic_type(&childs[0])?;
+                self.convert(self.basic_value(&childs[1])?, self.is_unsigned(&childs[1]), to).into()
+            },
+            // the variable's slot, without loading it
+            &SyntaxType::AddressOf => self.llvm_value(&self.children_ids(node_id)[0])?,
+            // the place the pointer points to
+            &SyntaxType::Dereference => {
+                let operand = &self.children_ids(node_id)[0];
+                match self.basic_value(operand)? {
+                    BasicValueEnum::PointerValue(ptr) => ptr.into(),
+                    _ => return Err(CodegenError::Unsupported(self.data(operand).clone())),
+                }
+            },
+            &SyntaxType::BooleanExpr => {
+                // comparisons are i1, widen them to the language's int
+                let cmp = self.bool_expr_gen(node_id)?;
+                self.builder.build_int_z_extend(cmp, self.context.i32_type(), "zext").as_any_value_enum()
+            },
+            node @ _ => return Err(CodegenError::Unsupported(node.clone())),
+        };
+
+        Ok(value)
+    }
+
+    // value of a node, loaded if the node is a place: `llvm_value` gives its address.
+    fn basic_value(&self, node_id: &NodeId) -> CodegenResult<BasicValueEnum> {
+        let value = self.llvm_value(node_id)?;
+        if self.is_place(node_id) {
+            return Ok(self.dereference_ptr(value.into_pointer_value()));
+        }
+
+        Ok(any_value_into_basic_value(value).unwrap())
+    }
+
+    // a variable, an array element, a struct member or `*ptr`. an enumerator
+    // is an identifier too, but a constant.
+    fn is_place(&self, node_id: &NodeId) -> bool {
+        match *self.data(node_id) {
+            SyntaxType::ArrayIndex | SyntaxType::MemberAccess | SyntaxType::Dereference => true,
+            SyntaxType::Terminal(ref tok) => match **tok {
+                Token::Identifier(ref name, _) => match self.symbols.borrow().lookup(name) {
+                    Some(&Symbol { value: AnyValueEnum::IntValue(_), .. }) => false,
+                    _ => true,
+                },
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    // whether the value of a node is unsigned. an operation is unsigned if one
+    // of its operands is, a comparison gives an `int`.
+    fn is_unsigned(&self, node_id: &NodeId) -> bool {
+        let childs = self.children_ids(node_id);
+
+        match *self.data(node_id) {
+            SyntaxType::Terminal(ref tok) => match **tok {
+                Token::Identifier(ref name, _) =>
+                    self.symbols.borrow().lookup(name).map_or(false, |x| x.unsigned),
+                _ => false,
+            },
+            SyntaxType::Expr => self.expr_int_type(node_id).1,
+            // the condition doesn't count
+            SyntaxType::TernaryExpr => childs.iter().skip(1).any(|x| self.is_unsigned(x)),
+            SyntaxType::Cast => self.is_unsigned_type(&childs[0]),
+            SyntaxType::Sizeof => true,
+            SyntaxType::PrefixIncDec => self.is_unsigned(&childs[1]),
+            SyntaxType::PostfixIncDec => self.is_unsigned(&childs[0]),
+            // a callee, the variable assigned to, or a pointer or array
+            SyntaxType::FuncCall |
+            SyntaxType::AssignStmt |
+            SyntaxType::ArrayIndex |
+            SyntaxType::Dereference => self.is_unsigned(&childs[0]),
+            _ => false,
+        }
+    }
+
+    // bit width and signedness of an integer `Expr`, by the conversions its
+    // operations apply. a comparison is a `BooleanExpr`, it isn't one of them.
+    fn expr_int_type(&self, node_id: &NodeId) -> (u32, bool) {
+        let childs = self.children_ids(node_id);
+
+        // unary `-operand`
+        if let Some(&Token::Operator(_)) = self.token(&childs[0]).as_ref().map(|x| &**x) {
+            let bits = self.int_width(&childs[1]);
+            return (bits.max(32), self.is_unsigned(&childs[1]) && bits >= 32);
+        }
+
+        let mut bits = self.int_width(&childs[0]);
+        let mut unsigned = self.is_unsigned(&childs[0]);
+        for pair in childs[1..].chunks(2) {
+            let op = self.token(&pair[0]).unwrap();
+            let rhs_bits = self.int_width(&pair[1]);
+            unsigned = int_result_unsigned(&op, bits, unsigned, rhs_bits, self.is_unsigned(&pair[1]));
+            bits = match *op {
+                Token::Operator(Operators::ShiftLeft) |
+                Token::Operator(Operators::ShiftRight) => bits.max(32),
+                _ => bits.max(rhs_bits).max(32),
+            };
+        }
+
+        (bits, unsigned)
+    }
+
+    // bit width of the value of a node if it's an integer, before promotion.
+    // the ones it can't tell without generating them count as an `int`.
+    fn int_width(&self, node_id: &NodeId) -> u32 {
+        let childs = self.children_ids(node_id);
+        let type_width = |t: AnyTypeEnum| match t {
+            AnyTypeEnum::IntType(t) => t.get_bit_width(),
+            _ => 32,
+        };
+
+        match *self.data(node_id) {
+            SyntaxType::Terminal(ref tok) => match **tok {
+                Token::Identifier(ref name, _) => match self.symbols.borrow().lookup(name).map(|x| x.value) {
+                    Some(AnyValueEnum::PointerValue(p)) => type_width(p.get_type().get_element_type()),
+                    Some(AnyValueEnum::IntValue(v)) => v.get_type().get_bit_width(),
+                    _ => 32,
+                },
+                Token::Number(Numbers::SignedInt(n)) if n as i32 as isize != n => 64,
+                _ => 32,
+            },
+            SyntaxType::Expr => self.expr_int_type(node_id).0,
+            SyntaxType::Cast => match self.llvm_basic_type(&childs[0]) {
+                Ok(BasicTypeEnum::IntType(t)) => t.get_bit_width(),
+                _ => 32,
+            },
+            SyntaxType::Sizeof => 64,
+            SyntaxType::TernaryExpr => self.int_width(&childs[1]).max(self.int_width(&childs[2])),
+            SyntaxType::PrefixIncDec => self.int_width(&childs[1]),
+            SyntaxType::PostfixIncDec |
+            SyntaxType::AssignStmt => self.int_width(&childs[0]),
+            SyntaxType::FuncCall => match self.ident_name(&childs[0]).and_then(|x| self.ident_value(&x).ok()) {
+                Some(AnyValueEnum::FunctionValue(f)) => match f.get_type().get_return_type() {
+                    Some(BasicTypeEnum::IntType(t)) => t.get_bit_width(),
+                    _ => 32,
+                },
+                _ => 32,
+            },
+            _ => 32,
+        }
+    }
+
+    fn is_unsigned_type(&self, node_id: &NodeId) -> bool {
+        match *self.data(node_id) {
+            SyntaxType::UnsignedType => true,
+            SyntaxType::ConstType => self.is_unsigned_type(&self.children_ids(node_id)[0]),
+            SyntaxType::TypedefName => {
+                let name = self.ident_name(&self.children_ids(node_id)[0]).unwrap();
+                self.typedefs.get(&name).map_or(false, |x| x.1)
+            },
+            _ => false,
+        }
+    }
+
+    // an integer literal, which may be negated.
+    fn case_value(&self, node_id: &NodeId) -> Option<isize> {
+        if let Some(tok) = self.token(node_id) {
+            return match *tok {
+                Token::Number(Numbers::SignedInt(n)) => Some(n),
+                _ => None,
+            };
+        }
+
+        let ids = self.children_ids(node_id);
+        match (self.data(node_id), ids.len()) {
+            (&SyntaxType::Expr, 2) if self.token(&ids[0]).map_or(false, |t| *t == Token::Operator(Operators::Minus)) =>
+                self.case_value(&ids[1]).map(|n| -n),
+            _ => None,
+        }
+    }
+
+    fn is_zero_literal(&self, node_id: &NodeId) -> bool {
+        match self.token(node_id) {
+            Some(ref tok) => **tok == Token::Number(Numbers::SignedInt(0)),
+            None => false,
+        }
+    }
+
+    // function type returning the type at `node_id`, which may be `void`.
+    fn llvm_fn_type(&self, node_id: &NodeId, args: &[&BasicType]) -> CodegenResult<FunctionType> {
+        match self.token(node_id).as_ref().map(|x| &**x) {
+            Some(&Token::KeyWord(KeyWords::Void)) => Ok(self.context.void_type().fn_type(args, false)),
+            _ => Ok(self.llvm_basic_type(node_id)?.fn_type(args, false)),
+        }
+    }
+
+    // an unsigned type is the integer type of its width, a const one is the type
+    // it qualifies.
+    fn llvm_basic_type(&self, node_id: &NodeId) -> CodegenResult<BasicTypeEnum> {
+        match *self.data(node_id) {
+            SyntaxType::UnsignedType |
+            SyntaxType::ConstType => return self.llvm_basic_type(&self.children_ids(node_id)[0]),
+            SyntaxType::TypedefName => {
+                let name = self.ident_name(&self.children_ids(node_id)[0]).unwrap();
+                return self.typedefs.get(&name).map(|x| x.0)
+                    .ok_or_else(|| CodegenError::Unsupported(self.data(node_id).clone()));
+            },
+            _ => {},
+        }
+
+        match *self.token(node_id).unwrap() {
+            Token::KeyWord(KeyWords::Char) => Ok(self.context.i8_type().into()),
+            Token::KeyWord(KeyWords::Short) => Ok(self.context.i16_type().into()),
+            Token::KeyWord(KeyWords::Int) => Ok(self.context.i32_type().into()),
+            Token::KeyWord(KeyWords::Long) => Ok(self.context.i64_type().into()),
+            Token::KeyWord(KeyWords::Float) => Ok(self.context.f32_type().into()),
+            Token::KeyWord(KeyWords::Double) => Ok(self.context.f64_type().into()),
+            Token::Identifier(ref name, _) if self.struct_types.contains_key(name) => {
+                Ok(self.struct_types[name].into())
+            },
+            _ => Err(CodegenError::Unsupported(self.data(node_id).clone())),
+        }
+    }
+
+    fn ident_value(&self, name: &str) -> CodegenResult<AnyValueEnum> {
+        match self.symbols.borrow().lookup(name) {
+            Some(symbol) => Ok(symbol.value),
+            None => Err(CodegenError::UndefinedSymbol(name.to_owned())),
+        }
+    }
+
+    fn dereference_ptr(&self, value: PointerValue) -> BasicValueEnum {
+        self.builder.build_load(&value, "load")
+    }
+
+    fn push_identifier(&self, ident: &str, value: AnyValueEnum, unsigned: bool) -> CodegenResult<()> {
+        self.symbols.borrow_mut().push_symbol(ident, Symbol { value, unsigned })
+            .map_err(|_| CodegenError::DuplicateSymbol(ident.to_owned()))
+    }
+
+    #[inline]
+    fn ident_name(&self, node_id: &NodeId) -> Option<String> {
+        self.data(node_id).symbol().map(|x| x.to_owned())
+    }
+
+    #[inline]
+    fn token(&self, node_id: &NodeId) -> Option<Rc<Token>> {
+        self.data(node_id).token()
+    }
+
+    #[inline]
+    fn data(&self, node_id: &NodeId) -> &SyntaxType {
+        self.ast.get(node_id).unwrap().data()
+    }
+
+    #[inline]
+    fn children_ids(&self, node_id: &NodeId) -> Vec<NodeId> {
+        self.ast.children_ids(&node_id).unwrap().map(|x| x.clone()).collect()
+    }
+
+    /// `children_ids` without the copy, borrowing from the tree rather than `self`
+    /// so the generater can be mutated while iterating.
+    #[inline]
+    fn children(&self, node_id: &NodeId) -> impl Iterator<Item = &'t NodeId> {
+        self.ast.children_ids(node_id).unwrap()
+    }
+
+    #[inline]
+    fn scope_guard<T: AsRef<str>>(&self, scope: T) -> ScopeGuard<Symbol, String> {
+        ScopeGuard::new(self.symbols.clone(), scope.as_ref().to_owned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use lexer::*;
+    use parser::*;
+    use parser::recursive_descent::*;
+    use parser::llvm_ir_generater::*;
+
+    use inkwell::AddressSpace;
+    use inkwell::OptimizationLevel;
+    use inkwell::targets::{Target, InitializationConfig};
+    use inkwell::execution_engine::Symbol;
+
+    use std::env;
+    use std::fs;
+
+    // parses `$src` into `$generater`, generating it has to succeed.
+    macro_rules! generate_llvm_ir {
+        ($src: ident, $generater: ident) => {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new($src.as_bytes()));
+            parser.run().unwrap();
+
+            Target::initialize_native(&InitializationConfig::default()).unwrap();
+
+            let mut $generater = LLVMIRGenerater::new(parser.syntax_tree());
+            assert_eq!($generater.ir_gen(), Ok(()));
+        };
+    }
+
+    macro_rules! create_llvm_execution_engine {
+        ($src: ident, $ee: ident) => {
+            generate_llvm_ir!($src, generater);
+            let $ee = generater.execution_engine().unwrap();
+        };
+    }
+
+    macro_rules! func_addr_in_ee {
+        ($ee: ident, $name: expr, $type: ty) => {{
+            let f: Symbol<$type> = unsafe { $ee.get_function($name).unwrap() };
+            f
+        }}
+    }
+
+    #[test]
+    fn test_jit_expr()
+    {
+        let src = "
+int f(int a, int b)
+{
+    if (a >= 5)
+        return a;
+
+    if (a < b)
+        return b;
+
+    return a + b;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32, i32) -> i32);
+
+        assert_eq!(3, unsafe { f(2, 3) });
+        assert_eq!(6, unsafe { f(6, 5) });
+        assert_eq!(7, unsafe { f(4, 3) });
+        assert_eq!(5, unsafe { f(5, 2) });
+    }
+
+    #[test]
+    fn test_jit_parentheses()
+    {
+        let src = "
+int grouped() { return (2 + 3) * 4; }
+int plain() { return 2 + 3 * 4; }
+
+int f(int a, int b, int c)
+{
+    return a - (b - c) * ((a));
+}
+
+int g(int a, int b, int c)
+{
+    return a / (b * c) + -(a - b);
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let grouped = func_addr_in_ee!(ee, "grouped", unsafe extern "C" fn() -> i32);
+        let plain = func_addr_in_ee!(ee, "plain", unsafe extern "C" fn() -> i32);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32, i32, i32) -> i32);
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn(i32, i32, i32) -> i32);
+
+        assert_eq!(20, unsafe { grouped() });
+        assert_eq!(14, unsafe { plain() });
+        assert_eq!(10 - (7 - 2) * 10, unsafe { f(10, 7, 2) });
+        assert_eq!(100 / (5 * 2) + -(100 - 5), unsafe { g(100, 5, 2) });
+    }
+
+    #[test]
+    fn test_jit_function()
+    {
+        let src = "
+int add(int a, int b)
+{
+    return a + b;
+}
+
+int twice(int a)
+{
+    return add(a, a);
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let add = unsafe { generater.jit_function::<unsafe extern "C" fn(i32, i32) -> i32>("add") }.unwrap();
+        let twice = unsafe { generater.jit_function::<unsafe extern "C" fn(i32) -> i32>("twice") }.unwrap();
+
+        assert_eq!(5, unsafe { add(2, 3) });
+        assert_eq!(-1, unsafe { add(2, -3) });
+        assert_eq!(14, unsafe { twice(7) });
+        assert!(unsafe { generater.jit_function::<unsafe extern "C" fn() -> i32>("missing") }.is_none());
+    }
+
+    #[test]
+    fn test_cached_execution_engine()
+    {
+        let src = "
+int inc(int a) { return a + 1; }
+int dec(int a) { return a - 1; }
+";
+
+        generate_llvm_ir!(src, generater);
+
+        // every call returns the one engine the module was given to
+        let inc = {
+            let ee = generater.execution_engine().unwrap();
+            func_addr_in_ee!(ee, "inc", unsafe extern "C" fn(i32) -> i32)
+        };
+        let ee = generater.execution_engine().unwrap();
+        let dec = func_addr_in_ee!(ee, "dec", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(3, unsafe { inc(2) });
+        assert_eq!(1, unsafe { dec(2) });
+        assert_eq!(2, unsafe { dec(inc(2)) });
+    }
+
+    fn instruction_count(function: FunctionValue) -> usize {
+        let mut count = 0;
+
+        for bb in function.get_basic_blocks() {
+            let mut inst = bb.get_first_instruction();
+            while let Some(i) = inst {
+                count += 1;
+                inst = i.get_next_instruction();
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn test_optimize()
+    {
+        let src = "
+int f(int a)
+{
+    int b = 2 * 3;
+    int c = b + 4;
+
+    return a + c - c;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let before = instruction_count(generater.module.get_function("f").unwrap());
+        assert!(generater.optimize(OptimizationLevel::Default));
+        let after = instruction_count(generater.module.get_function("f").unwrap());
+
+        // folds down to `ret i32 %a`
+        assert!(after < before);
+        assert_eq!(after, 1);
+
+        let ee = generater.execution_engine().unwrap();
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32) -> i32);
+        assert_eq!(7, unsafe { f(7) });
+    }
+
+    #[test]
+    fn test_module_name()
+    {
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new("int f() { return 1; }".as_bytes()));
+        parser.run().unwrap();
+
+        let generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.module.get_name().to_str(), Ok("main"));
+
+        let generater = LLVMIRGenerater::with_module_name(parser.syntax_tree(), "unit_a");
+        assert_eq!(generater.module.get_name().to_str(), Ok("unit_a"));
+    }
+
+    #[test]
+    fn test_array()
+    {
+        let src = "
+int squares(int n)
+{
+    int i, a[10];
+
+    for (i = 0; i < 10; i = i + 1)
+        a[i] = i * i;
+
+    a[a[2]] = -1;
+    return a[n];
+}
+
+double average()
+{
+    double d[3];
+    d[0] = 1.5;
+    d[1] = 2.0;
+    d[2] = 4.0;
+
+    return (d[0] + d[1] + d[2]) / 3;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let squares = func_addr_in_ee!(ee, "squares", unsafe extern "C" fn(i32) -> i32);
+        let average = func_addr_in_ee!(ee, "average", unsafe extern "C" fn() -> f64);
+
+        assert_eq!(9, unsafe { squares(3) });
+        assert_eq!(-1, unsafe { squares(4) });
+        assert_eq!(81, unsafe { squares(9) });
+        assert_eq!(2.5, unsafe { average() });
+
+        let tests = vec![("int f() { int a[2]; return a[-1]; }", CodegenError::NegativeIndex(-1)),
+                         ("int f() { int a[2]; a[-2] = 0; return 0; }", CodegenError::NegativeIndex(-2))];
+
+        for (src, err) in tests {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+            parser.run().unwrap();
+
+            let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+            assert_eq!(generater.ir_gen(), Err(err));
+        }
+    }
+
+    #[test]
+    fn test_pointer()
+    {
+        let src = "
+int f()
+{
+    int x = 1, *p = &x;
+    *p = 7;
+    return x;
+}
+
+int g(int a)
+{
+    int b[3], *q;
+    q = &b[1];
+    *q = a;
+    b[0] = *q * 2;
+    q[1] = b[0] - 1;
+    return b[0] + b[1] + *(&b[2]);
+}
+
+int h(int a)
+{
+    a = a + 1;
+    return a;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn() -> i32);
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn(i32) -> i32);
+        let h = func_addr_in_ee!(ee, "h", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(7, unsafe { f() });
+        assert_eq!(9, unsafe { g(2) });
+        assert_eq!(6, unsafe { h(5) });
+    }
+
+    #[test]
+    fn test_pointer_arith()
+    {
+        let src = "
+int f(int i)
+{
+    int a[4], *p = &a[0], *q;
+    unsigned char n = 2;
+
+    a[0] = 10;
+    a[1] = 20;
+    a[2] = 30;
+    a[3] = 40;
+
+    if (i == 1)
+        return *(p + 1);
+    if (i == 2) {
+        q = n + p;
+        return *q;
+    }
+
+    q = p + 3;
+    q = q - 2;
+    return *q;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(20, unsafe { f(1) });
+        assert_eq!(30, unsafe { f(2) });
+        assert_eq!(20, unsafe { f(3) });
+    }
+
+    #[test]
+    fn test_pointer_operand()
+    {
+        let minus = SyntaxType::Terminal(Rc::new(Token::Operator(Operators::Minus)));
+        let srcs = [
+            ("int f() { int x, *p = &x, *q = &x; return p - q; }", minus.clone()),
+            ("int f() { int x, *p = &x; return p * 2; }", SyntaxType::Terminal(Rc::new(Token::Operator(Operators::Mul)))),
+            ("int f() { int x, *p = &x; return 1 - p; }", minus.clone()),
+            ("int f() { int x, *p = &x; return -p; }", minus.clone()),
+            ("int f() { int x, *p = &x, *q = &x; return p < q; }", SyntaxType::Terminal(Rc::new(Token::Operator(Operators::Less)))),
+        ];
+
+        for &(src, ref err) in &srcs {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+            parser.run().unwrap();
+
+            let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+            assert_eq!(generater.ir_gen(), Err(CodegenError::Unsupported(err.clone())));
+        }
+    }
+
+    #[test]
+    fn test_struct()
+    {
+        let src = "
+struct Pair { int a; int b; };
+
+int sum(int x, int y)
+{
+    struct Pair p, *q = &p;
+    p.a = x;
+    q->b = y;
+    return p.a + q->b;
+}
+
+struct Node { int v; struct Node *next; };
+
+int second()
+{
+    struct Node a, b;
+    a.v = 1;
+    a.next = &b;
+    b.v = 2;
+    b.next = &a;
+    return a.next->v * 10 + a.next->next->v;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let sum = func_addr_in_ee!(ee, "sum", unsafe extern "C" fn(i32, i32) -> i32);
+        let second = func_addr_in_ee!(ee, "second", unsafe extern "C" fn() -> i32);
+
+        assert_eq!(5, unsafe { sum(2, 3) });
+        assert_eq!(-1, unsafe { sum(-4, 3) });
+        assert_eq!(21, unsafe { second() });
+
+        let src = "struct P { int x; }; int f() { struct P p; return p.y; }";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::UnknownMember("P".to_owned(), "y".to_owned())));
+    }
+
+    #[test]
+    fn test_conversion()
+    {
+        let src = "
+double add(int a, double b)
+{
+    return a + b;
+}
+
+int truncate(double d)
+{
+    return (int)d;
+}
+
+double average(int a, int b)
+{
+    return (double)(a + b) / 2;
+}
+
+double widen(int a)
+{
+    double d;
+    d = a;
+    return d + add(a, 1);
+}
+
+int narrow(double d)
+{
+    int a = (int)d;
+    return a;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let add = func_addr_in_ee!(ee, "add", unsafe extern "C" fn(i32, f64) -> f64);
+        let truncate = func_addr_in_ee!(ee, "truncate", unsafe extern "C" fn(f64) -> i32);
+        let average = func_addr_in_ee!(ee, "average", unsafe extern "C" fn(i32, i32) -> f64);
+        let widen = func_addr_in_ee!(ee, "widen", unsafe extern "C" fn(i32) -> f64);
+        let narrow = func_addr_in_ee!(ee, "narrow", unsafe extern "C" fn(f64) -> i32);
+
+        assert_eq!(2.5, unsafe { add(2, 0.5) });
+        assert_eq!(-1.25, unsafe { add(-3, 1.75) });
+        assert_eq!(3, unsafe { truncate(3.7) });
+        assert_eq!(-3, unsafe { truncate(-3.7) });
+        assert_eq!(2.5, unsafe { average(2, 3) });
+        assert_eq!(7.0, unsafe { widen(3) });
+        assert_eq!(9, unsafe { narrow(9.99) });
+    }
+
+    #[test]
+    fn test_int_width()
+    {
+        let src = "
+int add(int a, int b)
+{
+    return a + b;
+}
+
+char low(int a)
+{
+    char c;
+    c = a;
+    return c;
+}
+
+short wrap(short s)
+{
+    return s + 1;
+}
+
+int twice(char c)
+{
+    return c * 2;
+}
+
+long big(int a)
+{
+    long l = a;
+    return l * 100000;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let ir = generater.ir_string();
+        assert!(ir.contains("define i32 @add(i32 %a, i32 %b)"));
+        assert!(ir.contains("define i8 @low(i32 %a)"));
+        assert!(ir.contains(" = trunc i32 "));
+        assert!(ir.contains(" = sext i8 "));
+        assert!(ir.contains("define i64 @big(i32 %a)"));
+
+        let ee = generater.execution_engine().unwrap();
+        let add = func_addr_in_ee!(ee, "add", unsafe extern "C" fn(i32, i32) -> i32);
+        let low = func_addr_in_ee!(ee, "low", unsafe extern "C" fn(i32) -> i8);
+        let wrap = func_addr_in_ee!(ee, "wrap", unsafe extern "C" fn(i16) -> i16);
+        let twice = func_addr_in_ee!(ee, "twice", unsafe extern "C" fn(i8) -> i32);
+        let big = func_addr_in_ee!(ee, "big", unsafe extern "C" fn(i32) -> i64);
+
+        assert_eq!(5, unsafe { add(2, 3) });
+        assert_eq!(44, unsafe { low(300) });
+        assert_eq!(-1, unsafe { low(255) });
+        assert_eq!(-32768, unsafe { wrap(32767) });
+        // promoted to int before the multiplication, so it doesn't wrap
+        assert_eq!(200, unsafe { twice(100) });
+        assert_eq!(10000000000, unsafe { big(100000) });
+    }
+
+    #[test]
+    fn test_integer_promotion()
+    {
+        let src = "
+int uchar_less(int a, unsigned char c)
+{
+    return a < c;
+}
+
+int uchar_div(int a, unsigned char c)
+{
+    return a / c;
+}
+
+int ushort_sum_less(int a, unsigned short c)
+{
+    return a + c < 0;
+}
+
+int long_less(long l, unsigned int u)
+{
+    return l < u;
+}
+
+int uint_less(int a, unsigned int u)
+{
+    return a < u;
+}
+
+int ulong_less(int a, unsigned long u)
+{
+    return a < u;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let uchar_less = func_addr_in_ee!(ee, "uchar_less", unsafe extern "C" fn(i32, u8) -> i32);
+        let uchar_div = func_addr_in_ee!(ee, "uchar_div", unsafe extern "C" fn(i32, u8) -> i32);
+        let ushort_sum_less = func_addr_in_ee!(ee, "ushort_sum_less", unsafe extern "C" fn(i32, u16) -> i32);
+        let long_less = func_addr_in_ee!(ee, "long_less", unsafe extern "C" fn(i64, u32) -> i32);
+        let uint_less = func_addr_in_ee!(ee, "uint_less", unsafe extern "C" fn(i32, u32) -> i32);
+        let ulong_less = func_addr_in_ee!(ee, "ulong_less", unsafe extern "C" fn(i32, u64) -> i32);
+
+        // narrower than int, promoted to a signed int
+        assert_eq!(1, unsafe { uchar_less(-1, 1) });
+        assert_eq!(-2, unsafe { uchar_div(-6, 3) });
+        assert_eq!(1, unsafe { ushort_sum_less(-5, 1) });
+        // long holds every unsigned int, it stays signed
+        assert_eq!(1, unsafe { long_less(-1, 1) });
+        // same or lower rank than the unsigned operand, it's unsigned
+        assert_eq!(0, unsafe { uint_less(-1, 1) });
+        assert_eq!(0, unsafe { ulong_less(-1, 1) });
+    }
+
+    #[test]
+    fn test_unsigned()
+    {
+        let src = "
+int scmp(int a)
+{
+    return a > 2147483647;
+}
+
+int ucmp(unsigned a)
+{
+    return a > 2147483647;
+}
+
+unsigned udiv(unsigned a, unsigned b)
+{
+    return a / b;
+}
+
+unsigned ushr(unsigned a)
+{
+    return a >> 28;
+}
+
+unsigned long widen(unsigned int a)
+{
+    unsigned long l = a;
+    return l;
+}
+
+int byte(unsigned char c)
+{
+    return c;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let ir = generater.ir_string();
+        assert!(ir.contains("icmp sgt i32 "));
+        assert!(ir.contains("icmp ugt i32 "));
+        assert!(ir.contains(" = udiv i32 "));
+        assert!(ir.contains(" = lshr i32 "));
+        assert!(ir.contains(" = zext i32 "));
+
+        let ee = generater.execution_engine().unwrap();
+        let scmp = func_addr_in_ee!(ee, "scmp", unsafe extern "C" fn(i32) -> i32);
+        let ucmp = func_addr_in_ee!(ee, "ucmp", unsafe extern "C" fn(u32) -> i32);
+        let udiv = func_addr_in_ee!(ee, "udiv", unsafe extern "C" fn(u32, u32) -> u32);
+        let ushr = func_addr_in_ee!(ee, "ushr", unsafe extern "C" fn(u32) -> u32);
+        let widen = func_addr_in_ee!(ee, "widen", unsafe extern "C" fn(u32) -> u64);
+        let byte = func_addr_in_ee!(ee, "byte", unsafe extern "C" fn(u8) -> i32);
+
+        // 0xffffffff is -1 as an int, above INT_MAX as an unsigned
+        assert_eq!(0, unsafe { scmp(-1) });
+        assert_eq!(1, unsafe { ucmp(0xffffffff) });
+        assert_eq!(0, unsafe { ucmp(1) });
+        assert_eq!(0x7fffffff, unsafe { udiv(0xfffffffe, 2) });
+        assert_eq!(0xf, unsafe { ushr(0xf0000000) });
+        assert_eq!(0xffffffff, unsafe { widen(0xffffffff) });
+        assert_eq!(255, unsafe { byte(255) });
+    }
+
+    #[test]
+    fn test_sizeof()
+    {
+        let src = "
+struct P { int x; double y; };
+
+unsigned long int_size()
+{
+    return sizeof(int);
+}
+
+unsigned long double_size()
+{
+    return sizeof(double);
+}
+
+int sizes(int a)
+{
+    char c;
+    int arr[4];
+    struct P p;
+    return sizeof c + sizeof arr + sizeof(struct P) + sizeof p.y + sizeof(long) * 100;
+}
+
+int unevaluated()
+{
+    int a = 1;
+    int n = sizeof(a = 2);
+    return a + n;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let int_size = func_addr_in_ee!(ee, "int_size", unsafe extern "C" fn() -> u64);
+        let double_size = func_addr_in_ee!(ee, "double_size", unsafe extern "C" fn() -> u64);
+        let sizes = func_addr_in_ee!(ee, "sizes", unsafe extern "C" fn(i32) -> i32);
+        let unevaluated = func_addr_in_ee!(ee, "unevaluated", unsafe extern "C" fn() -> i32);
+
+        assert_eq!(4, unsafe { int_size() });
+        assert_eq!(8, unsafe { double_size() });
+        assert_eq!(1 + 16 + 16 + 8 + 800, unsafe { sizes(0) });
+        // the assignment isn't evaluated
+        assert_eq!(1 + 4, unsafe { unevaluated() });
+    }
+
+    #[test]
+    fn test_prototype()
+    {
+        let src = "
+int f(int a, int b);
+int unused(int a);
+
+int g(int a)
+{
+    return f(a, 2) + 1;
+}
+
+int f(int a, int b);
+
+int f(int x, int y)
+{
+    return x * y;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let ir = generater.ir_string();
+        assert!(ir.contains("declare i32 @unused(i32)"));
+        // the definition fills in the declared function, rather than adding `f.1`
+        assert!(ir.contains("define i32 @f(i32 %x, i32 %y)"));
+        assert!(!ir.contains("@f."));
+
+        let ee = generater.execution_engine().unwrap();
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(11, unsafe { g(5) });
+    }
+
+    #[test]
+    fn test_forward_reference()
+    {
+        let src = "
+int f(int a)
+{
+    return g(a) * 2;
+}
+
+int is_even(int n)
+{
+    if (n == 0) return 1;
+    return is_odd(n - 1);
+}
+
+int is_odd(int n)
+{
+    if (n == 0) return 0;
+    return is_even(n - 1);
+}
+
+int g(int a)
+{
+    return a + 1;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32) -> i32);
+        let is_even = func_addr_in_ee!(ee, "is_even", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(8, unsafe { f(3) });
+        assert_eq!(1, unsafe { is_even(10) });
+        assert_eq!(0, unsafe { is_even(7) });
+    }
+
+    #[test]
+    fn test_enum()
+    {
+        let src = "
+enum Color { RED, GREEN, BLUE };
+enum { LOW = -1, MID, HIGH = 10, TOP, };
+
+int blue()
+{
+    return BLUE;
+}
+
+int weight(int c)
+{
+    enum Color best = GREEN;
+    if (c == best) return HIGH;
+    return LOW + MID + TOP * c;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let blue = func_addr_in_ee!(ee, "blue", unsafe extern "C" fn() -> i32);
+        let weight = func_addr_in_ee!(ee, "weight", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(2, unsafe { blue() });
+        assert_eq!(10, unsafe { weight(1) });
+        assert_eq!(-1 + 0 + 11 * 2, unsafe { weight(2) });
+    }
+
+    #[test]
+    fn test_type_define()
+    {
+        let src = "
+typedef long Big;
+typedef unsigned int Word;
+typedef struct N N;
+typedef int Pair[2];
+
+struct N { Big v; N *next; };
+
+Big widen(Word w)
+{
+    Big b = w;
+    return b;
+}
+
+int sum()
+{
+    Pair p;
+    N a, b;
+    p[0] = 1;
+    p[1] = 2;
+    a.next = &b;
+    b.v = 10;
+    return p[0] + p[1] + a.next->v;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        // an alias generates the type it stands for
+        let ir = generater.ir_string();
+        assert!(ir.contains("define i64 @widen(i32 %w)"));
+        assert!(ir.contains("%N = type { i64, %N* }"));
+        assert!(ir.contains("alloca [2 x i32]"));
+        assert!(ir.contains(" = zext i32 "));
+
+        let ee = generater.execution_engine().unwrap();
+        let widen = func_addr_in_ee!(ee, "widen", unsafe extern "C" fn(u32) -> i64);
+        let sum = func_addr_in_ee!(ee, "sum", unsafe extern "C" fn() -> i32);
+
+        assert_eq!(0xffffffff, unsafe { widen(0xffffffff) });
+        assert_eq!(13, unsafe { sum() });
+    }
+
+    #[test]
+    fn test_global_variable()
+    {
+        let src = "
+int counter = 5;
+double scale;
+
+int bump(int n)
+{
+    counter = counter + n;
+    return counter;
+}
+
+int get()
+{
+    return counter + later;
+}
+
+double grow()
+{
+    scale = scale + 1.5;
+    return scale;
+}
+
+int later = 10, negative = -1;
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let bump = func_addr_in_ee!(ee, "bump", unsafe extern "C" fn(i32) -> i32);
+        let get = func_addr_in_ee!(ee, "get", unsafe extern "C" fn() -> i32);
+        let grow = func_addr_in_ee!(ee, "grow", unsafe extern "C" fn() -> f64);
+
+        assert_eq!(15, unsafe { get() });
+        assert_eq!(7, unsafe { bump(2) });
+        assert_eq!(10, unsafe { bump(3) });
+        assert_eq!(20, unsafe { get() });
+        assert_eq!(1.5, unsafe { grow() });
+        assert_eq!(3.0, unsafe { grow() });
+
+        let src = "int a = 1; int b = a; int f() { return b; }";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        let node = SyntaxType::Terminal(Rc::new(Token::ident("a")));
+        assert_eq!(generater.ir_gen(), Err(CodegenError::Unsupported(node)));
+    }
+
+    #[test]
+    fn test_shared_context()
+    {
+        let mut parser_a = RecursiveDescentParser::new(SimpleLexer::new("int f() { return 1; }".as_bytes()));
+        parser_a.run().unwrap();
+        let mut parser_b = RecursiveDescentParser::new(SimpleLexer::new("int g() { return 2; }".as_bytes()));
+        parser_b.run().unwrap();
+
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+
+        let context = Context::create();
+        let mut generater_a = LLVMIRGenerater::with_context(parser_a.syntax_tree(), &context, "unit_a");
+        let mut generater_b = LLVMIRGenerater::with_context(parser_b.syntax_tree(), &context, "unit_b");
+        assert_eq!(generater_a.ir_gen(), Ok(()));
+        assert_eq!(generater_b.ir_gen(), Ok(()));
+
+        // types from one context are the same in both modules.
+        let f = generater_a.module.get_function("f").unwrap();
+        let g = generater_b.module.get_function("g").unwrap();
+        assert_eq!(f.get_type(), g.get_type());
+        assert!(generater_b.module.get_function("f").is_none());
+
+        let ee = generater_a.execution_engine().unwrap();
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn() -> i32);
+        assert_eq!(1, unsafe { f() });
+
+        let ee = generater_b.execution_engine().unwrap();
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn() -> i32);
+        assert_eq!(2, unsafe { g() });
+    }
+
+    #[test]
+    fn test_link()
+    {
+        let srcs = ["int f(int a) { return a * 2; }",
+                    "int f(int a); int g(int a) { return f(a) + 1; }",
+                    "int f(int a) { return a; }",
+                    "int n = 3; int h() { return n; }",
+                    "int n = 4;",
+                    "int k() { return 1; }"];
+        let mut parsers = vec![];
+        for src in srcs.iter() {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+            parser.run().unwrap();
+            parsers.push(parser);
+        }
+
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+
+        let context = Context::create();
+        let mut generaters: Vec<_> = parsers.iter().enumerate()
+            .map(|(i, p)| LLVMIRGenerater::with_context(p.syntax_tree(), &context, &format!("unit_{}", i)))
+            .collect();
+        for generater in generaters.iter_mut() {
+            assert_eq!(generater.ir_gen(), Ok(()));
+        }
+
+        let late = generaters.pop().unwrap();
+        let redefined_global = generaters.pop().unwrap();
+        let global = generaters.pop().unwrap();
+        let redefined = generaters.pop().unwrap();
+        let caller = generaters.pop().unwrap();
+        let linked = generaters.pop().unwrap();
+
+        assert_eq!(linked.link(caller), Ok(()));
+        assert_eq!(linked.link(redefined), Err(CodegenError::DuplicateSymbol("f".to_owned())));
+        assert_eq!(linked.link(global), Ok(()));
+        assert_eq!(linked.link(redefined_global), Err(CodegenError::DuplicateSymbol("n".to_owned())));
+
+        // a generater of its own context can't be linked
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new("int h() { return 0; }".as_bytes()));
+        parser.run().unwrap();
+        let mut other = LLVMIRGenerater::new(parser.syntax_tree());
+        other.ir_gen().unwrap();
+        assert!(matches!(linked.link(other), Err(CodegenError::Link(_))));
+
+        let ee = linked.execution_engine().unwrap();
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn(i32) -> i32);
+        assert_eq!(11, unsafe { g(5) });
+        let h = func_addr_in_ee!(ee, "h", unsafe extern "C" fn() -> i32);
+        assert_eq!(3, unsafe { h() });
+
+        // the engine wouldn't see it
+        assert!(matches!(linked.link(late), Err(CodegenError::Link(_))));
+    }
+
+    #[test]
+    fn test_ir_string()
+    {
+        let src = "
+int add(int a, int b)
+{
+    return a + b;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let ir = generater.ir_string();
+        assert!(ir.contains("define i32 @add("));
+        assert!(ir.contains(" = add i32 "));
+    }
+
+    #[test]
+    fn test_write_object_file()
+    {
+        let src = "
+int add(int a, int b)
+{
+    return a + b;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let path = env::temp_dir().join("my_parser_write_object_file.o");
+        generater.write_object_file(&path, None).unwrap();
+        assert!(fs::metadata(&path).unwrap().len() > 0);
+        fs::remove_file(&path).unwrap();
+
+        match generater.write_object_file(&path, Some("no-such-triple")) {
+            Err(CodegenError::Target(ref msg)) => assert!(!msg.is_empty()),
+            r @ _ => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_module()
+    {
+        let src = "int f(int a) { return a; } int g(int a);";
+        generate_llvm_ir!(src, generater);
+
+        let module = generater.module();
+        assert!(module.get_function("f").is_some());
+        assert_eq!(module.get_function("g").map(|x| x.count_basic_blocks()), Some(0));
+        assert!(module.get_function("h").is_none());
+    }
+
+    #[test]
+    fn test_write_bitcode()
+    {
+        let src = "
+int add(int a, int b)
+{
+    return a + b;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let path = env::temp_dir().join("my_parser_write_bitcode.bc");
+        assert_eq!(generater.write_bitcode(&path), Ok(()));
+        let bitcode = fs::read(&path).unwrap();
+        assert_eq!(&bitcode[..4], b"BC\xC0\xDE");
+        fs::remove_file(&path).unwrap();
+
+        let path = env::temp_dir().join("no_such_dir").join("my_parser_write_bitcode.bc");
+        assert_eq!(generater.write_bitcode(&path), Err(CodegenError::WriteFile(path.display().to_string())));
+    }
+
+    #[test]
+    fn test_target_triple()
+    {
+        let src = "
+long size() { return sizeof(long); }
+";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.set_target_triple("x86_64-unknown-linux-gnu"), Ok(()));
+        generater.ir_gen().unwrap();
+
+        assert_eq!(generater.module.get_triple().to_string(), "x86_64-unknown-linux-gnu");
+        let ir = generater.ir_string();
+        assert!(ir.contains("target triple = \"x86_64-unknown-linux-gnu\""));
+        assert!(ir.contains("target datalayout = \"e-m:e-"));
+        assert!(ir.contains("ret i64 8"));
+
+        // the object file is for the module's triple
+        let path = env::temp_dir().join("my_parser_target_triple.o");
+        generater.write_object_file(&path, None).unwrap();
+        assert!(fs::metadata(&path).unwrap().len() > 0);
+        fs::remove_file(&path).unwrap();
+
+        match generater.set_target_triple("no-such-triple") {
+            Err(CodegenError::Target(ref msg)) => assert!(!msg.is_empty()),
+            r @ _ => panic!("unexpected result: {:?}", r),
+        }
+        assert_eq!(generater.module.get_triple().to_string(), "x86_64-unknown-linux-gnu");
+    }
+
+    #[test]
+    fn test_execution_engine_with_opt()
+    {
+        let src = "
+int sum(int n)
+{
+    int i, total = 0;
+
+    for (i = 1; i <= n; i = i + 1)
+        total = total + i;
+
+    return total;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let ee = generater.execution_engine_with_opt(OptimizationLevel::Default).unwrap();
+        let sum = func_addr_in_ee!(ee, "sum", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(55, unsafe { sum(10) });
+        assert_eq!(5050, unsafe { sum(100) });
+
+        // the engine exists, it can't be given another level
+        assert!(generater.execution_engine_with_opt(OptimizationLevel::Default).is_ok());
+        assert_eq!(generater.execution_engine().err(),
+                   Some(CodegenError::EngineOptLevel(OptimizationLevel::Default)));
+    }
+
+    #[test]
+    fn test_stack_var()
+    {
+        let src = "
+int f()
+{
+    int a, b;
+
+    a = 4;
+    b = 5;
+
+    return a + b;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn() -> i32);
+
+        assert_eq!(9, unsafe { f() });
+    }
+
+    #[test]
+    fn test_block_scope()
+    {
+        let src = "
+int f(int a)
+{
+    int b = 1;
+    int c;
+    {
+        int b = 10;
+        {
+            double b = 0.5;
+            c = a;
+        }
+        c = c + b;
+        a = 100;
+    }
+    return c + b + a;
+}
+
+int g(int a)
+{
+    if (a > 0) {
+        int a = 3;
+        return a;
+    }
+    return a;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32) -> i32);
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn(i32) -> i32);
+
+        // the inner `b`s are gone after their blocks, `a` isn't redeclared
+        assert_eq!(2 + 10 + 1 + 100, unsafe { f(2) });
+        assert_eq!(3, unsafe { g(7) });
+        assert_eq!(-7, unsafe { g(-7) });
+    }
+
+    #[test]
+    fn test_empty_stmt()
+    {
+        let src = "
+int count(int n)
+{
+    int i;
+    ;
+    for (i = 0; i < n; i = i + 1);
+    if (n > 100);
+    else;
+    while ((i = i - 1) >= 10);
+    i = i + 1;
+    return i;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+        assert!(generater.module.verify().is_ok());
+
+        let ee = generater.execution_engine().unwrap();
+        let count = func_addr_in_ee!(ee, "count", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(5, unsafe { count(5) });
+        assert_eq!(10, unsafe { count(50) });
+    }
+
+    #[test]
+    fn test_variable_initializer()
+    {
+        let src = "
+int f()
+{
+    int a = 4, c, b = a + 1;
+
+    c = b;
+
+    return a + c;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn() -> i32);
+
+        assert_eq!(9, unsafe { f() });
+    }
+
+    #[test]
+    fn test_jit_sub()
+    {
+        let src = "
+int sub(int a, int b)
+{
+    return a - b;
+}
+
+int sub3(int a, int b, int c)
+{
+    return a - b - c;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let sub = func_addr_in_ee!(ee, "sub", unsafe extern "C" fn(i32, i32) -> i32);
+        let sub3 = func_addr_in_ee!(ee, "sub3", unsafe extern "C" fn(i32, i32, i32) -> i32);
+
+        assert_eq!(-1, unsafe { sub(2, 3) });
+        assert_eq!(1, unsafe { sub(6, 5) });
+        assert_eq!(5, unsafe { sub3(10, 2, 3) });
+        assert_eq!(-15, unsafe { sub3(-10, 2, 3) });
+    }
+
+    #[test]
+    fn test_jit_mul()
+    {
+        let src = "
+int mul(int a, int b)
+{
+    return a * b;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let mul = func_addr_in_ee!(ee, "mul", unsafe extern "C" fn(i32, i32) -> i32);
+
+        assert_eq!(6, unsafe { mul(2, 3) });
+        assert_eq!(0, unsafe { mul(0, 5) });
+        assert_eq!(-12, unsafe { mul(-3, 4) });
+        assert_eq!(49, unsafe { mul(-7, -7) });
+    }
+
+    #[test]
+    fn test_jit_div_rem()
+    {
+        let src = "
+int div(int a, int b)
+{
+    return a / b;
+}
+
+int rem(int a, int b)
+{
+    return a % b;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let div = func_addr_in_ee!(ee, "div", unsafe extern "C" fn(i32, i32) -> i32);
+        let rem = func_addr_in_ee!(ee, "rem", unsafe extern "C" fn(i32, i32) -> i32);
+
+        assert_eq!(3, unsafe { div(7, 2) });
+        assert_eq!(-3, unsafe { div(-7, 2) });
+        assert_eq!(-3, unsafe { div(7, -2) });
+        assert_eq!(1, unsafe { rem(7, 2) });
+        assert_eq!(-1, unsafe { rem(-7, 2) });
+        assert_eq!(1, unsafe { rem(7, -2) });
+    }
+
+    #[test]
+    fn test_unreachable_stmt()
+    {
+        let src = "
+int f(int a)
+{
+    if (a > 0) {
+        return 1;
+        a = 2;
+    } else
+        return 2;
+    a = 3;
+}
+
+int g(int a)
+{
+    while (a) {
+        break;
+        a = a - 1;
+    }
+    return a;
+    a = 4;
+    return a;
+}
+        ";
+
+        // verification fails on an instruction after a terminator.
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32) -> i32);
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(1, unsafe { f(1) });
+        assert_eq!(2, unsafe { f(0) });
+        assert_eq!(5, unsafe { g(5) });
+    }
+
+    #[test]
+    fn test_inc_dec()
+    {
+        let src = "
+int a;
+
+int post()
+{
+    return a++;
+}
+
+int pre()
+{
+    return ++a;
+}
+
+int sum(int n)
+{
+    int i, total = 0;
+
+    for (i = 0; i < n; i++)
+        total = total + i;
+
+    return total;
+}
+
+int second(int n)
+{
+    int x[3];
+    int *p = &x[0];
+
+    x[0] = n;
+    x[1] = n * 2;
+    x[2] = n * 3;
+
+    p++;
+    --x[2];
+    return *p++ + *p;
+}
+
+int down(int n)
+{
+    int r = n--;
+    return r * 10 + n;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let post = func_addr_in_ee!(ee, "post", unsafe extern "C" fn() -> i32);
+        let pre = func_addr_in_ee!(ee, "pre", unsafe extern "C" fn() -> i32);
+        let sum = func_addr_in_ee!(ee, "sum", unsafe extern "C" fn(i32) -> i32);
+        let second = func_addr_in_ee!(ee, "second", unsafe extern "C" fn(i32) -> i32);
+        let down = func_addr_in_ee!(ee, "down", unsafe extern "C" fn(i32) -> i32);
+
+        // `a` is incremented after its value is returned
+        assert_eq!(0, unsafe { post() });
+        assert_eq!(1, unsafe { post() });
+        assert_eq!(3, unsafe { pre() });
+        assert_eq!(45, unsafe { sum(10) });
+        assert_eq!(4, unsafe { second(1) });
+        assert_eq!(54, unsafe { down(5) });
+    }
+
+    #[test]
+    fn test_switch()
+    {
+        let src = "
+int f(int a)
+{
+    switch (a) {
+    case 1:
+        return 10;
+    case 2:
+    case 3:
+        return 20;
+    case -1:
+        a = a * 100;
+        break;
+    default:
+        return 0;
+    }
+
+    return a;
+}
+
+int fallthrough(int a)
+{
+    int r = 0;
+
+    switch (a) {
+    case 0:
+        r = r + 1;
+    case 1:
+        r = r + 10;
+        break;
+    case 2:
+        r = 100;
+    }
+
+    return r;
+}
+
+int count(int n)
+{
+    int i, odd = 0;
+
+    for (i = 0; i < n; i = i + 1) {
+        switch (i % 2) {
+        case 0:
+            continue;
+        default:
+            break;
+        }
+        odd = odd + 1;
+    }
+
+    return odd;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32) -> i32);
+        let fallthrough = func_addr_in_ee!(ee, "fallthrough", unsafe extern "C" fn(i32) -> i32);
+        let count = func_addr_in_ee!(ee, "count", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(10, unsafe { f(1) });
+        assert_eq!(20, unsafe { f(2) });
+        assert_eq!(20, unsafe { f(3) });
+        assert_eq!(-100, unsafe { f(-1) });
+        assert_eq!(0, unsafe { f(7) });
+        assert_eq!(11, unsafe { fallthrough(0) });
+        assert_eq!(10, unsafe { fallthrough(1) });
+        assert_eq!(100, unsafe { fallthrough(2) });
+        assert_eq!(0, unsafe { fallthrough(3) });
+        assert_eq!(5, unsafe { count(10) });
+    }
+
+    #[test]
+    fn test_switch_not_integer()
+    {
+        let src = "
+int f(double d)
+{
+    switch (d) {
+    case 1:
+        return 1;
+    }
+
+    return 0;
+}
+";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::Unsupported(SyntaxType::SwitchStmt)));
+    }
+
+    #[test]
+    fn test_goto()
+    {
+        let src = "
+int sum(int n)
+{
+    int i = 1, total = 0;
+
+again:
+    if (i > n)
+        goto done;
+    total = total + i;
+    i = i + 1;
+    goto again;
+
+done:
+    return total;
+}
+
+int first_multiple(int n, int m)
+{
+    int i = 1;
+
+    while (1) {
+        if (i % m == 0)
+            goto found;
+        i = i + 1;
+    }
+    return 0;
+
+    {
+    found:
+        return i * n;
+    }
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let sum = func_addr_in_ee!(ee, "sum", unsafe extern "C" fn(i32) -> i32);
+        let first_multiple = func_addr_in_ee!(ee, "first_multiple", unsafe extern "C" fn(i32, i32) -> i32);
+
+        assert_eq!(55, unsafe { sum(10) });
+        assert_eq!(0, unsafe { sum(0) });
+        assert_eq!(14, unsafe { first_multiple(2, 7) });
+
+        let src = "int f(int a) { if (a) goto out; return 1; }";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::UndefinedLabel("out".to_owned())));
+    }
+
+    #[test]
+    fn test_division_by_zero()
+    {
+        for src in vec!["int f(int a) { return a / 0; }",
+                        "int f(int a) { return a % 0; }"] {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+            parser.run().unwrap();
+
+            let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+            assert_eq!(generater.ir_gen(), Err(CodegenError::DivisionByZero));
+        }
+    }
+
+    #[test]
+    fn test_jit_precedence()
+    {
+        let src = "
+int f()
+{
+    return 2 + 3 * 4;
+}
+
+int g()
+{
+    return 10 - 2 - 3;
+}
+
+int h(int a, int b, int c)
+{
+    return (a + b) * c - a * (b - c) / 2;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn() -> i32);
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn() -> i32);
+        let h = func_addr_in_ee!(ee, "h", unsafe extern "C" fn(i32, i32, i32) -> i32);
+
+        assert_eq!(14, unsafe { f() });
+        assert_eq!(5, unsafe { g() });
+        assert_eq!(21, unsafe { h(2, 3, 4) });
+    }
+
+    #[test]
+    fn test_jit_compare_precedence()
+    {
+        let src = "
+int f(int a, int b, int c)
+{
+    int r = a < b == c;
+    return r;
+}
+
+int g(int a, int b, int c)
+{
+    int r = a == b < c;
+    return r;
+}
+
+int h(int a, int b, int c)
+{
+    int r = a > b > c;
+    return r;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32, i32, i32) -> i32);
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn(i32, i32, i32) -> i32);
+        let h = func_addr_in_ee!(ee, "h", unsafe extern "C" fn(i32, i32, i32) -> i32);
+
+        // (a < b) == c
+        assert_eq!(1, unsafe { f(1, 2, 1) });
+        assert_eq!(0, unsafe { f(1, 2, 0) });
+        assert_eq!(1, unsafe { f(2, 1, 0) });
+        // a == (b < c)
+        assert_eq!(1, unsafe { g(1, 2, 3) });
+        assert_eq!(0, unsafe { g(2, 2, 3) });
+        // (a > b) > c
+        assert_eq!(0, unsafe { h(3, 2, 1) });
+        assert_eq!(1, unsafe { h(3, 2, 0) });
+    }
+
+    #[test]
+    fn test_if_else()
+    {
+        let src = "
+int max(int a, int b)
+{
+    if (a > b)
+        return a;
+    else
+        return b;
+}
+
+int clamp(int a)
+{
+    int r;
+
+    if (a > 10)
+        r = 10;
+    else
+        r = a;
+
+    if (r < 0)
+        r = 0;
+
+    return r;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let max = func_addr_in_ee!(ee, "max", unsafe extern "C" fn(i32, i32) -> i32);
+        let clamp = func_addr_in_ee!(ee, "clamp", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(3, unsafe { max(2, 3) });
+        assert_eq!(6, unsafe { max(6, 5) });
+        assert_eq!(-1, unsafe { max(-1, -2) });
+        assert_eq!(10, unsafe { clamp(12) });
+        assert_eq!(0, unsafe { clamp(-3) });
+        assert_eq!(7, unsafe { clamp(7) });
+    }
+
+    #[test]
+    fn test_if_phi()
+    {
+        let src = "
+int twice_abs(int a)
+{
+    int r;
+
+    if (a < 0)
+        r = 0 - a;
+    else {
+        r = a;
+    }
+
+    return r * 2;
+}
+
+long pick(int a, long b)
+{
+    long r = 0;
+
+    if (a)
+        r = a > 0 ? b : 0 - b;
+    else
+        r = 1;
+
+    return r + 1;
+}
+        ";
+
+        generate_llvm_ir!(src, generater);
+        assert!(generater.ir_string().contains(" = phi i32 "));
+        assert!(generater.ir_string().contains(" = phi i64 "));
+
+        let ee = generater.execution_engine().unwrap();
+        let twice_abs = func_addr_in_ee!(ee, "twice_abs", unsafe extern "C" fn(i32) -> i32);
+        let pick = func_addr_in_ee!(ee, "pick", unsafe extern "C" fn(i32, i64) -> i64);
+
+        assert_eq!(6, unsafe { twice_abs(-3) });
+        assert_eq!(8, unsafe { twice_abs(4) });
+        assert_eq!(0, unsafe { twice_abs(0) });
+        assert_eq!(11, unsafe { pick(1, 10) });
+        assert_eq!(-9, unsafe { pick(-1, 10) });
+        assert_eq!(2, unsafe { pick(0, 10) });
+    }
+
+    #[test]
+    fn test_else_if()
+    {
+        let src = "
+int range(int a)
+{
+    if (a < 0)
+        return 0;
+    else if (a < 10)
+        return 1;
+    else
+        return 2;
+}
+
+int range2(int a)
+{
+    int r;
+
+    if (a < 0)
+        r = 0;
+    else if (a < 10)
+        r = 1;
+    else if (a < 100)
+        r = 2;
+    else
+        r = 3;
+
+    return r;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let range = func_addr_in_ee!(ee, "range", unsafe extern "C" fn(i32) -> i32);
+        let range2 = func_addr_in_ee!(ee, "range2", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(0, unsafe { range(-5) });
+        assert_eq!(1, unsafe { range(0) });
+        assert_eq!(1, unsafe { range(9) });
+        assert_eq!(2, unsafe { range(10) });
+        assert_eq!(0, unsafe { range2(-1) });
+        assert_eq!(1, unsafe { range2(5) });
+        assert_eq!(2, unsafe { range2(50) });
+        assert_eq!(3, unsafe { range2(500) });
+    }
+
+    #[test]
+    fn test_if_stmt_block()
+    {
+        let src = "
+int f(int a)
+{
+    int r = 0;
+
+    if (a > 0) {
+        int b = a * 2;
+        r = b + 1;
+        return r;
+    } else {
+        r = a;
+    }
+
+    return r - 1;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(7, unsafe { f(3) });
+        assert_eq!(-1, unsafe { f(0) });
+        assert_eq!(-6, unsafe { f(-5) });
+    }
+
+    #[test]
+    fn test_comparison_value()
+    {
+        let src = "
+int gt(int a, int b)
+{
+    return a > b;
+}
+
+int f(int a, int b)
+{
+    int c = a <= b, e = a + 1 == b;
+    int d;
+
+    d = a != b;
+
+    return c + d * 2 + e * 4;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let gt = func_addr_in_ee!(ee, "gt", unsafe extern "C" fn(i32, i32) -> i32);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32, i32) -> i32);
+
+        assert_eq!(1, unsafe { gt(3, 2) });
+        assert_eq!(0, unsafe { gt(2, 2) });
+        assert_eq!(0, unsafe { gt(-1, 2) });
+        assert_eq!(7, unsafe { f(1, 2) });
+        assert_eq!(1, unsafe { f(2, 2) });
+        assert_eq!(2, unsafe { f(3, 2) });
+    }
+
+    #[test]
+    fn test_while_loop()
+    {
+        let src = "
+int sum(int n)
+{
+    int i = 1, total = 0;
+
+    while (i <= n) {
+        total = total + i;
+        i = i + 1;
+    }
+
+    return total;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let sum = func_addr_in_ee!(ee, "sum", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(0, unsafe { sum(0) });
+        assert_eq!(1, unsafe { sum(1) });
+        assert_eq!(55, unsafe { sum(10) });
+    }
+
+    #[test]
+    fn test_do_while()
+    {
+        let src = "
+int count(int n)
+{
+    int i = 0;
+
+    do {
+        i = i + 1;
+    } while (i < n);
+
+    return i;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let count = func_addr_in_ee!(ee, "count", unsafe extern "C" fn(i32) -> i32);
+
+        // the body runs once even though the condition is false from the start
+        assert_eq!(1, unsafe { count(0) });
+        assert_eq!(1, unsafe { count(-3) });
+        assert_eq!(5, unsafe { count(5) });
+    }
+
+    #[test]
+    fn test_ternary()
+    {
+        let src = "
+int max(int a, int b)
+{
+    return a > b ? a : b;
+}
+
+int sign(int a)
+{
+    return a > 0 ? 1 : a < 0 ? -1 : 0;
+}
+
+double positive(int a)
+{
+    return a > 0 ? a : 0.5;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let max = func_addr_in_ee!(ee, "max", unsafe extern "C" fn(i32, i32) -> i32);
+        let sign = func_addr_in_ee!(ee, "sign", unsafe extern "C" fn(i32) -> i32);
+        let positive = func_addr_in_ee!(ee, "positive", unsafe extern "C" fn(i32) -> f64);
+
+        assert_eq!(3, unsafe { max(2, 3) });
+        assert_eq!(6, unsafe { max(6, 5) });
+        assert_eq!(1, unsafe { sign(42) });
+        assert_eq!(-1, unsafe { sign(-7) });
+        assert_eq!(0, unsafe { sign(0) });
+        assert_eq!(3.0, unsafe { positive(3) });
+        assert_eq!(0.5, unsafe { positive(-3) });
+    }
+
+    #[test]
+    fn test_break_continue()
+    {
+        let src = "
+int first_multiple(int n, int m)
+{
+    int i = 1;
+
+    while (1) {
+        if (i % m == 0)
+            break;
+        i = i + 1;
+    }
+
+    return i;
+}
+
+int sum_odd(int n)
+{
+    int i, total = 0;
+
+    for (i = 0; i < n; i = i + 1) {
+        if (i % 2 == 0)
+            continue;
+        total = total + i;
+    }
+
+    return total;
+}
+
+int skip_three(int n)
+{
+    int i = 0, total = 0;
+
+    do {
+        i = i + 1;
+        if (i == 3)
+            continue;
+        total = total + i;
+    } while (i < n);
+
+    return total;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let first_multiple = func_addr_in_ee!(ee, "first_multiple", unsafe extern "C" fn(i32, i32) -> i32);
+        let sum_odd = func_addr_in_ee!(ee, "sum_odd", unsafe extern "C" fn(i32) -> i32);
+        let skip_three = func_addr_in_ee!(ee, "skip_three", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(7, unsafe { first_multiple(0, 7) });
+        assert_eq!(1, unsafe { first_multiple(0, 1) });
+        // 1 + 3 + 5 + 7 + 9
+        assert_eq!(25, unsafe { sum_odd(10) });
+        assert_eq!(0, unsafe { sum_odd(1) });
+        // 1 + 2 + 4 + 5
+        assert_eq!(12, unsafe { skip_three(5) });
+    }
+
+    #[test]
+    fn test_unsupported_syntax()
+    {
+        let tests = vec![("int f() { void v; return 0; }",
+                          SyntaxType::Terminal(Rc::new(Token::KeyWord(KeyWords::Void)))),
+                         ("int f() { struct T t; return 0; }",
+                          SyntaxType::Terminal(Rc::new(Token::ident("T"))))];
+
+        for (src, node) in tests {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+            parser.run().unwrap();
+
+            let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+            assert_eq!(generater.ir_gen(), Err(CodegenError::Unsupported(node)));
+        }
+    }
+
+    #[test]
+    fn test_loop_control_outside_loop()
+    {
+        let tests = vec![("void f() { break; }", CodegenError::BreakOutsideLoop),
+                         ("void f() { continue; }", CodegenError::ContinueOutsideLoop)];
+
+        for (src, err) in tests {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+            parser.run().unwrap();
+
+            let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+            assert_eq!(generater.ir_gen(), Err(err));
+        }
+    }
+
+    #[test]
+    fn test_children()
+    {
+        let src = "
+int f(int a, int b)
+{
+    int i, total = 0;
+
+    for (i = 0; i < a; i = i + 1) {
+        total = total + -b * 2 + i;
+    }
+
+    return total;
+}
+";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let generater = LLVMIRGenerater::new(parser.syntax_tree());
+        let root = parser.syntax_tree().root_node_id().unwrap();
+
+        let mut stack = vec![root.clone()];
+        let mut count = 0;
+        while let Some(id) = stack.pop() {
+            let ids: Vec<NodeId> = generater.children(&id).cloned().collect();
+            assert_eq!(ids, generater.children_ids(&id));
+
+            stack.extend(ids);
+            count += 1;
+        }
+
+        assert_eq!(count, parser.syntax_tree().traverse_pre_order(root).unwrap().count());
+    }
+
+    #[test]
+    fn test_duplicate_symbol()
+    {
+        let src = "int f(int a) { int b; int b; return a; }";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::DuplicateSymbol("b".to_owned())));
+    }
+
+    #[test]
+    fn test_for_loop()
+    {
+        let src = "
+int sum(int n)
+{
+    int i, total = 0;
+
+    for (i = 1; i <= n; i = i + 1)
+        total = total + i;
+
+    return total;
+}
+
+int first_square_above(int n)
+{
+    int i = 0;
+
+    for (;;) {
+        if (i * i > n)
+            return i;
+        i = i + 1;
+    }
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let sum = func_addr_in_ee!(ee, "sum", unsafe extern "C" fn(i32) -> i32);
+        let first_square_above = func_addr_in_ee!(ee, "first_square_above", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(0, unsafe { sum(0) });
+        assert_eq!(55, unsafe { sum(10) });
+        assert_eq!(4, unsafe { first_square_above(10) });
+        assert_eq!(0, unsafe { first_square_above(-1) });
+    }
+
+    #[test]
+    fn test_for_step_expr()
+    {
+        let src = "
+int calls;
+
+int next(int i)
+{
+    calls = calls + 1;
+    return i + 2;
+}
+
+int count(int n)
+{
+    int i, steps = 0;
+
+    calls = 0;
+    for (i = 0; i < n; i = next(i))
+        steps = steps + 1;
+
+    return steps * 100 + calls;
+}
+
+int countdown(int n)
+{
+    int total = 0;
+
+    for (next(n); n > 0; n = n - 1)
+        total = total + n;
+
+    return total + calls;
+}
+        ";
+
+        create_llvm_execution_engine!(src, ee);
+        let count = func_addr_in_ee!(ee, "count", unsafe extern "C" fn(i32) -> i32);
+        let countdown = func_addr_in_ee!(ee, "countdown", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(505, unsafe { count(10) });
+        assert_eq!(0, unsafe { count(0) });
+        // the init is called once, after `count(0)` left `calls` at 0
+        assert_eq!(11, unsafe { countdown(4) });
+    }
+
+    #[test]
+    fn test_local_variable()
+    {
+        let src = "
+int f(int a, int b)
+{
+    int c;
+    c = a + b;
+
+    return c;
+}
+
+int g(int a, int b)
+{
+    int c;
+    c = a + b;
+    int d = c * 2;
+    d = d - a;
+    int e;
+    e = d + c;
+
+    return e;
+}";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32, i32) -> i32);
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn(i32, i32) -> i32);
+
+        assert_eq!(5, unsafe { f(2, 3) });
+        assert_eq!(7, unsafe { f(3, 4) });
+        assert_eq!(9, unsafe { f(4, 5) });
+        // c = 5, d = 10 - 2, e = 8 + 5
+        assert_eq!(13, unsafe { g(2, 3) });
+    }
+
+    #[test]
+    fn test_assign_value()
+    {
+        let src = "
+int f()
+{
+    int a, b;
+    a = b = 5;
+
+    return a + b;
+}
+
+int g(int n)
+{
+    int a, c;
+    c = (a = n + 1) * 3;
+
+    return a + c;
+}";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn() -> i32);
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(10, unsafe { f() });
+        assert_eq!(8, unsafe { g(1) });
+    }
+
+    #[test]
+    fn test_func_call()
+    {
+        let src = "
+int f(int a, int b)
+{
+    int c;
+    c = a + b;
+
+    return c;
+}
+
+int f1(int a)
+{
+    return f(a, a + 1) * 2;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f1", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(10, unsafe { f(2) });
+        assert_eq!(-2, unsafe { f(-1) });
+    }
+
+    #[test]
+    fn test_recursive_call()
+    {
+        let src = "
+int fib(int n)
+{
+    if (n < 2)
+        return n;
+
+    return fib(n - 1) + fib(n - 2);
+}
+
+int fact(int n)
+{
+    if (n <= 1)
+        return 1;
+    else
+        return n * fact(n - 1);
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let fib = func_addr_in_ee!(ee, "fib", unsafe extern "C" fn(i32) -> i32);
+        let fact = func_addr_in_ee!(ee, "fact", unsafe extern "C" fn(i32) -> i32);
+
+        assert_eq!(55, unsafe { fib(10) });
+        assert_eq!(1, unsafe { fib(1) });
+        assert_eq!(120, unsafe { fact(5) });
+        assert_eq!(1, unsafe { fact(0) });
+    }
+
+    #[test]
+    fn test_return_type()
+    {
+        let src = "
+int f()
+{
+    return 1;
+}
+
+void g()
+{
+    return;
+}
+
+void h()
+{
+    g();
+    return;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let f = generater.module.get_function("f").unwrap();
+        let g = generater.module.get_function("g").unwrap();
+
+        assert_eq!(f.get_type(), generater.context.i32_type().fn_type(&[], false));
+        assert_eq!(g.get_type(), generater.context.void_type().fn_type(&[], false));
+        assert_ne!(f.get_type(), g.get_type());
+    }
+
+    #[test]
+    fn test_void_function()
+    {
+        let src = "
+void f()
+{
+    return;
+}
+
+void g(int a)
+{
+    int b;
+
+    if (a > 0)
+        return;
+
+    b = a;
+}
+
+int h(int a)
+{
+    f();
+    g(a);
+    return a;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn());
+        let h = func_addr_in_ee!(ee, "h", unsafe extern "C" fn(i32) -> i32);
+
+        unsafe { f() };
+        assert_eq!(3, unsafe { h(3) });
+        assert_eq!(-3, unsafe { h(-3) });
+    }
+
+    #[test]
+    fn test_float_type()
+    {
+        let src = "
+float f(float a)
+{
+    return a;
+}
+
+double g(double a, int b)
+{
+    double c;
+    c = a;
+    return c;
+}
+";
+
+        generate_llvm_ir!(src, generater);
+
+        let f32_type: BasicTypeEnum = generater.context.f32_type().into();
+        let f64_type: BasicTypeEnum = generater.context.f64_type().into();
+        let i32_type: BasicTypeEnum = generater.context.i32_type().into();
+
+        let f = generater.module.get_function("f").unwrap();
+        assert_eq!(f.get_type().get_param_types(), vec![f32_type]);
+        assert_eq!(f.get_type().get_return_type(), Some(f32_type));
+
+        let g = generater.module.get_function("g").unwrap();
+        assert_eq!(g.get_type().get_param_types(), vec![f64_type, i32_type]);
+        assert_eq!(g.get_type().get_return_type(), Some(f64_type));
+    }
+
+    #[test]
+    fn test_float_arith()
+    {
+        let src = "
+double add(double a, double b)
+{
+    return a + b;
+}
+
+double mixed(double a, int b)
+{
+    return a * b - 0.5;
+}
+
+float half(float a)
+{
+    return a / 2;
+}
+
+double widen(float a, double b)
+{
+    return a - b;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let add = func_addr_in_ee!(ee, "add", unsafe extern "C" fn(f64, f64) -> f64);
+        let mixed = func_addr_in_ee!(ee, "mixed", unsafe extern "C" fn(f64, i32) -> f64);
+        let half = func_addr_in_ee!(ee, "half", unsafe extern "C" fn(f32) -> f32);
+        let widen = func_addr_in_ee!(ee, "widen", unsafe extern "C" fn(f32, f64) -> f64);
+
+        assert_eq!(4.0, unsafe { add(1.5, 2.5) });
+        assert_eq!(-1.25, unsafe { add(-3.75, 2.5) });
+        assert_eq!(5.5, unsafe { mixed(1.5, 4) });
+        assert_eq!(1.25, unsafe { half(2.5) });
+        assert_eq!(0.25, unsafe { widen(0.75, 0.5) });
+    }
+
+    #[test]
+    fn test_float_compare()
+    {
+        let src = "
+int less(double a, double b)
+{
+    return a < b;
+}
+
+int eq(float a, double b)
+{
+    return a == b;
+}
+
+int ge(double a, int b)
+{
+    int r = a >= b;
+    return r;
+}
+
+int sign(double a)
+{
+    if (a > 0)
+        return 1;
+    if (a < 0.0)
+        return -1;
+    return 0;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let less = func_addr_in_ee!(ee, "less", unsafe extern "C" fn(f64, f64) -> i32);
+        let eq = func_addr_in_ee!(ee, "eq", unsafe extern "C" fn(f32, f64) -> i32);
+        let ge = func_addr_in_ee!(ee, "ge", unsafe extern "C" fn(f64, i32) -> i32);
+        let sign = func_addr_in_ee!(ee, "sign", unsafe extern "C" fn(f64) -> i32);
+
+        assert_eq!(1, unsafe { less(1.5, 2.5) });
+        assert_eq!(0, unsafe { less(2.5, 2.5) });
+        assert_eq!(0, unsafe { less(::std::f64::NAN, 2.5) });
+        assert_eq!(1, unsafe { eq(0.5, 0.5) });
+        assert_eq!(0, unsafe { eq(0.1, 0.1) });
+        assert_eq!(1, unsafe { ge(2.0, 2) });
+        assert_eq!(0, unsafe { ge(1.5, 2) });
+        assert_eq!(1, unsafe { sign(0.25) });
+        assert_eq!(-1, unsafe { sign(-3.0) });
+        assert_eq!(0, unsafe { sign(0.0) });
+    }
+
+    #[test]
+    fn test_float_condition()
+    {
+        let src = "
+int truth(double a)
+{
+    if (a)
+        return 1;
+    return 0;
+}
+
+int count(float a)
+{
+    int n = 0;
+    while (a) {
+        a = a - 0.5;
+        n = n + 1;
+    }
+    return n;
+}
+
+int negate(double a)
+{
+    return !a;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let truth = func_addr_in_ee!(ee, "truth", unsafe extern "C" fn(f64) -> i32);
+        let count = func_addr_in_ee!(ee, "count", unsafe extern "C" fn(f32) -> i32);
+        let negate = func_addr_in_ee!(ee, "negate", unsafe extern "C" fn(f64) -> i32);
+
+        assert_eq!(1, unsafe { truth(0.5) });
+        assert_eq!(0, unsafe { truth(0.0) });
+        assert_eq!(0, unsafe { truth(-0.0) });
+        assert_eq!(4, unsafe { count(2.0) });
+        assert_eq!(0, unsafe { count(0.0) });
+        assert_eq!(1, unsafe { negate(0.0) });
+        assert_eq!(0, unsafe { negate(1.5) });
+    }
+
+    #[test]
+    fn test_logic_not()
+    {
+        let src = "
+int not(int a)
+{
+    return !a;
+}
+
+int f(int a, int b)
+{
+    if (!(a > b))
+        return !!b;
+
+    return 2;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let not = func_addr_in_ee!(ee, "not", unsafe extern "C" fn(i32) -> i32);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn(i32, i32) -> i32);
+
+        assert_eq!(1, unsafe { not(0) });
+        assert_eq!(0, unsafe { not(1) });
+        assert_eq!(0, unsafe { not(-7) });
+        assert_eq!(1, unsafe { f(1, 5) });
+        assert_eq!(0, unsafe { f(0, 0) });
+        assert_eq!(2, unsafe { f(3, 0) });
+    }
+
+    #[test]
+    fn test_unary_minus()
+    {
+        let src = "
+int neg(int a)
+{
+    return -a;
+}
+
+int neg_sum(int a, int b)
+{
+    return -(a + b);
+}
+
+int literal(int a)
+{
+    return a - -5 * -2;
+}
+
+double fneg(double a)
+{
+    return -a;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let neg = func_addr_in_ee!(ee, "neg", unsafe extern "C" fn(i32) -> i32);
+        let neg_sum = func_addr_in_ee!(ee, "neg_sum", unsafe extern "C" fn(i32, i32) -> i32);
+        let literal = func_addr_in_ee!(ee, "literal", unsafe extern "C" fn(i32) -> i32);
+        let fneg = func_addr_in_ee!(ee, "fneg", unsafe extern "C" fn(f64) -> f64);
+
+        assert_eq!(-3, unsafe { neg(3) });
+        assert_eq!(4, unsafe { neg(-4) });
+        assert_eq!(-5, unsafe { neg_sum(2, 3) });
+        assert_eq!(1, unsafe { neg_sum(-4, 3) });
+        assert_eq!(0, unsafe { literal(10) });
+        assert_eq!(-1.5, unsafe { fneg(1.5) });
+    }
+
+    #[test]
+    fn test_bitwise_op()
+    {
+        let src = "
+int and(int a, int b)
+{
+    return a & b;
+}
+
+int or(int a, int b)
+{
+    return a | b;
+}
+
+int xor(int a, int b)
+{
+    return a ^ b;
+}
+
+int mixed(int a, int b)
+{
+    return a | b & a + 1;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let and = func_addr_in_ee!(ee, "and", unsafe extern "C" fn(i32, i32) -> i32);
+        let or = func_addr_in_ee!(ee, "or", unsafe extern "C" fn(i32, i32) -> i32);
+        let xor = func_addr_in_ee!(ee, "xor", unsafe extern "C" fn(i32, i32) -> i32);
+        let mixed = func_addr_in_ee!(ee, "mixed", unsafe extern "C" fn(i32, i32) -> i32);
+
+        assert_eq!(0b1000, unsafe { and(0b1100, 0b1010) });
+        assert_eq!(0b1110, unsafe { or(0b1100, 0b1010) });
+        assert_eq!(0b0110, unsafe { xor(0b1100, 0b1010) });
+        assert_eq!(-1, unsafe { and(-1, -1) });
+        // a | (b & (a + 1))
+        assert_eq!(0b0101, unsafe { mixed(0b0100, 0b0111) });
+    }
+
+    #[test]
+    fn test_shift_op()
+    {
+        let src = "
+int shl(int a, int b)
+{
+    return a << b;
+}
+
+int shr(int a, int b)
+{
+    return a >> b;
+}
+
+int f()
+{
+    return 1 << 4;
+}
+
+int g()
+{
+    return 256 >> 2;
+}
+";
+
+        create_llvm_execution_engine!(src, ee);
+        let shl = func_addr_in_ee!(ee, "shl", unsafe extern "C" fn(i32, i32) -> i32);
+        let shr = func_addr_in_ee!(ee, "shr", unsafe extern "C" fn(i32, i32) -> i32);
+        let f = func_addr_in_ee!(ee, "f", unsafe extern "C" fn() -> i32);
+        let g = func_addr_in_ee!(ee, "g", unsafe extern "C" fn() -> i32);
+
+        assert_eq!(16, unsafe { f() });
+        assert_eq!(64, unsafe { g() });
+        assert_eq!(40, unsafe { shl(5, 3) });
+        assert_eq!(5, unsafe { shr(40, 3) });
+        // arithmetic shift keeps the sign
+        assert_eq!(-4, unsafe { shr(-16, 2) });
+    }
+
+    #[test]
+    fn test_float_bitwise_operand()
+    {
+        for &(src, ref op) in &[("double f(double d) { return d & 1; }", Operators::And),
+                                ("double f(double d) { return d << 1; }", Operators::ShiftLeft),
+                                ("int f(int a, float b) { return a ^ b; }", Operators::Xor)] {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+            parser.run().unwrap();
+
+            let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+            assert_eq!(generater.ir_gen(),
+                       Err(CodegenError::Unsupported(SyntaxType::Terminal(Rc::new(Token::Operator(op.clone()))))));
+        }
+    }
+
+    #[test]
+    fn test_shift_out_of_range()
+    {
+        let src = "int f(int a) { return a << 64; }";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::ShiftOutOfRange(64)));
+
+        for &(src, n) in &[("int f(int a) { return a >> -1; }", -1),
+                           ("int f(int a) { return a << 32; }", 32),
+                           ("int f(int a) { return a << 4294967297; }", 4294967297)] {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+            parser.run().unwrap();
+
+            let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+            assert_eq!(generater.ir_gen(), Err(CodegenError::ShiftOutOfRange(n)));
+        }
+    }
+
+    #[test]
+    fn test_external_function()
+    {
+        let src = "
+void show(int a)
+{
+    printf(\"value: %d\\n\", a);
+}
+
+int distance(int a, int b)
+{
+    return labs(a - b);
+}
+";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        Target::initialize_native(&InitializationConfig::default()).unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        let i8_ptr = generater.context.i8_type().ptr_type(AddressSpace::Generic).into();
+        let i32_type = generater.context.i32_type().into();
+        let i64_type = generater.context.i64_type().into();
+
+        let printf = generater.declare_external("printf", &[i8_ptr], Some(i32_type), true).unwrap();
+        generater.declare_external("labs", &[i64_type], Some(i64_type), false).unwrap();
+        assert_eq!(generater.declare_external("labs", &[i64_type], Some(i64_type), false).err(),
+                   Some(CodegenError::DuplicateSymbol("labs".to_owned())));
+
+        // ir_gen verifies the module
+        assert_eq!(generater.ir_gen(), Ok(()));
+        assert!(printf.get_type().is_var_arg());
+        assert_eq!(printf.count_basic_blocks(), 0);
+
+        let ee = generater.execution_engine().unwrap();
+        let distance = func_addr_in_ee!(ee, "distance", unsafe extern "C" fn(i32, i32) -> i32);
+
+        assert_eq!(5, unsafe { distance(2, 7) });
+        assert_eq!(5, unsafe { distance(7, 2) });
+    }
+
+    #[test]
+    fn test_func_call_argument_count()
+    {
+        let src = "
+int f(int a, int b)
+{
+    return a + b;
+}
+
+int g(int a)
+{
+    return f(a);
+}
+";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::ArgumentCount("f".to_owned(), 2, 1)));
+    }
+
+    #[test]
+    fn test_undefined_symbol()
+    {
+        let src = "
+int f()
+{
+    return g();
+}
+";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::UndefinedSymbol("g".to_owned())));
+
+        let src = "
+int f()
+{
+    return a + 1;
+}
+";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::UndefinedSymbol("a".to_owned())));
+
+        let src = "
+int a;
+
+int f()
+{
+    return a();
+}
+";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::UndefinedSymbol("a".to_owned())));
+    }
+
+    #[test]
+    fn test_void_value()
+    {
+        let src = "
+void g()
+{
+}
+
+int f()
+{
+    return g() + 1;
+}
+";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut generater = LLVMIRGenerater::new(parser.syntax_tree());
+        assert_eq!(generater.ir_gen(), Err(CodegenError::VoidValue("g".to_owned())));
+    }
 }
\ No newline at end of file
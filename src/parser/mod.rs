@@ -2,11 +2,15 @@ pub mod recursive_descent;
 pub mod type_analyzer;
 pub mod syntax_node;
 pub mod llvm_ir_generater;
-mod symbol_manager;
+pub mod visitor;
+mod interner;
+pub mod symbol_manager;
 mod symbol_checker;
 
 use id_tree::NodeId;
-use self::syntax_node::SyntaxTree;
+use inkwell::OptimizationLevel;
+use lexer::{LexerError, Span};
+use self::syntax_node::{SyntaxTree, SyntaxType};
 
 #[derive(Debug)]
 pub enum ParseError {
@@ -14,18 +18,64 @@ pub enum ParseError {
     SemanticError,
     MultiDefineError,
     UndefinedSymbol,
+    LexicalError(LexerError),
 }
 
 #[derive(Debug)]
 pub struct ParseErrInfo {
     err_type: ParseError,
+    span: Option<Span>,
 }
 
 type ParserResult = Result<(), ParseErrInfo>;
 
+#[derive(Debug, PartialEq)]
+pub enum CodegenError {
+    DivisionByZero,
+    /// function name, expected and given argument count.
+    ArgumentCount(String, usize, usize),
+    /// a literal shift amount not below the operand's bit width.
+    ShiftOutOfRange(isize),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    /// a name with no definition in scope, or a call to something that isn't
+    /// a function.
+    UndefinedSymbol(String),
+    /// the result of calling a `void` function, with its name, used as a value.
+    VoidValue(String),
+    /// a `goto` to a label its function doesn't have.
+    UndefinedLabel(String),
+    /// a `case` value which isn't an integer constant.
+    CaseNotConstant,
+    /// a constant array index below zero.
+    NegativeIndex(isize),
+    /// a name defined twice in one scope.
+    DuplicateSymbol(String),
+    /// struct name and the member it doesn't have.
+    UnknownMember(String, String),
+    /// target lookup or object emission failed, with LLVM's message.
+    Target(String),
+    /// the JIT engine couldn't be created, with LLVM's message.
+    Engine(String),
+    /// the JIT engine was already created with another optimization level, the
+    /// one it has.
+    EngineOptLevel(OptimizationLevel),
+    /// the module failed verification, with LLVM's message.
+    Verify(String),
+    /// an output file couldn't be written, with its path.
+    WriteFile(String),
+    /// modules couldn't be linked, with the reason.
+    Link(String),
+    /// a node the generator has no lowering for.
+    Unsupported(SyntaxType),
+}
+
+pub type CodegenResult<T> = Result<T, CodegenError>;
+
 pub trait Parser {
     fn run(&mut self) -> ParserResult;
     fn syntax_tree(&self) -> &SyntaxTree;
+    fn syntax_tree_mut(&mut self) -> &mut SyntaxTree;
 }
 
 fn print_space(indentation: usize) {
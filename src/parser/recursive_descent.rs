@@ -1,7 +1,7 @@
 
 use token::*;
 use token::Token::*;
-use lexer::Lexer;
+use lexer::{Lexer, LexerError, Span};
 use parser::*;
 use parser::symbol_checker::*;
 use parser::syntax_node::*;
@@ -11,9 +11,13 @@ use id_tree::InsertBehavior::*;
 use id_tree::RemoveBehavior::*;
 
 use std::rc::Rc;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 type TokenResult = Option<Rc<Token>>;
 
+// number of binary operator levels in `expr`, see `match_expr_op`.
+const EXPR_LEVELS: usize = 6;
+
 macro_rules! insert {
     ($tree: expr, $root: expr, $tok: expr) => {
         $tree.insert(Node::new(SyntaxType::Terminal($tok)), UnderNode(&$root)).unwrap();
@@ -32,35 +36,147 @@ macro_rules! replace {
     }
 }
 
-pub struct RecursiveDescentParser {
-    tokens: Vec<Rc<Token>>,
+/// Tokens pulled from the lexer as the parser reaches them. The parser only
+/// backtracks within a top level definition, so the tokens before one are
+/// dropped once it's matched, and the buffer holds the longest definition at
+/// most rather than the whole input.
+struct TokenStream<'l> {
+    lexer: Box<Lexer + 'l>,
+    buffer: VecDeque<(Rc<Token>, Span)>,
+    // index in the input of the first buffered token.
+    base: usize,
+    error: Option<(LexerError, Span)>,
+    // the lexer is at its end, or failed.
+    exhausted: bool,
+    // the most tokens buffered at once.
+    max_buffered: usize,
+}
+
+impl<'l> TokenStream<'l> {
+    fn new<T: Lexer + 'l>(lexer: T) -> TokenStream<'l> {
+        TokenStream {
+            lexer: Box::new(lexer),
+            buffer: VecDeque::new(),
+            base: 0,
+            error: None,
+            exhausted: false,
+            max_buffered: 0,
+        }
+    }
+
+    /// The `index`-th token of the input, `None` at its end or at a lexer error.
+    fn get(&mut self, index: usize) -> Option<Rc<Token>> {
+        self.fill(index);
+        let base = self.base;
+        index.checked_sub(base).and_then(|i| self.buffer.get(i)).map(|x| x.0.clone())
+    }
+
+    /// Source position of the `index`-th token, `None` if it's been dropped.
+    fn span(&mut self, index: usize) -> Option<Span> {
+        self.fill(index);
+        let base = self.base;
+        index.checked_sub(base).and_then(|i| self.buffer.get(i)).map(|x| x.1)
+    }
+
+    // comments are skipped, the lexer stops at the first error.
+    fn fill(&mut self, index: usize) {
+        while !self.exhausted && index >= self.base + self.buffer.len() {
+            match self.lexer.next() {
+                Some(Ok(Token::Comment(_))) => {},
+                Some(Ok(tok)) => self.buffer.push_back((Rc::new(tok), self.lexer.span())),
+                Some(Err(e)) => {
+                    self.error = Some((e, self.lexer.span()));
+                    self.exhausted = true;
+                },
+                None => self.exhausted = true,
+            }
+        }
+
+        if self.buffer.len() > self.max_buffered {
+            self.max_buffered = self.buffer.len();
+        }
+    }
+
+    /// Spans of the tokens before `index`, by the address of the token, which
+    /// the terminals of the tree share.
+    fn spans_before(&self, index: usize) -> HashMap<*const Token, Span> {
+        self.buffer.iter().take(index.saturating_sub(self.base))
+            .map(|&(ref tok, span)| (&**tok as *const Token, span))
+            .collect()
+    }
+
+    /// Drop the tokens before `index`, the parser won't go back to them.
+    fn commit(&mut self, index: usize) {
+        while self.base < index && self.buffer.pop_front().is_some() {
+            self.base += 1;
+        }
+    }
+
+    /// The lexer error of the input, the rest of which is lexed if needed, but
+    /// not buffered.
+    fn lexer_error(&mut self) -> Option<(LexerError, Span)> {
+        while !self.exhausted {
+            match self.lexer.next() {
+                Some(Ok(_)) => {},
+                Some(Err(e)) => {
+                    self.error = Some((e, self.lexer.span()));
+                    self.exhausted = true;
+                },
+                None => self.exhausted = true,
+            }
+        }
+
+        self.error.take()
+    }
+}
+
+pub struct RecursiveDescentParser<'l> {
+    tokens: TokenStream<'l>,
     current: usize,
     tree: SyntaxTree,
+    // names defined by `typedef` so far, they're types rather than identifiers.
+    typedefs: HashSet<String>,
+    // source position of the terminals and the statements of the tree.
+    spans: HashMap<NodeId, Span>,
 }
 
-impl RecursiveDescentParser {
-    pub fn new<T>(lexer: T) -> RecursiveDescentParser
-      where T: Lexer {
+impl<'l> RecursiveDescentParser<'l> {
+    pub fn new<T>(lexer: T) -> RecursiveDescentParser<'l>
+      where T: Lexer + 'l {
         let mut tree = SyntaxTree::new();
         let root_node = Node::new(SyntaxType::SyntaxTree);
         tree.insert(root_node, AsRoot).unwrap();
 
         RecursiveDescentParser {
-            tokens: lexer
-                .filter(|x| !matches!(x, &Token::Comment(_)))
-                .map(|x| Rc::new(x))
-                .collect(),
+            tokens: TokenStream::new(lexer),
             current: 0,
             tree: tree,
+            typedefs: HashSet::new(),
+            spans: HashMap::new(),
         }
     }
 
-    pub fn dump_lexer(&self) {
-        for token in &self.tokens {
+    /// print the tokens from the current one to the end of the input.
+    pub fn dump_lexer(&mut self) {
+        let mut index = self.current;
+        while let Some(token) = self.tokens.get(index) {
             println!("{:?}", *token);
+            index += 1;
         }
     }
 
+    /// source position of the `index`-th token, `None` once the definition
+    /// holding it has been parsed.
+    pub fn token_span(&mut self, index: usize) -> Option<Span> {
+        self.tokens.span(index)
+    }
+
+    /// source position of the terminals and the statements of the tree, by
+    /// node. a statement is where its first token is.
+    pub fn spans(&self) -> &HashMap<NodeId, Span> {
+        &self.spans
+    }
+
     pub fn dump(&self) {
         let ref id = self.root_id();
         dump_tree(&self.tree, id, 0);
@@ -72,8 +188,8 @@ impl RecursiveDescentParser {
     }
 
     #[cfg(debug_assertions)]
-    pub fn lexer_end(&self) -> bool {
-        self.current == self.tokens.len()
+    pub fn lexer_end(&mut self) -> bool {
+        self.copy_current().is_none()
     }
 
     fn root_id(&self) -> NodeId {
@@ -87,6 +203,20 @@ impl RecursiveDescentParser {
         self.match_bool_expr_fix(root)
     }
 
+    /// a `bool_expr` kept as a single child of `root`, a BooleanExpr unless it's
+    /// just one operand.
+    fn match_bool_expr_node(&mut self, root: &NodeId) -> bool {
+        let self_id = insert_type!(self.tree, root, SyntaxType::BooleanExpr);
+
+        if self.match_bool_expr(&self_id) {
+            self.adjust_single_child(self_id);
+            return true;
+        }
+
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
     /// bool_expr_fix = || bool_expr_and bool_expr_fix | epsilon
     fn match_bool_expr_fix(&mut self, root: &NodeId) -> bool {
         if self.term(Token::Operator(Operators::LogicOr)) {
@@ -137,7 +267,7 @@ impl RecursiveDescentParser {
     /// bool_expr_equal = bool_expr_equal equal_op bool_expr_cmp
     ///                -> bool_expr_cmp bool_expr_equal_fix
     fn match_bool_expr_equal(&mut self, root: &NodeId) -> bool {
-        self.match_bool_expr_cmp(root) &&
+        self.match_bool_expr_cmp_node(root) &&
         self.match_bool_expr_equal_fix(root)
     }
 
@@ -146,7 +276,7 @@ impl RecursiveDescentParser {
         if let Some(tok) = self.match_equal_op() {
             let id = insert!(self.tree, root, tok);
 
-            if self.match_bool_expr_cmp(root) &&
+            if self.match_bool_expr_cmp_node(root) &&
                self.match_bool_expr_equal_fix(root) {
                 return true;
             }
@@ -158,6 +288,21 @@ impl RecursiveDescentParser {
         true
     }
 
+    /// a `bool_expr_cmp` under a BooleanExpr of its own, like an `Expr` a
+    /// BooleanExpr only holds operators of one level, left-associative. so
+    /// `a < b == c` compares `a < b` with `c`.
+    fn match_bool_expr_cmp_node(&mut self, root: &NodeId) -> bool {
+        let self_id = insert_type!(self.tree, root, SyntaxType::BooleanExpr);
+
+        if self.match_bool_expr_cmp(&self_id) {
+            self.adjust_single_child(self_id);
+            return true;
+        }
+
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
     /// bool_expr_cmp = bool_expr_cmp cmp_op bool_expr_factor
     ///              -> bool_expr_factor bool_expr_cmp_fix
     fn match_bool_expr_cmp(&mut self, root: &NodeId) -> bool {
@@ -182,7 +327,7 @@ impl RecursiveDescentParser {
         true
     }
 
-    /// bool_expr_factor = !bool_expr | (bool_expr) | expr
+    /// bool_expr_factor = !bool_expr_factor | expr | (bool_expr)
     fn match_bool_expr_factor(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
         let self_id = insert_type!(self.tree, root, SyntaxType::BooleanExpr);
@@ -190,12 +335,19 @@ impl RecursiveDescentParser {
         loop {
             if self.term(Token::Operator(Operators::LogicNot)) {
                 insert!(self.tree, &self_id, Rc::new(Token::Operator(Operators::LogicNot)));
-                if self.match_bool_expr(&self_id) {
+                if self.match_bool_expr_factor(&self_id) {
                     return true;
                 }
                 break;
             }
 
+            // try `expr` first, so `(a + b) * c` is not cut off after `)`
+            if self.match_expr(&self_id) {
+                replace!(self.tree, &self_id, SyntaxType::Expr);
+                self.adjust_single_child(self_id);
+                return true;
+            }
+
             if self.term(Token::Bracket(Brackets::LeftParenthesis)) {
                 if self.match_bool_expr(&self_id) &&
                    self.term(Token::Bracket(Brackets::RightParenthesis)) {
@@ -205,12 +357,6 @@ impl RecursiveDescentParser {
                 break;
             }
 
-            if self.match_expr(&self_id) {
-                replace!(self.tree, &self_id, SyntaxType::Expr);
-                self.adjust_single_child(self_id);
-                return true;
-            }
-
             break;
         }
 
@@ -219,19 +365,96 @@ impl RecursiveDescentParser {
         false
     }
 
-    fn match_type(&mut self) -> TokenResult {
+    // type = const type | unsigned [ type_keyword ] | signed [ type_keyword ]
+    //      | type_keyword | struct ident | enum ident | typedef_name
+    //
+    // a struct type is given by the struct's name, an unsigned one by an
+    // UnsignedType node holding the width, `unsigned` alone is `unsigned int`.
+    // `signed` is the default, it's dropped. an enum is an `int`. a TypedefName
+    // node holds the name of a typedef, a ConstType node the qualified type.
+    fn match_type(&mut self, root: &NodeId) -> bool {
+
+        let tok = match self.copy_current() {
+            Some(tok) => tok,
+            None => return false,
+        };
+
+        if self.term(Token::KeyWord(KeyWords::Const)) {
+            let self_id = insert_type!(self.tree, root, SyntaxType::ConstType);
+            if self.match_type(&self_id) {
+                return true;
+            }
+
+            self.current -= 1;
+            self.tree.remove_node(self_id, DropChildren).unwrap();
+            return false;
+        }
+
+        if let Identifier(ref name, _) = *tok {
+            if self.typedefs.contains(name) {
+                let self_id = insert_type!(self.tree, root, SyntaxType::TypedefName);
+                let name = self.match_identifier().unwrap();
+                insert!(self.tree, self_id, name);
+                return true;
+            }
+        }
+
+        if self.term(Token::KeyWord(KeyWords::Enum)) {
+            if self.match_identifier().is_none() {
+                self.current -= 1;
+                return false;
+            }
+
+            insert!(self.tree, root, Rc::new(Token::KeyWord(KeyWords::Int)));
+            return true;
+        }
+
+        if self.term(Token::KeyWord(KeyWords::Struct)) {
+            match self.match_identifier() {
+                Some(name) => { insert!(self.tree, root, name); return true; },
+                None => { self.current -= 1; return false; },
+            }
+        }
+
+        if self.term(Token::KeyWord(KeyWords::Unsigned)) {
+            let self_id = insert_type!(self.tree, root, SyntaxType::UnsignedType);
+            let width = self.match_int_keyword().unwrap_or(Rc::new(Token::KeyWord(KeyWords::Int)));
+            insert!(self.tree, self_id, width);
+            return true;
+        }
 
-        if self.current >= self.tokens.len() { return None; }
+        if self.term(Token::KeyWord(KeyWords::Signed)) {
+            let width = self.match_int_keyword().unwrap_or(Rc::new(Token::KeyWord(KeyWords::Int)));
+            insert!(self.tree, root, width);
+            return true;
+        }
 
-        if let KeyWord(ref k) = *self.tokens[self.current] {
+        if let KeyWord(ref k) = *tok {
             if k.is_type() {
-                let r = self.copy_current();
+                let r = self.copy_current().unwrap();
                 self.current += 1;
-                return r;
+                insert!(self.tree, root, r);
+                return true;
             }
         }
 
-        return None;
+        return false;
+    }
+
+    // the width following `unsigned` or `signed`.
+    fn match_int_keyword(&mut self) -> TokenResult {
+
+        let tok = self.copy_current()?;
+
+        match *tok {
+            KeyWord(KeyWords::Char) | KeyWord(KeyWords::Short) |
+            KeyWord(KeyWords::Int) | KeyWord(KeyWords::Long) => {
+                let r = self.copy_current();
+                self.current += 1;
+                r
+            },
+            _ => None,
+        }
     }
 
     fn match_variable_define_stmt(&mut self, root: &NodeId) -> bool {
@@ -239,7 +462,18 @@ impl RecursiveDescentParser {
     }
 
     fn match_global_variable_define(&mut self, root: &NodeId) -> bool {
-        return self.match_variable_define(root) && self.term(Token::Semicolon);
+        let cur = self.current;
+
+        if self.match_variable_define(root) {
+            if self.term(Token::Semicolon) { return true; }
+
+            // e.g. `int f` of a following function define, give the tokens back
+            let last = self.tree.children_ids(root).unwrap().last().unwrap().clone();
+            self.tree.remove_node(last, DropChildren).unwrap();
+        }
+
+        self.current = cur;
+        false
     }
 
     // variable_define = type variable_list
@@ -247,9 +481,7 @@ impl RecursiveDescentParser {
         let cur = self.current;
         let self_id = insert_type!(self.tree, root, SyntaxType::VariableDefine);
 
-        if let Some(t) = self.match_type() {
-            insert!(self.tree, self_id, t);
-
+        if self.match_type(&self_id) {
             if self.match_variable_list(&self_id) {
                 return true;
             }
@@ -262,9 +494,7 @@ impl RecursiveDescentParser {
 
     // variable_list = variable | variable , variable_list
     fn match_variable_list(&mut self, root: &NodeId) -> bool {
-        if let Some(v) = self.match_identifier() {
-            insert!(self.tree, root, v.clone());
-        }
+        if !self.match_variable(root) { return false; }
 
         if self.term(Token::Comma) {
             self.match_variable_list(root)
@@ -273,6 +503,43 @@ impl RecursiveDescentParser {
         }
     }
 
+    // variable = * variable | ident [ number ] | ident = right_value | ident
+    //
+    // a PointerDeclare holds the variable it declares a pointer.
+    fn match_variable(&mut self, root: &NodeId) -> bool {
+        if self.term(Token::Asterisk) {
+            let ptr_id = insert_type!(self.tree, root, SyntaxType::PointerDeclare);
+            return self.match_variable(&ptr_id);
+        }
+
+        if let Some(v) = self.match_identifier() {
+            if self.term(Token::Bracket(Brackets::LeftSquareBracket)) {
+                // the ArrayDeclare node holds the identifier and the size
+                let array_id = insert_type!(self.tree, root, SyntaxType::ArrayDeclare);
+                insert!(self.tree, array_id, v);
+
+                match self.match_number() {
+                    Some(size) => insert!(self.tree, array_id, size),
+                    None => return false,
+                };
+
+                if !self.term(Token::Bracket(Brackets::RightSquareBracket)) { return false; }
+            } else if self.term(Token::Operator(Operators::Assign)) {
+                // keep the declarator and its initializer together, like an assign_stmt
+                let init_id = insert_type!(self.tree, root, SyntaxType::AssignStmt);
+                insert!(self.tree, init_id, v);
+
+                if !self.match_right_value(&init_id) { return false; }
+            } else {
+                insert!(self.tree, root, v);
+            }
+
+            return true;
+        }
+
+        false
+    }
+
     fn match_struct_define(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
         let self_id = insert_type!(self.tree, root, SyntaxType::StructDefine);
@@ -302,34 +569,56 @@ impl RecursiveDescentParser {
         return false;
     }
 
-    //// expr = expr add_op expr_mul
-    ///      -> expr_mul expr_fix
-    fn match_expr(&mut self, root: &NodeId) -> bool {
-        if self.match_expr_mul(root) {
-            return self.match_expr_fix(root);
+    // type_define = typedef type variable ;
+    //
+    // the TypeDefine node holds the type and the declarator, like a VariableDefine.
+    // the declared name is a type from then on.
+    fn match_type_define(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::TypeDefine);
+
+        loop {
+            if !self.term(Token::KeyWord(KeyWords::Typedef)) { break; }
+            if !self.match_type(&self_id) { break; }
+            if !self.match_variable(&self_id) { break; }
+            if !self.term(Token::Semicolon) { break; }
+
+            let mut id = self.tree.children_ids(&self_id).unwrap().last().unwrap().clone();
+            while self.tree.get(&id).unwrap().data().token().is_none() {
+                id = self.tree.children_ids(&id).unwrap().next().unwrap().clone();
+            }
+
+            let name = self.tree.get(&id).unwrap().data().symbol().unwrap().to_owned();
+            self.typedefs.insert(name);
+
+            return true;
         }
 
-        false
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        return false;
     }
 
-    /// expr_fix = add_op expt_mul expr_fix | epsilon
-    fn match_expr_fix(&mut self, root: &NodeId) -> bool {
+    // enum_define = enum [ ident ] { enumerator_list } ;
+    // enumerator_list = enumerator [ , ] | enumerator , enumerator_list
+    fn match_enum_define(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::EnumDefine);
 
         loop {
-            if let Some(tok) = self.match_add_op() {
-                let id = insert!(self.tree, root, tok);
+            if !self.term(Token::KeyWord(KeyWords::Enum)) { break; }
 
-                let self_id = insert_type!(self.tree, root, SyntaxType::Expr);
-                if self.match_expr_mul(&self_id) {
-                    if self.match_expr_fix(&self_id) {
-                        self.adjust_single_child(self_id);
-                        return true;
-                    }
-                }
+            if let Some(v) = self.match_identifier() {
+                insert!(self.tree, self_id, v);
+            }
 
-                self.tree.remove_node(self_id, DropChildren).unwrap();
-                self.tree.remove_node(id, DropChildren).unwrap();
+            if !self.term(Token::Bracket(Brackets::LeftCurlyBracket)) { break; }
+            if !self.match_enumerator(&self_id) { break; }
+
+            while self.term(Token::Comma) && self.match_enumerator(&self_id) { }
+
+            if !self.term(Token::Bracket(Brackets::RightCurlyBracket)) ||
+               !self.term(Token::Semicolon) {
                 break;
             }
 
@@ -337,53 +626,168 @@ impl RecursiveDescentParser {
         }
 
         self.current = cur;
-        false
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        return false;
     }
 
-    /// expr_mul = expr_mul mul_op expr_factor
-    ///         -> expr_factor expr_mul_fix
-    fn match_expr_mul(&mut self, root: &NodeId) -> bool {
-        if self.match_expr_factor(root) {
-            return self.match_expr_mul_fix(root);
+    // enumerator = ident [ = expr ]
+    //
+    // an Enumerator node holds the identifier and the value, if it's given.
+    fn match_enumerator(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::Enumerator);
+
+        loop {
+            match self.match_identifier() {
+                Some(id) => insert!(self.tree, self_id, id),
+                None => break,
+            };
+
+            if self.term(Token::Operator(Operators::Assign)) && !self.match_expr(&self_id) { break; }
+
+            return true;
         }
 
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
         false
     }
 
-    /// expr_mul_fix = mul_op expr_factor expr_mul_fix | epsilon
-    fn match_expr_mul_fix(&mut self, root: &NodeId) -> bool {
-        if let Some(tok) = self.match_mul_op() {
-            insert!(self.tree, root, tok);
+    /// expr = expr_level(0)
+    fn match_expr(&mut self, root: &NodeId) -> bool {
+        self.match_expr_level(root, 0)
+    }
+
+    /// expr_level(n) = expr_level(n) expr_op(n) expr_level(n + 1)
+    ///              -> expr_level(n + 1) { expr_op(n) expr_level(n + 1) }
+    ///
+    /// an `Expr` node only holds operators of one level, left-associative. when
+    /// the level has operators they are put under `root` with their operands,
+    /// otherwise `root` gets the content of the next level.
+    fn match_expr_level(&mut self, root: &NodeId, level: usize) -> bool {
+        if level == EXPR_LEVELS {
+            return self.match_expr_factor(root);
+        }
 
-            let self_id = insert_type!(self.tree, root, SyntaxType::Expr);
-            if !self.match_expr_factor(&self_id) {
-                self.tree.remove_node(self_id, DropChildren).unwrap();
+        let cur = self.current;
+        let count = self.tree.children(root).unwrap().count();
+
+        let first_id = insert_type!(self.tree, root, SyntaxType::Expr);
+        if !self.match_expr_level(&first_id, level + 1) {
+            self.tree.remove_node(first_id, DropChildren).unwrap();
+            return false;
+        }
+
+        let mut tok = self.match_expr_op(level);
+        if tok.is_none() {
+            self.tree.remove_node(first_id, LiftChildren).unwrap();
+            return true;
+        }
+
+        self.adjust_single_child(first_id);
+
+        while let Some(op) = tok {
+            insert!(self.tree, root, op);
+
+            let operand_id = insert_type!(self.tree, root, SyntaxType::Expr);
+            if !self.match_expr_level(&operand_id, level + 1) {
+                // drop everything this level put under `root`
+                let ids: Vec<NodeId> = self.tree.children_ids(root).unwrap()
+                                           .skip(count).cloned().collect();
+                for id in ids {
+                    self.tree.remove_node(id, DropChildren).unwrap();
+                }
+
+                self.current = cur;
                 return false;
             }
 
-            self.adjust_single_child(self_id);
-
-            return self.match_expr_mul_fix(root);
+            self.adjust_single_child(operand_id);
+            tok = self.match_expr_op(level);
         }
 
         true
     }
 
-    /// expr_factor = (expr) | ident
+    /// expr_factor = (type) expr_factor | sizeof | (assign_stmt) | (expr) | - expr_factor
+    ///             | & left_value | * expr_factor | inc_dec | member_access | array_index
+    ///             | func_call | ident
     fn match_expr_factor(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
 
         loop {
-            // (expr)
+            // (type) expr_factor
+            if self.match_cast(root) {
+                return true;
+            }
+
+            if self.match_sizeof(root) {
+                return true;
+            }
+
+            // (assign_stmt) | (expr)
             if self.term(Token::Bracket(Brackets::LeftParenthesis)) {
-                if self.match_expr(root) {
-                    if self.term(Token::Bracket(Brackets::RightParenthesis)) {
-                        return true;
-                    }
+                let self_id = insert_type!(self.tree, root, SyntaxType::Expr);
+                if (self.match_assign_stmt(&self_id) || self.match_expr(&self_id)) &&
+                   self.term(Token::Bracket(Brackets::RightParenthesis)) {
+                    self.adjust_single_child(self_id);
+                    return true;
+                }
+
+                self.tree.remove_node(self_id, DropChildren).unwrap();
+                break;
+            }
+
+            // - expr_factor, an Expr with the operator and a single operand
+            if self.term(Token::Operator(Operators::Minus)) {
+                let self_id = insert_type!(self.tree, root, SyntaxType::Expr);
+                insert!(self.tree, self_id, Rc::new(Token::Operator(Operators::Minus)));
+
+                if self.match_expr_factor(&self_id) {
+                    return true;
+                }
+
+                self.tree.remove_node(self_id, DropChildren).unwrap();
+                break;
+            }
+
+            // & left_value, an AddressOf holding the variable
+            if self.term(Token::Operator(Operators::And)) {
+                let self_id = insert_type!(self.tree, root, SyntaxType::AddressOf);
+
+                if self.match_left_value(&self_id) {
+                    return true;
                 }
+
+                self.tree.remove_node(self_id, DropChildren).unwrap();
                 break;
             }
 
+            // * expr_factor
+            if self.match_dereference(root) {
+                return true;
+            }
+
+            // ++ left_value | left_value ++
+            if self.match_inc_dec(root) {
+                return true;
+            }
+
+            // member_access
+            if self.match_member_access(root) {
+                return true;
+            }
+
+            // array_index
+            if self.match_array_index(root) {
+                return true;
+            }
+
+            // func_call
+            if self.match_func_call(root) {
+                return true;
+            }
+
             // ident
             if let Some(tok) = self.match_expr_ident() {
                 insert!(self.tree, root, tok);
@@ -399,55 +803,156 @@ impl RecursiveDescentParser {
 
     // - `stmt_factor`
     fn match_stmt(&mut self, root: &NodeId) -> bool {
-        self.match_stmt_factor(root)
+        let cur = self.current;
+        if !self.match_stmt_factor(root) { return false; }
+
+        // e.g. a `return;` has no terminal to take the position of.
+        let id = self.tree.get(root).unwrap().children().last().cloned();
+        if let (Some(id), Some(span)) = (id, self.token_span(cur)) {
+            self.spans.insert(id, span);
+        }
+
+        true
     }
 
+    // - `labeled_stmt`
+    // - `case_stmt`
+    // - `default_stmt`
     // - `stmt_single` `;`
     // - `stmt_block`
     // - `stmt_control`
     // - `;`
     fn match_stmt_factor(&mut self, root: &NodeId) -> bool {
+        self.match_labeled_stmt(root) ||
+        self.match_case_stmt(root) ||
+        self.match_default_stmt(root) ||
         self.match_stmt_block(root) ||
         self.match_stmt_control(root) ||
         self.match_stmt_single(root) && self.term(Token::Semicolon) ||
-        self.term(Token::Semicolon)
-    }
-
-    // - `assign_stmt`
-    // - `break_stmt`
-    // - `return_stmt`
-    fn match_stmt_single(&mut self, root: &NodeId) -> bool {
-        self.match_assign_stmt(root) ||
-        self.match_break_stmt(root) ||
-        self.match_return_stmt(root) ||
-        self.match_variable_define_stmt(root) ||
-        self.match_func_call(root)
+        self.match_empty_stmt(root)
     }
 
-    // - `if_stmt`
-    // - `while_loop`
-    // - `for_loop`
-    fn match_stmt_control(&mut self, root: &NodeId) -> bool {
-        self.match_if_stmt(root) ||
-        self.match_while_loop(root) ||
-        self.match_for_loop(root)
-    }
-
-    fn match_stmt_list(&mut self, root: &NodeId) -> bool {
-        while self.match_stmt(root) {}
+    // `;` is an EmptyStmt, so e.g. `while (f());` still has a body.
+    fn match_empty_stmt(&mut self, root: &NodeId) -> bool {
+        if !self.term(Token::Semicolon) { return false; }
 
+        insert_type!(self.tree, root, SyntaxType::EmptyStmt);
         true
     }
 
-    fn match_stmt_block(&mut self, root: &NodeId) -> bool {
+    // `ident` `:` `stmt`
+    //
+    // a LabeledStmt holds the label, then the statement it's on.
+    fn match_labeled_stmt(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
-        let self_id = insert_type!(self.tree, root, SyntaxType::StmtBlock);
+        let self_id = insert_type!(self.tree, root, SyntaxType::LabeledStmt);
 
         loop {
-            // `{`
-            if !self.term(Token::Bracket(Brackets::LeftCurlyBracket)) { break; }
-
-            // `stmt_list`
+            match self.match_identifier() {
+                Some(id) => insert!(self.tree, self_id, id),
+                _ => break,
+            };
+
+            if !self.term(Token::Colon) { break; }
+            if !self.match_stmt(&self_id) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // `case` `expr` `:` `stmt`
+    //
+    // a CaseStmt holds the value, then the statement it's on. like a label it
+    // only marks where the switch jumps to, the statements after it follow.
+    fn match_case_stmt(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::CaseStmt);
+
+        loop {
+            if !self.term(Token::KeyWord(KeyWords::Case)) { break; }
+
+            let value_id = insert_type!(self.tree, &self_id, SyntaxType::Expr);
+            if !self.match_expr(&value_id) { break; }
+            self.adjust_single_child(value_id);
+
+            if !self.term(Token::Colon) { break; }
+            if !self.match_stmt(&self_id) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // `default` `:` `stmt`
+    fn match_default_stmt(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::DefaultStmt);
+
+        loop {
+            if !self.term(Token::KeyWord(KeyWords::Default)) { break; }
+            if !self.term(Token::Colon) { break; }
+            if !self.match_stmt(&self_id) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // - `assign_stmt`
+    // - `break_stmt`
+    // - `continue_stmt`
+    // - `goto_stmt`
+    // - `return_stmt`
+    // - `inc_dec`
+    fn match_stmt_single(&mut self, root: &NodeId) -> bool {
+        self.match_assign_stmt(root) ||
+        self.match_break_stmt(root) ||
+        self.match_continue_stmt(root) ||
+        self.match_goto_stmt(root) ||
+        self.match_return_stmt(root) ||
+        self.match_variable_define_stmt(root) ||
+        self.match_func_call(root) ||
+        self.match_inc_dec(root)
+    }
+
+    // - `if_stmt`
+    // - `while_loop`
+    // - `do_while_stmt`
+    // - `for_loop`
+    // - `switch_stmt`
+    fn match_stmt_control(&mut self, root: &NodeId) -> bool {
+        self.match_if_stmt(root) ||
+        self.match_while_loop(root) ||
+        self.match_do_while_stmt(root) ||
+        self.match_for_loop(root) ||
+        self.match_switch_stmt(root)
+    }
+
+    fn match_stmt_list(&mut self, root: &NodeId) -> bool {
+        while self.match_stmt(root) {}
+
+        true
+    }
+
+    fn match_stmt_block(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::StmtBlock);
+
+        loop {
+            // `{`
+            if !self.term(Token::Bracket(Brackets::LeftCurlyBracket)) { break; }
+
+            // `stmt_list`
             if !self.match_stmt_list(&self_id) { break; }
 
             // '}'
@@ -476,17 +981,17 @@ impl RecursiveDescentParser {
 
             // expr_opt1 ;
             let expr_opt1 = insert_type!(self.tree, &self_id, SyntaxType::ExprOpt);
-            self.match_assign_stmt(&expr_opt1);
+            self.match_expr_stmt(&expr_opt1);
             if !self.term(Token::Semicolon) { break; }
 
             // expr_opt2 ;
             let expr_opt2 = insert_type!(self.tree, &self_id, SyntaxType::ExprOpt);
-            self.match_bool_expr(&expr_opt2);
+            self.match_bool_expr_node(&expr_opt2);
             if !self.term(Token::Semicolon) { break; }
 
             // expr_opt3
             let expr_opt3 = insert_type!(self.tree, &self_id, SyntaxType::ExprOpt);
-            if !self.match_assign_stmt(&expr_opt3) { break; }
+            self.match_expr_stmt(&expr_opt3);
 
             // ')'
             if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
@@ -502,6 +1007,25 @@ impl RecursiveDescentParser {
         false
     }
 
+    /// expr_stmt = assign_stmt | expr
+    ///
+    /// an expression evaluated for its side effects, e.g. the step of a `for`,
+    /// kept as a single child of `root`.
+    fn match_expr_stmt(&mut self, root: &NodeId) -> bool {
+        if self.match_assign_stmt(root) {
+            return true;
+        }
+
+        let self_id = insert_type!(self.tree, root, SyntaxType::Expr);
+        if self.match_expr(&self_id) {
+            self.adjust_single_child(self_id);
+            return true;
+        }
+
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
     // `while` `(` `bool_expr` `)` `stmt`
     fn match_while_loop(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
@@ -515,7 +1039,38 @@ impl RecursiveDescentParser {
             if !self.term(Token::Bracket(Brackets::LeftParenthesis)) { break; }
 
             // `bool_expr`
-            if !self.match_bool_expr(&self_id) { break; }
+            if !self.match_bool_expr_node(&self_id) { break; }
+
+            // ')'
+            if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
+
+            // `stmt`
+            if !self.match_stmt(&self_id) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // `switch` `(` `expr` `)` `stmt`
+    fn match_switch_stmt(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::SwitchStmt);
+
+        loop {
+            // `switch`
+            if !self.term(Token::KeyWord(KeyWords::Switch)) { break; }
+
+            // `(`
+            if !self.term(Token::Bracket(Brackets::LeftParenthesis)) { break; }
+
+            // `expr`
+            let cond_id = insert_type!(self.tree, &self_id, SyntaxType::Expr);
+            if !self.match_expr(&cond_id) { break; }
+            self.adjust_single_child(cond_id);
 
             // ')'
             if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
@@ -531,6 +1086,43 @@ impl RecursiveDescentParser {
         false
     }
 
+    // `do` `stmt` `while` `(` `bool_expr` `)` `;`
+    //
+    // the DoWhileStmt node holds the body first, then the condition.
+    fn match_do_while_stmt(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::DoWhileStmt);
+
+        loop {
+            // `do`
+            if !self.term(Token::KeyWord(KeyWords::Do)) { break; }
+
+            // `stmt`
+            if !self.match_stmt(&self_id) { break; }
+
+            // `while`
+            if !self.term(Token::KeyWord(KeyWords::While)) { break; }
+
+            // `(`
+            if !self.term(Token::Bracket(Brackets::LeftParenthesis)) { break; }
+
+            // `bool_expr`
+            if !self.match_bool_expr_node(&self_id) { break; }
+
+            // ')'
+            if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
+
+            // `;`
+            if !self.term(Token::Semicolon) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
     // assign_stmt = left_value = right_value
     fn match_assign_stmt(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
@@ -554,43 +1146,41 @@ impl RecursiveDescentParser {
         false
     }
 
-    // if_stmt = if ( bool_expr ) stmt else stmt
+    // if_stmt = if ( bool_expr ) stmt | if ( bool_expr ) stmt else stmt
+    //
+    // the IfStmt node holds the condition, the `then` stmt and an optional ElseStmt.
+    // `else if` is just an else stmt which is an if_stmt, so chains nest in the ElseStmt.
     fn match_if_stmt(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::IfStmt);
 
         loop {
             // if
-            if self.term(Token::KeyWord(KeyWords::If)) {
-                let if_id = insert_type!(self.tree, root, SyntaxType::IfStmt);
-
-                // ( bool_expr ) stmt
-                if self.term(Token::Bracket(Brackets::LeftParenthesis)) &&
-                   self.match_bool_expr(&if_id) &&
-                   self.term(Token::Bracket(Brackets::RightParenthesis)) &&
-                   self.match_stmt(&if_id) {
-                    // else
-                    if self.term(Token::KeyWord(KeyWords::Else)) {
-                        let else_cur = self.current;
-                        let else_id = insert_type!(self.tree, root, SyntaxType::ElseStmt);
-
-                        // stmt
-                        if self.match_stmt(&else_id) { return true; }
-
-                        self.current = else_cur;
-                        self.tree.remove_node(else_id, DropChildren).unwrap();
-                    }
+            if !self.term(Token::KeyWord(KeyWords::If)) { break; }
 
-                    return true;
-                }
+            // `(`
+            if !self.term(Token::Bracket(Brackets::LeftParenthesis)) { break; }
 
-                self.tree.remove_node(if_id, DropChildren).unwrap();
-                break;
+            // `bool_expr`
+            if !self.match_bool_expr_node(&self_id) { break; }
+
+            // `)`
+            if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
+
+            // `stmt`
+            if !self.match_stmt(&self_id) { break; }
+
+            // else `stmt`, binds to the nearest `if`
+            if self.term(Token::KeyWord(KeyWords::Else)) {
+                let else_id = insert_type!(self.tree, self_id, SyntaxType::ElseStmt);
+                if !self.match_stmt(&else_id) { break; }
             }
 
-            break;
+            return true;
         }
 
         self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
         false
     }
 
@@ -621,18 +1211,50 @@ impl RecursiveDescentParser {
         false
     }
 
-    // - `func_call`
-    // - `bool_expr`
+    // `continue`
+    fn match_continue_stmt(&mut self, root: &NodeId) -> bool {
+        if self.term(Token::KeyWord(KeyWords::Continue)) {
+            insert_type!(self.tree, root, SyntaxType::ContinueStmt);
+            return true
+        }
+
+        false
+    }
+
+    // `goto` `ident`
+    fn match_goto_stmt(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        if !self.term(Token::KeyWord(KeyWords::Goto)) { return false; }
+
+        match self.match_identifier() {
+            Some(id) => {
+                let self_id = insert_type!(self.tree, root, SyntaxType::GotoStmt);
+                insert!(self.tree, self_id, id);
+                true
+            },
+            None => {
+                self.current = cur;
+                false
+            },
+        }
+    }
+
+    // - `right_value`
     // - `epsilon`
     fn match_return_type(&mut self, root: &NodeId) -> bool {
-        let _ = self.match_func_call(root) ||
-                self.match_bool_expr(root);
+        self.match_right_value(root);
 
         true
     }
 
-    // left_value = ident
+    // left_value = * expr_factor | member_access | array_index | ident
     fn match_left_value(&mut self, root: &NodeId) -> bool {
+        if self.match_dereference(root) ||
+           self.match_member_access(root) ||
+           self.match_array_index(root) {
+            return true;
+        }
+
         if let Some(id) = self.match_identifier() {
             insert!(self.tree, root, id);
 
@@ -641,35 +1263,32 @@ impl RecursiveDescentParser {
         return false;
     }
 
-    // right_value = bool_expr
-    fn match_right_value(&mut self, root: &NodeId) -> bool {
-        self.match_bool_expr(root)
-    }
-
-    // `func_ret_type` `func_name` `(` `func_param_list` `)` `;`
-    fn match_function_declare(&mut self, root: &NodeId) -> bool {
+    // inc_dec = inc_dec_op left_value | left_value inc_dec_op
+    //
+    // a PrefixIncDec holds the operator and the operand, a PostfixIncDec the
+    // operand and the operator, both in the order of the source.
+    fn match_inc_dec(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
-        let self_id = insert_type!(self.tree, root, SyntaxType::FuncDeclare);
 
-        loop {
-            // type
-            match self.match_type() {
-                Some(t) => insert!(self.tree, self_id, t),
-                _ => break,
-            };
+        if let Some(op) = self.match_inc_dec_op() {
+            let self_id = insert_type!(self.tree, root, SyntaxType::PrefixIncDec);
+            insert!(self.tree, self_id, op);
 
-            // func_name
-            match self.match_identifier() {
-                Some(id) => insert!(self.tree, self_id, id),
-                _ => break,
-            };
+            if self.match_left_value(&self_id) {
+                return true;
+            }
 
-            if !self.term(Token::Bracket(Brackets::LeftParenthesis)) { break; }
-            if !self.match_func_param_list(&self_id) { break; }
-            if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
-            if !self.term(Token::Semicolon) { break; }
+            self.current = cur;
+            self.tree.remove_node(self_id, DropChildren).unwrap();
+            return false;
+        }
 
-            return true;
+        let self_id = insert_type!(self.tree, root, SyntaxType::PostfixIncDec);
+        if self.match_left_value(&self_id) {
+            if let Some(op) = self.match_inc_dec_op() {
+                insert!(self.tree, self_id, op);
+                return true;
+            }
         }
 
         self.current = cur;
@@ -677,33 +1296,34 @@ impl RecursiveDescentParser {
         false
     }
 
-    // - `func_ret_type` `func_name` `(` `func_param_list` `)` `{` `func_body` `}`
-    fn match_function_define(&mut self, root: &NodeId) -> bool {
-        let cur = self.current;
-        let self_id = insert_type!(self.tree, root, SyntaxType::FuncDefine);
+    // ++ | --
+    fn match_inc_dec_op(&mut self) -> TokenResult {
+        let tok = self.copy_current()?;
 
-        loop {
-            // type
-            match self.match_type() {
-                Some(t) => insert!(self.tree, self_id, t),
-                _ => break,
-            };
+        return match *tok {
+            Token::Operator(Operators::DoubleAdd) |
+            Token::Operator(Operators::DoubleMinus) => {
+                self.current += 1;
+                self.copy_previous()
+            },
+            _ => None,
+        }
+    }
 
-            // func_name
-            match self.match_identifier() {
-                Some(id) => insert!(self.tree, self_id, id),
-                _ => break,
-            };
+    // cast = ( type ) expr_factor
+    //
+    // the Cast node holds the type and the operand.
+    fn match_cast(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::Cast);
 
+        loop {
             if !self.term(Token::Bracket(Brackets::LeftParenthesis)) { break; }
-            if !self.match_func_param_list(&self_id) { break; }
-            if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
-            if !self.term(Token::Bracket(Brackets::LeftCurlyBracket)) { break; }
 
-            // func_body
-            if !self.match_stmt_list(&self_id) { break; }
+            if !self.match_type(&self_id) { break; }
 
-            if !self.term(Token::Bracket(Brackets::RightCurlyBracket)) { break; }
+            if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
+            if !self.match_expr_factor(&self_id) { break; }
 
             return true;
         }
@@ -713,45 +1333,288 @@ impl RecursiveDescentParser {
         false
     }
 
-    // - `func_param` `func_param_list_tail`
-    // - `epsilon`
-    fn match_func_param_list(&mut self, root: &NodeId) -> bool {
-
-        if self.match_func_param(root) {
-            return self.match_func_param_list_tail(root);
-        }
-
-        true
-    }
-
-    // - `,` `func_param` `func_param_list_tail`
-    // - `epsilon`
-    fn match_func_param_list_tail(&mut self, root: &NodeId) -> bool {
-
-        if self.term(Token::Comma) {
-            return self.match_func_param(root) &&
-                   self.match_func_param_list_tail(root);
-        }
-
-        true
+    // sizeof = sizeof ( type ) | sizeof expr_factor
+    fn match_sizeof(&mut self, root: &NodeId) -> bool {
+        self.match_sizeof_type(root) || self.match_sizeof_expr(root)
     }
 
-    // `func_param_type` `func_param_name`
-    fn match_func_param(&mut self, root: &NodeId) -> bool {
+    fn match_sizeof_type(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
-        let self_id = insert_type!(self.tree, root, SyntaxType::FuncParam);
+        let self_id = insert_type!(self.tree, root, SyntaxType::Sizeof);
 
         loop {
-            // func_param_type
-            match self.match_type() {
-                Some(t) => insert!(self.tree, self_id, t),
-                _ => break,
-            };
-
-            match self.match_identifier() {
-                Some(id) => insert!(self.tree, self_id, id),
-                _ => break,
-            };
+            if !self.term(Token::KeyWord(KeyWords::Sizeof)) { break; }
+            if !self.term(Token::Bracket(Brackets::LeftParenthesis)) { break; }
+            if !self.match_type(&self_id) { break; }
+            if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // the operand is kept in an Expr node, which tells it from a struct's name.
+    fn match_sizeof_expr(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::Sizeof);
+
+        loop {
+            if !self.term(Token::KeyWord(KeyWords::Sizeof)) { break; }
+
+            let expr_id = insert_type!(self.tree, self_id, SyntaxType::Expr);
+            if !self.match_expr_factor(&expr_id) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // dereference = * expr_factor
+    //
+    // the Dereference node holds the pointer.
+    fn match_dereference(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        if !self.term(Token::Asterisk) { return false; }
+
+        let self_id = insert_type!(self.tree, root, SyntaxType::Dereference);
+        if self.match_expr_factor(&self_id) {
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // member_access = (array_index | ident) member_op ident { member_op ident }
+    // member_op = . | ->
+    //
+    // the MemberAccess node holds the struct, the operator and the member name,
+    // `a.b.c` is `(a.b).c`.
+    fn match_member_access(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let mut self_id = insert_type!(self.tree, root, SyntaxType::MemberAccess);
+
+        loop {
+            if !self.match_array_index(&self_id) {
+                match self.match_identifier() {
+                    Some(id) => insert!(self.tree, self_id, id),
+                    _ => break,
+                };
+            }
+
+            let mut op = self.match_member_op();
+            if op.is_none() { break; }
+
+            while let Some(tok) = op {
+                insert!(self.tree, self_id, tok);
+                match self.match_identifier() {
+                    Some(id) => insert!(self.tree, self_id, id),
+                    _ => break,
+                };
+
+                op = self.match_member_op();
+                if op.is_some() {
+                    // the access so far becomes the struct of the next one
+                    let outer_id = insert_type!(self.tree, root, SyntaxType::MemberAccess);
+                    self.tree.move_node(&self_id, MoveBehavior::ToParent(&outer_id)).unwrap();
+                    self_id = outer_id;
+                }
+            }
+
+            // a member name is missing after the last operator
+            if self.tree.children(&self_id).unwrap().count() != 3 { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    fn match_member_op(&mut self) -> TokenResult {
+        if self.term(Token::Dot) { return Some(Rc::new(Token::Dot)); }
+        if self.term(Token::Arrow) { return Some(Rc::new(Token::Arrow)); }
+
+        None
+    }
+
+    // array_index = ident [ expr ]
+    //
+    // the ArrayIndex node holds the array and the index.
+    fn match_array_index(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::ArrayIndex);
+
+        loop {
+            match self.match_identifier() {
+                Some(id) => insert!(self.tree, self_id, id),
+                _ => break,
+            };
+
+            if !self.term(Token::Bracket(Brackets::LeftSquareBracket)) { break; }
+
+            // the index stays a single child, like `(expr)`
+            let index_id = insert_type!(self.tree, self_id, SyntaxType::Expr);
+            if !self.match_expr(&index_id) { break; }
+            self.adjust_single_child(index_id);
+
+            if !self.term(Token::Bracket(Brackets::RightSquareBracket)) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // right_value = assign_stmt | conditional_expr
+    //
+    // assignment is right associative, `a = b = 5` assigns `b = 5` first.
+    fn match_right_value(&mut self, root: &NodeId) -> bool {
+        self.match_assign_stmt(root) ||
+        self.match_conditional_expr(root)
+    }
+
+    // conditional_expr = bool_expr | bool_expr ? right_value : conditional_expr
+    //
+    // the TernaryExpr node holds the condition and both arms.
+    fn match_conditional_expr(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::TernaryExpr);
+
+        loop {
+            // `bool_expr`
+            if !self.match_bool_expr_node(&self_id) { break; }
+
+            // without `?` it's a plain bool_expr
+            if !self.term(Token::Question) {
+                self.adjust_single_child(self_id);
+                return true;
+            }
+
+            // `right_value`
+            if !self.match_right_value(&self_id) { break; }
+
+            // `:`
+            if !self.term(Token::Colon) { break; }
+
+            // `conditional_expr`
+            if !self.match_conditional_expr(&self_id) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // `func_ret_type` `func_name` `(` `func_param_list` `)` `;`
+    fn match_function_declare(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::FuncDeclare);
+
+        loop {
+            // type
+            if !self.match_type(&self_id) { break; }
+
+            // func_name
+            match self.match_identifier() {
+                Some(id) => insert!(self.tree, self_id, id),
+                _ => break,
+            };
+
+            if !self.term(Token::Bracket(Brackets::LeftParenthesis)) { break; }
+            if !self.match_func_param_list(&self_id) { break; }
+            if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
+            if !self.term(Token::Semicolon) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // - `func_ret_type` `func_name` `(` `func_param_list` `)` `{` `func_body` `}`
+    fn match_function_define(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::FuncDefine);
+
+        loop {
+            // type
+            if !self.match_type(&self_id) { break; }
+
+            // func_name
+            match self.match_identifier() {
+                Some(id) => insert!(self.tree, self_id, id),
+                _ => break,
+            };
+
+            if !self.term(Token::Bracket(Brackets::LeftParenthesis)) { break; }
+            if !self.match_func_param_list(&self_id) { break; }
+            if !self.term(Token::Bracket(Brackets::RightParenthesis)) { break; }
+            if !self.term(Token::Bracket(Brackets::LeftCurlyBracket)) { break; }
+
+            // func_body
+            if !self.match_stmt_list(&self_id) { break; }
+
+            if !self.term(Token::Bracket(Brackets::RightCurlyBracket)) { break; }
+
+            return true;
+        }
+
+        self.current = cur;
+        self.tree.remove_node(self_id, DropChildren).unwrap();
+        false
+    }
+
+    // - `func_param` `func_param_list_tail`
+    // - `epsilon`
+    fn match_func_param_list(&mut self, root: &NodeId) -> bool {
+
+        if self.match_func_param(root) {
+            return self.match_func_param_list_tail(root);
+        }
+
+        true
+    }
+
+    // - `,` `func_param` `func_param_list_tail`
+    // - `epsilon`
+    fn match_func_param_list_tail(&mut self, root: &NodeId) -> bool {
+
+        if self.term(Token::Comma) {
+            return self.match_func_param(root) &&
+                   self.match_func_param_list_tail(root);
+        }
+
+        true
+    }
+
+    // `func_param_type` `func_param_name`
+    fn match_func_param(&mut self, root: &NodeId) -> bool {
+        let cur = self.current;
+        let self_id = insert_type!(self.tree, root, SyntaxType::FuncParam);
+
+        loop {
+            // func_param_type
+            if !self.match_type(&self_id) { break; }
+
+            match self.match_identifier() {
+                Some(id) => insert!(self.tree, self_id, id),
+                _ => break,
+            };
 
             return true;
         }
@@ -762,6 +1625,8 @@ impl RecursiveDescentParser {
     }
 
     // `func_name` `(` `func_arg_list` `)`
+    //
+    // a FuncCall holds the callee's name, then a FuncArg per argument in order.
     fn match_func_call(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
         let self_id = insert_type!(self.tree, root, SyntaxType::FuncCall);
@@ -806,15 +1671,13 @@ impl RecursiveDescentParser {
         true
     }
 
+    // `bool_expr`
     fn match_func_arg(&mut self, root: &NodeId) -> bool {
         let cur = self.current;
         let self_id = insert_type!(self.tree, root, SyntaxType::FuncArg);
 
         loop {
-            match self.match_expr_ident() {
-                Some(id) => insert!(self.tree, self_id, id),
-                _ => break,
-            };
+            if !self.match_right_value(&self_id) { break; }
 
             return true;
         }
@@ -826,9 +1689,9 @@ impl RecursiveDescentParser {
 
     // > | >= | < | <=
     fn match_cmp_op(&mut self) -> TokenResult {
-        if self.current >= self.tokens.len() { return None; }
+        let tok = self.copy_current()?;
 
-        return match *self.tokens[self.current] {
+        return match *tok {
             Token::Operator(Operators::Greater) |
             Token::Operator(Operators::GreaterEqual) |
             Token::Operator(Operators::Less) |
@@ -842,9 +1705,9 @@ impl RecursiveDescentParser {
 
     // == | !=
     fn match_equal_op(&mut self) -> TokenResult {
-        if self.current >= self.tokens.len() { return None; }
+        let tok = self.copy_current()?;
 
-        return match *self.tokens[self.current] {
+        return match *tok {
             Token::Operator(Operators::Equal) |
             Token::Operator(Operators::NotEqual) => {
                 self.current += 1;
@@ -854,41 +1717,59 @@ impl RecursiveDescentParser {
         }
     }
 
-    fn match_add_op(&mut self) -> TokenResult {
-        if self.term(Token::Operator(Operators::Add)) {
-            return self.copy_previous();
-        }
-
-        if self.term(Token::Operator(Operators::Minus)) {
-            return self.copy_previous();
-        }
-
-        None
-    }
+    // binary operators of `expr_level(level)`, loosest first:
+    // `|`, `^`, `&`, `<< >>`, `+ -`, `* / %`
+    fn match_expr_op(&mut self, level: usize) -> TokenResult {
+        let tok = self.copy_current()?;
+
+        let matched = match (level, &*tok) {
+            (0, &Token::Operator(Operators::Or)) |
+            (1, &Token::Operator(Operators::Xor)) |
+            (2, &Token::Operator(Operators::And)) |
+            (3, &Token::Operator(Operators::ShiftLeft)) |
+            (3, &Token::Operator(Operators::ShiftRight)) |
+            (4, &Token::Operator(Operators::Add)) |
+            (4, &Token::Operator(Operators::Minus)) |
+            (5, &Token::Operator(Operators::Division)) |
+            (5, &Token::Operator(Operators::Mod)) => true,
+            (5, &Token::Asterisk) => {
+                self.current += 1;
+                return Some(Rc::new(Token::Operator(Operators::Mul)));
+            },
+            _ => false,
+        };
 
-    fn match_mul_op(&mut self) -> TokenResult {
-        if self.term(Token::Operator(Operators::Division)) {
+        if matched {
+            self.current += 1;
             return self.copy_previous();
         }
 
-        if self.term(Token::Asterisk) {
-            return Some(Rc::new(Token::Operator(Operators::Mul)));
-        }
-
         None
     }
 
     fn match_expr_ident(&mut self) -> TokenResult {
         if let Some(t) = self.match_identifier() { return Some(t); }
         if let Some(t) = self.match_number() { return Some(t); }
+        if let Some(t) = self.match_literal_str() { return Some(t); }
 
         None
     }
 
     fn match_identifier(&mut self) -> TokenResult {
-        if self.current >= self.tokens.len() { return None; }
+        let tok = self.copy_current()?;
+
+        if let Identifier(_, _) = *tok {
+            self.current += 1;
+            return self.copy_previous();
+        }
+
+        return None;
+    }
+
+    fn match_literal_str(&mut self) -> TokenResult {
+        let tok = self.copy_current()?;
 
-        if let Identifier(_, _) = *self.tokens[self.current] {
+        if let LiteralStr(_) = *tok {
             self.current += 1;
             return self.copy_previous();
         }
@@ -897,9 +1778,9 @@ impl RecursiveDescentParser {
     }
 
     fn match_number(&mut self) -> TokenResult {
-        if self.current >= self.tokens.len() { return None; }
+        let tok = self.copy_current()?;
 
-        if let Number(_) = *self.tokens[self.current] {
+        if let Number(_) = *tok {
             self.current += 1;
             return self.copy_previous();
         }
@@ -915,23 +1796,44 @@ impl RecursiveDescentParser {
         }
     }
 
-    fn copy_previous(&self) -> TokenResult {
+    fn copy_previous(&mut self) -> TokenResult {
         if self.current == 0 { return None; }
-        return Some(self.tokens[self.current - 1].clone())
+        return self.tokens.get(self.current - 1)
     }
 
-    fn copy_current(&self) -> TokenResult {
-        if self.current >= self.tokens.len() { return None; }
-        return Some(self.tokens[self.current].clone())
+    fn copy_current(&mut self) -> TokenResult {
+        return self.tokens.get(self.current)
     }
 
-    fn term(&mut self, tok: Token) -> bool {
+    // the tokens of the definitions from the `first`-th one are about to be
+    // dropped, their terminals keep the spans. returns the number of definitions.
+    fn keep_spans(&mut self, first: usize) -> usize {
+        let spans = self.tokens.spans_before(self.current);
+        let definitions: Vec<NodeId> = self.tree.get(&self.root_id()).unwrap().children().clone();
 
-        if self.current >= self.tokens.len() {
-            return false;
+        let mut stack: Vec<NodeId> = definitions.iter().skip(first).cloned().collect();
+        while let Some(id) = stack.pop() {
+            let node = self.tree.get(&id).unwrap();
+            if let Some(tok) = node.data().token() {
+                if let Some(&span) = spans.get(&(&*tok as *const Token)) {
+                    self.spans.insert(id.clone(), span);
+                }
+            }
+
+            stack.extend(node.children().iter().cloned());
         }
 
-        if *self.tokens[self.current] == tok {
+        definitions.len()
+    }
+
+    fn term(&mut self, tok: Token) -> bool {
+
+        let current = match self.copy_current() {
+            Some(current) => current,
+            None => return false,
+        };
+
+        if *current == tok {
             self.current += 1;
             return true;
         }
@@ -941,35 +1843,54 @@ impl RecursiveDescentParser {
 
     #[allow(dead_code)]
     #[cfg(debug_assertions)]
-    fn peek<'a>(&'a self) -> Option<&'a Token> {
-        if self.current >= self.tokens.len() {
-            return None;
-        }
-
-        return Some(&self.tokens[self.current]);
+    fn peek(&mut self) -> TokenResult {
+        self.copy_current()
     }
-}
 
-impl Parser for RecursiveDescentParser {
-    fn run(&mut self) -> ParserResult {
+    // top level definitions to the end of the input, false at the first one
+    // which doesn't match. the tokens of every matched one are dropped.
+    fn match_definitions(&mut self) -> bool {
         let ref id = self.root_id();
-        let mut last_pos = self.tokens.len();
+        let mut last_pos = None;
+        let mut spanned = 0;
 
         loop {
-            if self.current == self.tokens.len() { break; }
-            if self.current == last_pos {
-                return Err(ParseErrInfo {
-                        err_type: ParseError::SyntaxError
-                       });
-            }
+            spanned = self.keep_spans(spanned);
+            self.tokens.commit(self.current);
 
-            last_pos = self.current;
+            if self.copy_current().is_none() { return true; }
+            if last_pos == Some(self.current) { return false; }
+
+            last_pos = Some(self.current);
 
             self.match_struct_define(id);
+            self.match_enum_define(id);
+            self.match_type_define(id);
             self.match_function_define(id);
             self.match_function_declare(id);
             self.match_global_variable_define(id);
         }
+    }
+}
+
+impl<'l> Parser for RecursiveDescentParser<'l> {
+    fn run(&mut self) -> ParserResult {
+        let matched = self.match_definitions();
+
+        // a lexer error anywhere in the input comes before a syntax error.
+        if let Some((e, span)) = self.tokens.lexer_error() {
+            return Err(ParseErrInfo {
+                    err_type: ParseError::LexicalError(e),
+                    span: Some(span),
+                   });
+        }
+
+        if !matched {
+            return Err(ParseErrInfo {
+                    err_type: ParseError::SyntaxError,
+                    span: self.token_span(self.current),
+                   });
+        }
 
         SymbolChecker::new(&mut self.tree).check()
     }
@@ -977,6 +1898,10 @@ impl Parser for RecursiveDescentParser {
     fn syntax_tree(&self) -> &SyntaxTree {
         &self.tree
     }
+
+    fn syntax_tree_mut(&mut self) -> &mut SyntaxTree {
+        &mut self.tree
+    }
 }
 
 #[cfg(test)]
@@ -1053,6 +1978,28 @@ mod test {
         test_func!(tests, match_variable_define);
     }
 
+    #[test]
+    fn test_unsigned() {
+        let tests = vec!["unsigned a", "unsigned int a, b", "unsigned char c = 1", "signed d",
+                         "signed long e", "unsigned long *p"];
+        test_func!(tests, match_variable_define);
+
+        let (mut tree, root_id) = tree!();
+        let define = insert_type!(tree, root_id, VariableDefine);
+            let unsigned = insert_type!(tree, define, UnsignedType);
+                insert!(tree, unsigned, Rc::new(Token::KeyWord(KeyWords::Int)));
+            insert!(tree, define, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+
+        test_tree!("unsigned a", match_variable_define, tree);
+
+        let (mut tree, root_id) = tree!();
+        let define = insert_type!(tree, root_id, VariableDefine);
+            insert!(tree, define, Rc::new(Token::KeyWord(KeyWords::Short)));
+            insert!(tree, define, Rc::new(Token::Identifier("s".to_owned(), Type::NoType)));
+
+        test_tree!("signed short s", match_variable_define, tree);
+    }
+
     #[test]
     fn test_variable_list() {
         let tests = vec!["int a, b_, c"];
@@ -1060,8 +2007,33 @@ mod test {
     }
 
     #[test]
-    fn test_struct_define() {
-        let tests = vec!["struct Str { int a; short b; };",
+    fn test_variable_initializer() {
+        let tests = vec!["int a = 4", "int a = 4, b", "int a, b = a + 1, c"];
+        test_func!(tests, match_variable_define);
+
+        let tests = vec!["int a = "];
+        test_func!(tests, match_variable_define, false);
+
+        let (mut tree, root_id) = tree!();
+        let define = insert_type!(tree, root_id, VariableDefine);
+            insert!(tree, define, Rc::new(Token::KeyWord(KeyWords::Int)));
+            let init = insert_type!(tree, define, AssignStmt);
+                insert!(tree, init, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                insert!(tree, init, Rc::new(Token::Number(Numbers::from_str("4"))));
+            insert!(tree, define, Rc::new(Token::Identifier("c".to_owned(), Type::NoType)));
+            let init = insert_type!(tree, define, AssignStmt);
+                insert!(tree, init, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+                let expr = insert_type!(tree, init, Expr);
+                    insert!(tree, expr, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                    insert!(tree, expr, Rc::new(Token::Operator(Operators::Add)));
+                    insert!(tree, expr, Rc::new(Token::Number(Numbers::from_str("1"))));
+
+        test_tree!("int a = 4, c, b = a + 1", match_variable_define, tree);
+    }
+
+    #[test]
+    fn test_struct_define() {
+        let tests = vec!["struct Str { int a; short b; };",
                          "\nstruct\n    { int a; }; \n",
                          "\nstruct\nS\n{\nint\na\n;\n}\n;\n"];
         test_func!(tests, match_struct_define);
@@ -1071,6 +2043,158 @@ mod test {
         test_func!(tests, match_struct_define, false);
     }
 
+    #[test]
+    fn test_type_define() {
+        let tests = vec!["typedef int MyInt;", "typedef unsigned char *Bytes;",
+                         "typedef struct S S;", "typedef double Vec[3];"];
+        test_func!(tests, match_type_define);
+
+        let tests = vec!["typedef MyInt;", "typedef int;", "typedef int A"];
+        test_func!(tests, match_type_define, false);
+
+        // the name is a type once it's defined
+        let src = "typedef long Big; Big f(Big a) { Big b = (Big)a; return b; }";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        assert!(parser.run().is_ok());
+
+        let (mut tree, root_id) = tree!();
+        let define = insert_type!(tree, root_id, VariableDefine);
+            let name = insert_type!(tree, define, TypedefName);
+                insert!(tree, name, Rc::new(Token::Identifier("Big".to_owned(), Type::NoType)));
+            insert!(tree, define, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new("Big b".as_bytes()));
+        parser.typedefs.insert("Big".to_owned());
+        let id = parser.root_id();
+        assert!(parser.match_variable_define(&id) && parser.lexer_end());
+        assert_eq!(to_pretty_string(parser.syntax_tree()), to_pretty_string(&tree));
+    }
+
+    #[test]
+    fn test_const_type() {
+        let tests = vec!["const int a", "const unsigned char c = 1, d", "const struct S s", "const const int a"];
+        test_func!(tests, match_variable_define);
+
+        let tests = vec!["const a", "const"];
+        test_func!(tests, match_variable_define, false);
+
+        let (mut tree, root_id) = tree!();
+        let define = insert_type!(tree, root_id, VariableDefine);
+            let qualified = insert_type!(tree, define, ConstType);
+                insert!(tree, qualified, Rc::new(Token::KeyWord(KeyWords::Int)));
+            insert!(tree, define, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new("const int a".as_bytes()));
+        let id = parser.root_id();
+        assert!(parser.match_variable_define(&id) && parser.lexer_end());
+        assert_eq!(to_pretty_string(parser.syntax_tree()), to_pretty_string(&tree));
+    }
+
+    #[test]
+    fn test_enum_define() {
+        let tests = vec!["enum Color { RED, GREEN, BLUE };",
+                         "enum { A = 1, B, C = -2 };",
+                         "enum E { A = 1 << 2, };"];
+        test_func!(tests, match_enum_define);
+
+        let tests = vec!["enum E { };", "enum E { A B };", "enum E { A = };", "enum E { A }"];
+        test_func!(tests, match_enum_define, false);
+
+        let tests = vec!["enum Color c", "enum Color c = RED, d"];
+        test_func!(tests, match_variable_define);
+
+        let (mut tree, root_id) = tree!();
+        let define = insert_type!(tree, root_id, EnumDefine);
+            insert!(tree, define, Rc::new(Token::Identifier("E".to_owned(), Type::NoType)));
+            let enumerator = insert_type!(tree, define, Enumerator);
+                insert!(tree, enumerator, Rc::new(Token::Identifier("A".to_owned(), Type::NoType)));
+            let enumerator = insert_type!(tree, define, Enumerator);
+                insert!(tree, enumerator, Rc::new(Token::Identifier("B".to_owned(), Type::NoType)));
+                insert!(tree, enumerator, Rc::new(Token::Number(Numbers::SignedInt(5))));
+
+        test_tree!("enum E { A, B = 5 };", match_enum_define, tree);
+    }
+
+    #[test]
+    fn test_cast() {
+        let tests = vec!["(double)a", "(int)(a + b) * 2", "(float)-a", "(struct S)s", "(int)(double)a"];
+        test_func!(tests, match_expr);
+
+        let failure_tests = vec!["(double)", "(int a)b", "(a)b"];
+        for test in failure_tests {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(test.as_bytes()));
+            let id = parser.root_id();
+            assert!(!(parser.match_expr(&id) && parser.lexer_end()));
+        }
+
+        let (mut tree, root_id) = tree!();
+        let expr = insert_type!(tree, root_id, Expr);
+            let cast = insert_type!(tree, expr, Cast);
+                insert!(tree, cast, Rc::new(Token::KeyWord(KeyWords::Double)));
+                insert!(tree, cast, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            insert!(tree, expr, Rc::new(Token::Operator(Operators::Division)));
+            insert!(tree, expr, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+
+        test_tree!("(double)a / b", match_bool_expr_node, tree);
+    }
+
+    #[test]
+    fn test_sizeof() {
+        let tests = vec!["sizeof(int)", "sizeof(unsigned char) * 2", "sizeof a", "sizeof(a)",
+                         "sizeof a[1] + 1", "sizeof(struct S)", "sizeof *p", "sizeof -a"];
+        test_func!(tests, match_expr);
+
+        let failure_tests = vec!["sizeof", "sizeof(int", "sizeof()"];
+        for test in failure_tests {
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new(test.as_bytes()));
+            let id = parser.root_id();
+            assert!(!(parser.match_expr(&id) && parser.lexer_end()));
+        }
+
+        let (mut tree, root_id) = tree!();
+        let expr = insert_type!(tree, root_id, Expr);
+            let sizeof = insert_type!(tree, expr, Sizeof);
+                insert!(tree, sizeof, Rc::new(Token::KeyWord(KeyWords::Double)));
+            insert!(tree, expr, Rc::new(Token::Operator(Operators::Add)));
+            let sizeof = insert_type!(tree, expr, Sizeof);
+                let operand = insert_type!(tree, sizeof, Expr);
+                    insert!(tree, operand, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+
+        test_tree!("sizeof(double) + sizeof a", match_bool_expr_node, tree);
+    }
+
+    #[test]
+    fn test_member_access() {
+        let tests = vec!["struct S s", "struct S *p, a[2]"];
+        test_func!(tests, match_variable_define);
+
+        let tests = vec!["s.a = 1", "p->a = s.b + 1", "a[1].b.c = p->d->e", "p = &s.a"];
+        test_func!(tests, match_assign_stmt);
+
+        let failure_tests = vec!["struct 1 s;", "s. = 1;", "s.a. = 1;", "p-> = 1;", "s.1 = 1;"];
+        test_func!(failure_tests, match_stmt, false);
+
+        let (mut tree, root_id) = tree!();
+        let define = insert_type!(tree, root_id, VariableDefine);
+            insert!(tree, define, Rc::new(Token::Identifier("S".to_owned(), Type::NoType)));
+            insert!(tree, define, Rc::new(Token::Identifier("s".to_owned(), Type::NoType)));
+
+        test_tree!("struct S s", match_variable_define, tree);
+
+        let (mut tree, root_id) = tree!();
+        let assign = insert_type!(tree, root_id, AssignStmt);
+            let outer = insert_type!(tree, assign, MemberAccess);
+                let inner = insert_type!(tree, outer, MemberAccess);
+                    insert!(tree, inner, Rc::new(Token::Identifier("p".to_owned(), Type::NoType)));
+                    insert!(tree, inner, Rc::new(Token::Arrow));
+                    insert!(tree, inner, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                insert!(tree, outer, Rc::new(Token::Dot));
+                insert!(tree, outer, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+            insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("1"))));
+
+        test_tree!("p->a.b = 1", match_assign_stmt, tree);
+    }
+
     #[test]
     fn test_experssion() {
         let tests = vec!["num1 + num2 * 1",
@@ -1081,10 +2205,13 @@ mod test {
                          "(3)+1",
                          "2 \n- \t4 +\n 3\n *\n2",
                         //  "3 ^ 2",
-                        //  "3 % num",
+                         "3 % num",
                          "2-((4)*(2))"];
         test_func!(tests, match_expr);
 
+        let tests = vec!["a + ", "a * (b - )"];
+        test_func!(tests, match_expr, false);
+
         let test = "1 * 1 * 1 * 1";
         let (mut tree, root_id) = tree!();
         insert!(tree, root_id, Rc::new(Token::Number(Numbers::from_str("1"))));
@@ -1096,6 +2223,81 @@ mod test {
         insert!(tree, root_id, Rc::new(Token::Number(Numbers::from_str("1"))));
 
         test_tree!(test, match_expr, tree);
+
+        let test = "a * b + c";
+        let (mut tree, root_id) = tree!();
+        let expr = insert_type!(tree, root_id, Expr);
+            insert!(tree, expr, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            insert!(tree, expr, Rc::new(Token::Operator(Operators::Mul)));
+            insert!(tree, expr, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Add)));
+        insert!(tree, root_id, Rc::new(Token::Identifier("c".to_owned(), Type::NoType)));
+
+        test_tree!(test, match_expr, tree);
+
+        let test = "(a + b) * c";
+        let (mut tree, root_id) = tree!();
+        let expr = insert_type!(tree, root_id, Expr);
+            insert!(tree, expr, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            insert!(tree, expr, Rc::new(Token::Operator(Operators::Add)));
+            insert!(tree, expr, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Mul)));
+        insert!(tree, root_id, Rc::new(Token::Identifier("c".to_owned(), Type::NoType)));
+
+        test_tree!(test, match_expr, tree);
+
+        let test = "a * -(b + 1)";
+        let (mut tree, root_id) = tree!();
+        insert!(tree, root_id, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Mul)));
+        let neg = insert_type!(tree, root_id, Expr);
+            insert!(tree, neg, Rc::new(Token::Operator(Operators::Minus)));
+            let expr = insert_type!(tree, neg, Expr);
+                insert!(tree, expr, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+                insert!(tree, expr, Rc::new(Token::Operator(Operators::Add)));
+                insert!(tree, expr, Rc::new(Token::Number(Numbers::from_str("1"))));
+
+        test_tree!(test, match_expr, tree);
+
+        let test = "a | b ^ c & d + 1";
+        let (mut tree, root_id) = tree!();
+        insert!(tree, root_id, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Or)));
+        let xor = insert_type!(tree, root_id, Expr);
+            insert!(tree, xor, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+            insert!(tree, xor, Rc::new(Token::Operator(Operators::Xor)));
+            let and = insert_type!(tree, xor, Expr);
+                insert!(tree, and, Rc::new(Token::Identifier("c".to_owned(), Type::NoType)));
+                insert!(tree, and, Rc::new(Token::Operator(Operators::And)));
+                let add = insert_type!(tree, and, Expr);
+                    insert!(tree, add, Rc::new(Token::Identifier("d".to_owned(), Type::NoType)));
+                    insert!(tree, add, Rc::new(Token::Operator(Operators::Add)));
+                    insert!(tree, add, Rc::new(Token::Number(Numbers::from_str("1"))));
+
+        test_tree!(test, match_expr, tree);
+
+        let test = "a << b + 1 >> 2";
+        let (mut tree, root_id) = tree!();
+        insert!(tree, root_id, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::ShiftLeft)));
+        let add = insert_type!(tree, root_id, Expr);
+            insert!(tree, add, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+            insert!(tree, add, Rc::new(Token::Operator(Operators::Add)));
+            insert!(tree, add, Rc::new(Token::Number(Numbers::from_str("1"))));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::ShiftRight)));
+        insert!(tree, root_id, Rc::new(Token::Number(Numbers::from_str("2"))));
+
+        test_tree!(test, match_expr, tree);
+
+        let test = "a - b - c";
+        let (mut tree, root_id) = tree!();
+        insert!(tree, root_id, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Minus)));
+        insert!(tree, root_id, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Minus)));
+        insert!(tree, root_id, Rc::new(Token::Identifier("c".to_owned(), Type::NoType)));
+
+        test_tree!(test, match_expr, tree);
     }
 
     #[test]
@@ -1104,7 +2306,9 @@ mod test {
                          "a == b || c + d == 1",
                          "a+b!=c+d||(e+f) && (d==1||f==2)",
                          "a&&b\n  ||c&&!d\t||\n   !\t\n!e||f\t+\n1==3",
-                         "a == b || c + d == 1"];
+                         "a == b || c + d == 1",
+                         "(a + b) * c == d",
+                         "(a == b) && (c)"];
         test_func!(tests, match_bool_expr);
 
         let test = "a + b != c + 1 || !e";
@@ -1126,6 +2330,30 @@ mod test {
 
         test_tree!(test, match_bool_expr, tree);
         test_tree!(test1, match_bool_expr, tree);
+
+        // `!` only applies to the next factor
+        let test = "!a && b";
+        let (mut tree, root_id) = tree!();
+        let not = insert_type!(tree, root_id, BooleanExpr);
+            insert!(tree, not, Rc::new(Token::Operator(Operators::LogicNot)));
+            insert!(tree, not, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::LogicAnd)));
+        insert!(tree, root_id, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+
+        test_tree!(test, match_bool_expr, tree);
+
+        // comparisons bind tighter than equality, a level's operators are left-associative
+        let (mut tree, root_id) = tree!();
+        let cmp = insert_type!(tree, root_id, BooleanExpr);
+            insert!(tree, cmp, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            insert!(tree, cmp, Rc::new(Token::Operator(Operators::Less)));
+            insert!(tree, cmp, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Equal)));
+        insert!(tree, root_id, Rc::new(Token::Identifier("c".to_owned(), Type::NoType)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::NotEqual)));
+        insert!(tree, root_id, Rc::new(Token::Identifier("d".to_owned(), Type::NoType)));
+
+        test_tree!("a < b == c != d", match_bool_expr, tree);
     }
 
     #[test]
@@ -1147,6 +2375,134 @@ mod test {
             insert!(tree, expr, Rc::new(Token::Number(Numbers::from_str("1"))));
 
         test_tree!("number = x + 1;", match_assign_stmt, tree);
+
+        let (mut tree, root_id) = tree!();
+        let assign = insert_type!(tree, root_id, AssignStmt);
+            insert!(tree, assign, Rc::new(Token::Identifier("c".to_owned(), Type::NoType)));
+            let cmp = insert_type!(tree, assign, BooleanExpr);
+            insert!(tree, cmp, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            insert!(tree, cmp, Rc::new(Token::Operator(Operators::Greater)));
+            insert!(tree, cmp, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+
+        test_tree!("c = a > b;", match_assign_stmt, tree);
+
+        // chained assignment
+        let (mut tree, root_id) = tree!();
+        let assign = insert_type!(tree, root_id, AssignStmt);
+            insert!(tree, assign, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            let inner = insert_type!(tree, assign, AssignStmt);
+                insert!(tree, inner, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+                insert!(tree, inner, Rc::new(Token::Number(Numbers::from_str("5"))));
+
+        test_tree!("a = b = 5;", match_assign_stmt, tree);
+
+        // assignment as an operand
+        let (mut tree, root_id) = tree!();
+        let assign = insert_type!(tree, root_id, AssignStmt);
+            insert!(tree, assign, Rc::new(Token::Identifier("c".to_owned(), Type::NoType)));
+            let expr = insert_type!(tree, assign, Expr);
+                let inner = insert_type!(tree, expr, AssignStmt);
+                    insert!(tree, inner, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                    insert!(tree, inner, Rc::new(Token::Number(Numbers::from_str("2"))));
+                insert!(tree, expr, Rc::new(Token::Operator(Operators::Mul)));
+                insert!(tree, expr, Rc::new(Token::Number(Numbers::from_str("3"))));
+
+        test_tree!("c = (a = 2) * 3;", match_assign_stmt, tree);
+    }
+
+    #[test]
+    fn test_conditional_expr() {
+        let tests = vec!["a ? b : c",
+                         "a > b ? a : b",
+                         "a ? b : c ? d : e",
+                         "a ? b = 1 : c"];
+        test_func!(tests, match_right_value);
+
+        let failure_tests = vec!["a ? b", "a ? b :", "a ? : c"];
+        test_func!(failure_tests, match_stmt, false);
+
+        let (mut tree, root_id) = tree!();
+        let ternary = insert_type!(tree, root_id, TernaryExpr);
+            let cond = insert_type!(tree, ternary, BooleanExpr);
+                insert!(tree, cond, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                insert!(tree, cond, Rc::new(Token::Operator(Operators::Greater)));
+                insert!(tree, cond, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+            insert!(tree, ternary, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            let inner = insert_type!(tree, ternary, TernaryExpr);
+                insert!(tree, inner, Rc::new(Token::Identifier("c".to_owned(), Type::NoType)));
+                let expr = insert_type!(tree, inner, Expr);
+                    insert!(tree, expr, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+                    insert!(tree, expr, Rc::new(Token::Operator(Operators::Add)));
+                    insert!(tree, expr, Rc::new(Token::Number(Numbers::from_str("1"))));
+                insert!(tree, inner, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+
+        test_tree!("a > b ? a : c ? b + 1 : b", match_right_value, tree);
+    }
+
+    #[test]
+    fn test_array() {
+        let tests = vec!["int a[10]", "int a[2], b, c[3]"];
+        test_func!(tests, match_variable_define);
+
+        let tests = vec!["a[1] = 2", "a[i + 1] = a[i] * 2", "a[b[0]] = f(a[1])"];
+        test_func!(tests, match_assign_stmt);
+
+        let failure_tests = vec!["int a[];", "int a[2;", "a[] = 1;", "a[1 = 2;"];
+        test_func!(failure_tests, match_stmt, false);
+
+        let (mut tree, root_id) = tree!();
+        let define = insert_type!(tree, root_id, VariableDefine);
+            insert!(tree, define, Rc::new(Token::KeyWord(KeyWords::Int)));
+            let array = insert_type!(tree, define, ArrayDeclare);
+                insert!(tree, array, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                insert!(tree, array, Rc::new(Token::Number(Numbers::from_str("2"))));
+            insert!(tree, define, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+
+        test_tree!("int a[2], b", match_variable_define, tree);
+
+        let (mut tree, root_id) = tree!();
+        let assign = insert_type!(tree, root_id, AssignStmt);
+            let index = insert_type!(tree, assign, ArrayIndex);
+                insert!(tree, index, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                let expr = insert_type!(tree, index, Expr);
+                    insert!(tree, expr, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+                    insert!(tree, expr, Rc::new(Token::Operator(Operators::Add)));
+                    insert!(tree, expr, Rc::new(Token::Number(Numbers::from_str("1"))));
+            let index = insert_type!(tree, assign, ArrayIndex);
+                insert!(tree, index, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                insert!(tree, index, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+
+        test_tree!("a[i + 1] = a[i]", match_assign_stmt, tree);
+    }
+
+    #[test]
+    fn test_pointer() {
+        let tests = vec!["int *p", "int **p, a, *b = &a", "int *a[3]"];
+        test_func!(tests, match_variable_define);
+
+        let tests = vec!["*p = 1", "*p = *q + 1", "p = &a[1]", "**p = -*q"];
+        test_func!(tests, match_assign_stmt);
+
+        let failure_tests = vec!["int *;", "int * = 1;", "&a = 1;", "a = &1;"];
+        test_func!(failure_tests, match_stmt, false);
+
+        let (mut tree, root_id) = tree!();
+        let define = insert_type!(tree, root_id, VariableDefine);
+            insert!(tree, define, Rc::new(Token::KeyWord(KeyWords::Int)));
+            let pointer = insert_type!(tree, define, PointerDeclare);
+                insert!(tree, pointer, Rc::new(Token::Identifier("p".to_owned(), Type::NoType)));
+            insert!(tree, define, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+
+        test_tree!("int *p, x", match_variable_define, tree);
+
+        let (mut tree, root_id) = tree!();
+        let assign = insert_type!(tree, root_id, AssignStmt);
+            let deref = insert_type!(tree, assign, Dereference);
+                insert!(tree, deref, Rc::new(Token::Identifier("p".to_owned(), Type::NoType)));
+            let address = insert_type!(tree, assign, AddressOf);
+                insert!(tree, address, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+
+        test_tree!("*p = &x", match_assign_stmt, tree);
     }
 
     #[test]
@@ -1157,40 +2513,306 @@ mod test {
         // if-else
         let (mut tree, root_id) = tree!();
         let if_stmt = insert_type!(tree, root_id, IfStmt);
-            insert!(tree, if_stmt, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
-            insert!(tree, if_stmt, Rc::new(Token::Operator(Operators::Equal)));
-            insert!(tree, if_stmt, Rc::new(Token::Number(Numbers::from_str("1"))));
+            let cond = insert_type!(tree, if_stmt, BooleanExpr);
+                insert!(tree, cond, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+                insert!(tree, cond, Rc::new(Token::Operator(Operators::Equal)));
+                insert!(tree, cond, Rc::new(Token::Number(Numbers::from_str("1"))));
             let assign = insert_type!(tree, if_stmt, AssignStmt);
                 insert!(tree, assign, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
                 insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("1"))));
-        let else_stmt = insert_type!(tree, root_id, ElseStmt);
-            let assign = insert_type!(tree, else_stmt, AssignStmt);
-                insert!(tree, assign, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
-                insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("2"))));
+            let else_stmt = insert_type!(tree, if_stmt, ElseStmt);
+                let assign = insert_type!(tree, else_stmt, AssignStmt);
+                    insert!(tree, assign, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+                    insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("2"))));
 
         let stmt = "if(x==1)x=1;else\nx=2;";
         test_tree!(stmt, match_if_stmt, tree);
 
-        // if-if-else
+        // if-if-else, `else` belongs to the inner `if`
         let (mut tree, root_id) = tree!();
         let if_stmt = insert_type!(tree, root_id, IfStmt);
-            insert!(tree, if_stmt, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
-            insert!(tree, if_stmt, Rc::new(Token::Operator(Operators::Equal)));
-            insert!(tree, if_stmt, Rc::new(Token::Number(Numbers::from_str("1"))));
+            let cond = insert_type!(tree, if_stmt, BooleanExpr);
+                insert!(tree, cond, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+                insert!(tree, cond, Rc::new(Token::Operator(Operators::Equal)));
+                insert!(tree, cond, Rc::new(Token::Number(Numbers::from_str("1"))));
             let inner_if = insert_type!(tree, if_stmt, IfStmt);
-                insert!(tree, inner_if, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
-                insert!(tree, inner_if, Rc::new(Token::Operator(Operators::NotEqual)));
-                insert!(tree, inner_if, Rc::new(Token::Number(Numbers::from_str("2"))));
+                let cond = insert_type!(tree, inner_if, BooleanExpr);
+                    insert!(tree, cond, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+                    insert!(tree, cond, Rc::new(Token::Operator(Operators::NotEqual)));
+                    insert!(tree, cond, Rc::new(Token::Number(Numbers::from_str("2"))));
                 let assign = insert_type!(tree, inner_if, AssignStmt);
                     insert!(tree, assign, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
                     insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("3"))));
-            let else_stmt = insert_type!(tree, if_stmt, ElseStmt);
-                let assign = insert_type!(tree, else_stmt, AssignStmt);
-                    insert!(tree, assign, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
-                    insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("2"))));
+                let else_stmt = insert_type!(tree, inner_if, ElseStmt);
+                    let assign = insert_type!(tree, else_stmt, AssignStmt);
+                        insert!(tree, assign, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+                        insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("2"))));
 
         let stmt = "if(x==1)if(x!=2)x=3;else\nx=2;";
         test_tree!(stmt, match_if_stmt, tree);
+
+        // statement blocks
+        let (mut tree, root_id) = tree!();
+        let if_stmt = insert_type!(tree, root_id, IfStmt);
+            insert!(tree, if_stmt, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+            let block = insert_type!(tree, if_stmt, StmtBlock);
+                let assign = insert_type!(tree, block, AssignStmt);
+                    insert!(tree, assign, Rc::new(Token::Identifier("y".to_owned(), Type::NoType)));
+                    insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("1"))));
+                let ret = insert_type!(tree, block, ReturnStmt);
+                    insert!(tree, ret, Rc::new(Token::Identifier("y".to_owned(), Type::NoType)));
+            let else_stmt = insert_type!(tree, if_stmt, ElseStmt);
+                insert_type!(tree, else_stmt, StmtBlock);
+
+        let stmt = "if (x) { y = 1; return y; } else {}";
+        test_tree!(stmt, match_if_stmt, tree);
+
+        // else-if chain
+        let (mut tree, root_id) = tree!();
+        let if_stmt = insert_type!(tree, root_id, IfStmt);
+            insert!(tree, if_stmt, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            let assign = insert_type!(tree, if_stmt, AssignStmt);
+                insert!(tree, assign, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+                insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("1"))));
+            let else_stmt = insert_type!(tree, if_stmt, ElseStmt);
+                let else_if = insert_type!(tree, else_stmt, IfStmt);
+                    insert!(tree, else_if, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+                    let assign = insert_type!(tree, else_if, AssignStmt);
+                        insert!(tree, assign, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+                        insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("2"))));
+                    let else_stmt = insert_type!(tree, else_if, ElseStmt);
+                        let assign = insert_type!(tree, else_stmt, AssignStmt);
+                            insert!(tree, assign, Rc::new(Token::Identifier("x".to_owned(), Type::NoType)));
+                            insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("3"))));
+
+        let stmt = "if (a) x = 1; else if (b) x = 2; else x = 3;";
+        test_tree!(stmt, match_if_stmt, tree);
+    }
+
+    #[test]
+    fn test_while_loop() {
+        let tests = vec!["while (i < 10) i = i + 1;",
+                         "while (1) {}",
+                         "while (a && b) { a = a - 1; }"];
+        test_func!(tests, match_while_loop);
+
+        let (mut tree, root_id) = tree!();
+        let while_loop = insert_type!(tree, root_id, WhileLoop);
+            let cond = insert_type!(tree, while_loop, BooleanExpr);
+                insert!(tree, cond, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+                insert!(tree, cond, Rc::new(Token::Operator(Operators::LessEqual)));
+                insert!(tree, cond, Rc::new(Token::Identifier("n".to_owned(), Type::NoType)));
+            let block = insert_type!(tree, while_loop, StmtBlock);
+                let assign = insert_type!(tree, block, AssignStmt);
+                    insert!(tree, assign, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+                    let expr = insert_type!(tree, assign, Expr);
+                        insert!(tree, expr, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+                        insert!(tree, expr, Rc::new(Token::Operator(Operators::Add)));
+                        insert!(tree, expr, Rc::new(Token::Number(Numbers::from_str("1"))));
+
+        test_tree!("while (i <= n) { i = i + 1; }", match_while_loop, tree);
+
+        let (mut tree, root_id) = tree!();
+        let while_loop = insert_type!(tree, root_id, WhileLoop);
+            let cond = insert_type!(tree, while_loop, FuncCall);
+                insert!(tree, cond, Rc::new(Token::Identifier("f".to_owned(), Type::NoType)));
+            insert_type!(tree, while_loop, EmptyStmt);
+
+        test_tree!("while (f());", match_while_loop, tree);
+    }
+
+    #[test]
+    fn test_break_continue() {
+        let (mut tree, root_id) = tree!();
+        let while_loop = insert_type!(tree, root_id, WhileLoop);
+            insert!(tree, while_loop, Rc::new(Token::Number(Numbers::from_str("1"))));
+            let block = insert_type!(tree, while_loop, StmtBlock);
+                let if_stmt = insert_type!(tree, block, IfStmt);
+                    insert!(tree, if_stmt, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                    insert_type!(tree, if_stmt, BreakStmt);
+                insert_type!(tree, block, ContinueStmt);
+
+        test_tree!("while (1) { if (a) break; continue; }", match_while_loop, tree);
+
+        let failure_tests = vec!["break", "continue"];
+        test_func!(failure_tests, match_stmt, false);
+    }
+
+    #[test]
+    fn test_do_while_stmt() {
+        let tests = vec!["do i = i + 1; while (i < 10);",
+                         "do {} while (1);",
+                         "do { a = a - 1; } while (a && b);"];
+        test_func!(tests, match_do_while_stmt);
+
+        let failure_tests = vec!["do {} while (1)",
+                                 "do while (1);"];
+        test_func!(failure_tests, match_do_while_stmt, false);
+
+        let (mut tree, root_id) = tree!();
+        let do_while = insert_type!(tree, root_id, DoWhileStmt);
+            let block = insert_type!(tree, do_while, StmtBlock);
+                let assign = insert_type!(tree, block, AssignStmt);
+                    insert!(tree, assign, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+                    let expr = insert_type!(tree, assign, Expr);
+                        insert!(tree, expr, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+                        insert!(tree, expr, Rc::new(Token::Operator(Operators::Add)));
+                        insert!(tree, expr, Rc::new(Token::Number(Numbers::from_str("1"))));
+            let cond = insert_type!(tree, do_while, BooleanExpr);
+                insert!(tree, cond, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+                insert!(tree, cond, Rc::new(Token::Operator(Operators::Less)));
+                insert!(tree, cond, Rc::new(Token::Identifier("n".to_owned(), Type::NoType)));
+
+        test_tree!("do { i = i + 1; } while (i < n);", match_do_while_stmt, tree);
+    }
+
+    #[test]
+    fn test_for_loop() {
+        let tests = vec!["for (i = 0; i < 10; i = i + 1) s = s + i;",
+                         "for (;;) {}",
+                         "for (; i;) { i = i - 1; }",
+                         "for (f(); i < 10; i = g(i) + 1) {}"];
+        test_func!(tests, match_for_loop);
+
+        let (mut tree, root_id) = tree!();
+        let for_loop = insert_type!(tree, root_id, ForLoop);
+            let init = insert_type!(tree, for_loop, ExprOpt);
+                let assign = insert_type!(tree, init, AssignStmt);
+                    insert!(tree, assign, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+                    insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("0"))));
+            let cond = insert_type!(tree, for_loop, ExprOpt);
+                let cmp = insert_type!(tree, cond, BooleanExpr);
+                    insert!(tree, cmp, Rc::new(Token::Identifier("i".to_owned(), Type::NoType)));
+                    insert!(tree, cmp, Rc::new(Token::Operator(Operators::NotEqual)));
+                    insert!(tree, cmp, Rc::new(Token::Number(Numbers::from_str("10"))));
+            insert_type!(tree, for_loop, ExprOpt);
+            insert_type!(tree, for_loop, StmtBlock);
+
+        test_tree!("for (i = 0; i != 10;) {}", match_for_loop, tree);
+
+        // any expression is a step, e.g. a call
+        let (mut tree, root_id) = tree!();
+        let for_loop = insert_type!(tree, root_id, ForLoop);
+            insert_type!(tree, for_loop, ExprOpt);
+            insert_type!(tree, for_loop, ExprOpt);
+            let step = insert_type!(tree, for_loop, ExprOpt);
+                let call = insert_type!(tree, step, FuncCall);
+                    insert!(tree, call, Rc::new(Token::Identifier("f".to_owned(), Type::NoType)));
+            insert_type!(tree, for_loop, StmtBlock);
+
+        test_tree!("for (;; f()) {}", match_for_loop, tree);
+    }
+
+    #[test]
+    fn test_func_call_literal_str() {
+        let (mut tree, root_id) = tree!();
+        let call = insert_type!(tree, root_id, FuncCall);
+            insert!(tree, call, Rc::new(Token::Identifier("printf".to_owned(), Type::NoType)));
+            let arg = insert_type!(tree, call, FuncArg);
+                insert!(tree, arg, Rc::new(Token::LiteralStr("\"%d\n\"".to_owned())));
+            let arg = insert_type!(tree, call, FuncArg);
+                insert!(tree, arg, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+
+        test_tree!(r#"printf("%d\n", a)"#, match_func_call, tree);
+    }
+
+    #[test]
+    fn test_func_call() {
+        let tests = vec!["f()", "f(a)", "f(a, b + 1, g(c) * 2)"];
+        test_func!(tests, match_func_call);
+
+        let (mut tree, root_id) = tree!();
+        insert!(tree, root_id, Rc::new(Token::Number(Numbers::from_str("1"))));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Add)));
+        let call = insert_type!(tree, root_id, FuncCall);
+            insert!(tree, call, Rc::new(Token::Identifier("f".to_owned(), Type::NoType)));
+            let arg = insert_type!(tree, call, FuncArg);
+                insert!(tree, arg, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            let arg = insert_type!(tree, call, FuncArg);
+                let expr = insert_type!(tree, arg, Expr);
+                    insert!(tree, expr, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+                    insert!(tree, expr, Rc::new(Token::Operator(Operators::Add)));
+                    insert!(tree, expr, Rc::new(Token::Number(Numbers::from_str("1"))));
+
+        test_tree!("1 + f(a, a + 1)", match_expr, tree);
+
+        let (mut tree, root_id) = tree!();
+        let call = insert_type!(tree, root_id, FuncCall);
+            insert!(tree, call, Rc::new(Token::Identifier("g".to_owned(), Type::NoType)));
+            let arg = insert_type!(tree, call, FuncArg);
+                insert!(tree, arg, Rc::new(Token::Number(Numbers::from_str("1"))));
+            let arg = insert_type!(tree, call, FuncArg);
+                insert!(tree, arg, Rc::new(Token::Number(Numbers::from_str("2"))));
+
+        test_tree!("g(1, 2)", match_func_call, tree);
+    }
+
+    #[test]
+    fn test_labeled_stmt() {
+        let tests = vec!["l: a = 1;", "l: m: ;", "l: { goto l; }", "l: if (a) goto l;"];
+        test_func!(tests, match_stmt);
+
+        let failure_tests = vec!["goto;", "goto 1;", "l: "];
+        test_func!(failure_tests, match_stmt, false);
+
+        let (mut tree, root_id) = tree!();
+        let labeled = insert_type!(tree, root_id, LabeledStmt);
+            insert!(tree, labeled, Rc::new(Token::Identifier("again".to_owned(), Type::NoType)));
+            let goto = insert_type!(tree, labeled, GotoStmt);
+                insert!(tree, goto, Rc::new(Token::Identifier("again".to_owned(), Type::NoType)));
+
+        test_tree!("again: goto again;", match_stmt, tree);
+    }
+
+    #[test]
+    fn test_switch_stmt() {
+        let tests = vec!["switch (a) {}",
+                         "switch (a + 1) { case 1: case -2: b = 1; break; default: b = 2; }",
+                         "switch (a) case 1: return 1;"];
+        test_func!(tests, match_switch_stmt);
+
+        let failure_tests = vec!["switch () {}", "switch (a) { case 1 b = 1; }", "switch (a) { default b = 1; }"];
+        test_func!(failure_tests, match_switch_stmt, false);
+
+        let (mut tree, root_id) = tree!();
+        let switch = insert_type!(tree, root_id, SwitchStmt);
+            insert!(tree, switch, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            let body = insert_type!(tree, switch, StmtBlock);
+                let case = insert_type!(tree, body, CaseStmt);
+                    insert!(tree, case, Rc::new(Token::Number(Numbers::from_str("1"))));
+                    let assign = insert_type!(tree, case, AssignStmt);
+                        insert!(tree, assign, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+                        insert!(tree, assign, Rc::new(Token::Number(Numbers::from_str("2"))));
+                insert_type!(tree, body, BreakStmt);
+                let default = insert_type!(tree, body, DefaultStmt);
+                    insert_type!(tree, default, EmptyStmt);
+
+        test_tree!("switch (a) { case 1: b = 2; break; default: ; }", match_switch_stmt, tree);
+    }
+
+    #[test]
+    fn test_inc_dec() {
+        let tests = vec!["a++", "--a", "a.b++", "++a[1]", "*p++"];
+        test_func!(tests, match_expr);
+
+        let tests = vec!["a++;", "--a;"];
+        test_func!(tests, match_stmt);
+
+        let failure_tests = vec!["1++;", "++(a + 1);"];
+        test_func!(failure_tests, match_stmt, false);
+
+        let (mut tree, root_id) = tree!();
+        insert!(tree, root_id, Rc::new(Token::Number(Numbers::from_str("1"))));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Add)));
+        let post = insert_type!(tree, root_id, PostfixIncDec);
+            insert!(tree, post, Rc::new(Token::Identifier("a".to_owned(), Type::NoType)));
+            insert!(tree, post, Rc::new(Token::Operator(Operators::DoubleAdd)));
+        insert!(tree, root_id, Rc::new(Token::Operator(Operators::Minus)));
+        let pre = insert_type!(tree, root_id, PrefixIncDec);
+            insert!(tree, pre, Rc::new(Token::Operator(Operators::DoubleMinus)));
+            insert!(tree, pre, Rc::new(Token::Identifier("b".to_owned(), Type::NoType)));
+
+        test_tree!("1 + a++ - --b", match_expr, tree);
     }
 
     #[test]
@@ -1202,6 +2824,53 @@ mod test {
         test_func!(tests, match_stmt_list);
     }
 
+    #[test]
+    fn test_syntax_error_span() {
+        let src = "int a;\nint b;\n  + int c;";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        let err = parser.run().unwrap_err();
+
+        assert!(matches!(err.err_type, ParseError::SyntaxError));
+        assert_eq!(err.span, Some(Span { line: 3, column: 3, offset: 16 }));
+    }
+
+    #[test]
+    fn test_lexical_error() {
+        let src = "int a;\nint b = 0x;";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        let err = parser.run().unwrap_err();
+
+        assert!(matches!(err.err_type, ParseError::LexicalError(LexerError::InvalidNumber(_))));
+        assert_eq!(err.span.map(|s| s.line), Some(2));
+
+        // the tokens are lexed as they're parsed, a syntax error before the lexer
+        // error doesn't hide it
+        let src = "int a;\n+ b;\nint c = 0x;";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        let err = parser.run().unwrap_err();
+
+        assert!(matches!(err.err_type, ParseError::LexicalError(LexerError::InvalidNumber(_))));
+        assert_eq!(err.span.map(|s| s.line), Some(3));
+    }
+
+    #[test]
+    fn test_token_buffer() {
+        let mut src = String::new();
+        for i in 0..2000 {
+            src.push_str(&format!("int g{}; int f{}(int a) {{ int b = a * {}; return b + g{}; }}\n", i, i, i, i));
+        }
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        assert!(parser.run().is_ok());
+
+        // every token went through the buffer, which held about one function
+        assert_eq!(parser.tokens.base, 2000 * 23);
+        assert!(parser.tokens.max_buffered <= 24);
+    }
+
     #[test]
     fn test_global_variable() {
         let src = "int a; void f(){}";
@@ -1211,4 +2880,16 @@ mod test {
         parser.run().unwrap();
         parser.dump();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_multiple_functions() {
+        let src = "int g() { return 1; }\nint f(int a) { return a; }\nint b;";
+
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let root_id = parser.syntax_tree().root_node_id().unwrap().clone();
+        let kinds: Vec<&SyntaxType> = parser.syntax_tree().children(&root_id).unwrap().map(|n| n.data()).collect();
+        assert_eq!(kinds, vec![&FuncDefine, &FuncDefine, &VariableDefine]);
+    }
+}
@@ -21,6 +21,7 @@ macro_rules! error {
     ($err: ident) => {
         Err(ParseErrInfo{
             err_type: $err,
+            span: None,
         })
     };
 }
@@ -45,6 +46,8 @@ impl<'t> SymbolChecker<'t> {
         for id in self.children_ids(root_id) {
             match self.ast.get(id).unwrap().data() {
                 &SyntaxType::StructDefine => self.check_struct(id)?,
+                &SyntaxType::EnumDefine => self.check_enum(id)?,
+                &SyntaxType::TypeDefine => self.check_type_define(id)?,
                 &SyntaxType::FuncDefine |
                 &SyntaxType::FuncDeclare => self.check_func(id)?,
                 &SyntaxType::VariableDefine => self.check_variable_define(id)?,
@@ -69,23 +72,66 @@ impl<'t> SymbolChecker<'t> {
     }
 
     fn check_struct(&self, root_id: &NodeId) -> ParserResult {
+        // the name is optional, the fields are VariableDefines. the name is a tag,
+        // which doesn't clash with identifiers, e.g. in `typedef struct S S`.
         let ids = self.children_ids(root_id);
-        if ids.len() == 2 { self.push_identifier(ids[0])?; }
+        let fields = match ids.first().and_then(|x| self.data(x).symbol()) {
+            Some(name) => {
+                if self.symbols.borrow_mut().push_struct(name, vec![]).is_err() {
+                    return error!(MultiDefineError);
+                }
+                1
+            },
+            None => 0,
+        };
 
         let _symbol_guard = self.scope_guard("");
-        for i in 1..ids.len() {
-            self.check_variable_define(ids[i])?;
+        for id in ids.iter().skip(fields) {
+            self.check_variable_define(id)?;
+        }
+
+        Ok(())
+    }
+
+    // enumerators are identifiers of the enclosing scope, the enum's name isn't.
+    fn check_enum(&self, root_id: &NodeId) -> ParserResult {
+        for id in self.children_ids(root_id) {
+            if let &SyntaxType::Enumerator = self.data(id) {
+                self.push_identifier(self.children_ids(id)[0])?;
+            }
         }
 
         Ok(())
     }
 
+    // a typedef name shares the namespace of variables, it can't be initialized.
+    fn check_type_define(&self, root_id: &NodeId) -> ParserResult {
+        let declarator = self.children_ids(root_id)[1];
+        if let &SyntaxType::AssignStmt = self.data(declarator) {
+            return error!(SemanticError);
+        }
+
+        self.push_identifier(self.declarator_ident(declarator))
+    }
+
     // check a variable define stmt, if variable already defined, return error.
     fn check_variable_define(&self, root_id: &NodeId) -> ParserResult {
-        for id in self.ast.children_ids(root_id).unwrap() {
+        // the type comes first, `struct S` is given by the struct's name.
+        for id in self.ast.children_ids(root_id).unwrap().skip(1) {
+            // `int a = 1` keeps the identifier as the first child of an AssignStmt,
+            // `int a[2]` of an ArrayDeclare and `int *a` of a PointerDeclare.
+            match self.data(id) {
+                &SyntaxType::AssignStmt |
+                &SyntaxType::ArrayDeclare |
+                &SyntaxType::PointerDeclare => {
+                    self.push_identifier(self.declarator_ident(id))?;
+                    continue;
+                },
+                _ => {},
+            }
+
             match *self.token(id).unwrap() {
                 Token::Identifier(_, _) => self.push_identifier(id)?,
-                Token::KeyWord(_) => {},
                 _ => return error!(SemanticError),
             }
         }
@@ -93,10 +139,17 @@ impl<'t> SymbolChecker<'t> {
         Ok(())
     }
 
+    fn declarator_ident<'a>(&'a self, id: &'a NodeId) -> &'a NodeId {
+        match self.token(id) {
+            Some(_) => id,
+            None => self.declarator_ident(self.children_ids(id)[0]),
+        }
+    }
+
     fn check_func(&self, id: &NodeId) -> ParserResult {
         let ids = self.children_ids(&id);
         // check function name, function return type is index 0.
-        self.push_identifier(ids[1])?;
+        self.push_function(id, ids[1])?;
 
         let _symbol_guard = self.scope_guard("");
 
@@ -114,6 +167,28 @@ impl<'t> SymbolChecker<'t> {
         Ok(())
     }
 
+    // a prototype may be repeated, and followed by the definition, which takes
+    // its place. a function can't be defined twice.
+    fn push_function(&self, id: &NodeId, ident: &NodeId) -> ParserResult {
+        let name = self.data(ident).symbol().unwrap();
+        let prev = self.symbols.borrow().lookup_current_scope(name).cloned();
+
+        let prev = match prev {
+            Some(prev) => prev,
+            None => return self.push_identifier(ident),
+        };
+
+        let prev_kind = self.ast.get(&prev).unwrap().parent().map(|x| self.data(x).clone());
+        match (prev_kind, self.data(id)) {
+            (Some(SyntaxType::FuncDeclare), _) => {
+                self.symbols.borrow_mut().replace_symbol(name, ident.clone());
+                Ok(())
+            },
+            (Some(SyntaxType::FuncDefine), &SyntaxType::FuncDeclare) => Ok(()),
+            _ => error!(MultiDefineError),
+        }
+    }
+
     fn check_func_arg(&self, id: &NodeId) -> ParserResult {
         let ids = self.children_ids(id);
         self.push_identifier(ids[1])?;
@@ -158,13 +233,72 @@ mod test {
 
     #[test]
     fn test_check_struct() {
-        let tests = vec!["struct S { int a; double b; };"];
+        let tests = vec!["struct S { int a; double b; };",
+                         "struct { int a, b; int c; };",
+                         "struct S { int a; }; struct T { struct S s; int a; }; struct S s;"];
+
+        for test in tests {
+            test_symbol_checker!(test, Ok(()));
+        }
+
+        let failed_tests = vec!["struct S { int a; double a; };",
+                                "struct S { int a = 1, a; };",
+                                "struct S { int a, b; double a; };",
+                                "struct S { int a; }; struct S { int b; };"];
+        for failed in failed_tests {
+            test_symbol_checker!(failed, Err(_));
+        }
+    }
+
+    #[test]
+    fn test_check_enum() {
+        let tests = vec!["enum E { A, B }; enum F { C, D };",
+                         "enum E { A }; int f() { int A; return A; }"];
+
+        for test in tests {
+            test_symbol_checker!(test, Ok(()));
+        }
+
+        let failed_tests = vec!["enum E { A, A };",
+                                "enum E { A }; enum F { A };",
+                                "enum E { A }; int A;"];
+        for failed in failed_tests {
+            test_symbol_checker!(failed, Err(_));
+        }
+    }
+
+    #[test]
+    fn test_check_type_define() {
+        let tests = vec!["typedef int A; typedef A *B; B b;",
+                         "struct S { int a; }; typedef struct S T; T t;",
+                         "typedef struct S S; struct S { int a; }; S s;"];
+
+        for test in tests {
+            test_symbol_checker!(test, Ok(()));
+        }
+
+        let failed_tests = vec!["typedef int A; typedef double A;",
+                                "typedef int A; int A;",
+                                "typedef int A = 1;"];
+        for failed in failed_tests {
+            test_symbol_checker!(failed, Err(_));
+        }
+    }
+
+    #[test]
+    fn test_check_prototype() {
+        let tests = vec!["int f(int a); int f(int b) { return b; }",
+                         "int f(); int f(); int g() { return f(); } int f() { return 1; }",
+                         "int f() { return 1; } int f();"];
 
         for test in tests {
             test_symbol_checker!(test, Ok(()));
         }
 
-        let failed_tests = vec!["struct S { int a; double a; };"];
+        let failed_tests = vec!["int f() { return 1; } int f() { return 2; }",
+                                "int f(); int f() { return 1; } int f() { return 2; }",
+                                "int f; int f();",
+                                "int f(); int f;"];
         for failed in failed_tests {
             test_symbol_checker!(failed, Err(_));
         }
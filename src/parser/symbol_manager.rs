@@ -1,12 +1,23 @@
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::fmt;
 
-type SymbolTable<V> = HashMap<String, V>;
+use parser::interner::*;
+
+type SymbolTable<V> = HashMap<NameId, V>;
 
 pub struct SymbolManager<V, S> {
+    // the scopes are keyed by the interned symbol, each name is stored once in
+    // `names` however many scopes define it.
+    names: Interner,
     symbols: Vec<SymbolTable<V>>,
+    // the symbols of every scope which are const.
+    consts: Vec<HashSet<NameId>>,
+    // field names of every struct, in declaration order. structs are only
+    // defined at file scope, so they aren't scoped.
+    structs: HashMap<String, Vec<String>>,
     scopes: Vec<S>,
 }
 
@@ -31,7 +42,10 @@ impl<V, S> Drop for ScopeGuard<V, S> {
 impl<V, S> SymbolManager<V, S> {
     pub fn new() -> SymbolManager<V, S> {
         SymbolManager {
+            names: Interner::new(),
             symbols: vec![SymbolTable::new()],
+            consts: vec![HashSet::new()],
+            structs: HashMap::new(),
             scopes: vec![],
         }
     }
@@ -52,15 +66,16 @@ impl<V, S> SymbolManager<V, S> {
         self.scopes.last_mut()
     }
 
+    /// Innermost definition of `symbol`, one hash lookup per enclosing scope.
     pub fn lookup<T: AsRef<str>>(&self, symbol: T) -> Option<&V> {
-        let s = symbol.as_ref();
-        for table in self.symbols.iter().rev() {
-            if table.contains_key(s) {
-                return table.get(s);
-            }
-        }
+        let id = self.names.get(symbol.as_ref())?;
+        self.symbols.iter().rev().filter_map(|table| table.get(&id)).next()
+    }
 
-        None
+    /// Like `lookup`, but ignoring the symbols of enclosing scopes.
+    pub fn lookup_current_scope<T: AsRef<str>>(&self, symbol: T) -> Option<&V> {
+        let id = self.names.get(symbol.as_ref())?;
+        self.symbols.last().unwrap().get(&id)
     }
 
     #[inline]
@@ -68,6 +83,7 @@ impl<V, S> SymbolManager<V, S> {
         trace!("create_scope");
 
         self.symbols.push(SymbolTable::new());
+        self.consts.push(HashSet::new());
         self.scopes.push(scope);
     }
 
@@ -76,18 +92,210 @@ impl<V, S> SymbolManager<V, S> {
         trace!("destory_scope");
 
         let _ = self.symbols.pop();
+        let _ = self.consts.pop();
+        let _ = self.scopes.pop();
     }
 
     pub fn push_symbol<T: AsRef<str>>(&mut self, symbol: T, id: V) -> Result<(), &V> {
         let s = symbol.as_ref();
+        let name = self.names.intern(s);
         let tbl = { self.symbols.last_mut().unwrap() };
-        if tbl.contains_key(s) {
-            return Err(tbl.get(s).unwrap())
+        if tbl.contains_key(&name) {
+            return Err(tbl.get(&name).unwrap())
         }
 
         trace!("symbol added: `{}`", s);
 
-        tbl.insert(s.to_owned(), id);
+        tbl.insert(name, id);
+        Ok(())
+    }
+
+    /// Like `push_symbol`, but a symbol of the current scope is replaced, and returned.
+    pub fn replace_symbol<T: AsRef<str>>(&mut self, symbol: T, id: V) -> Option<V> {
+        let s = symbol.as_ref();
+        trace!("symbol replaced: `{}`", s);
+
+        let name = self.names.intern(s);
+        self.symbols.last_mut().unwrap().insert(name, id)
+    }
+
+    /// Mark `symbol` of the current scope const.
+    pub fn set_const<T: AsRef<str>>(&mut self, symbol: T) {
+        let name = self.names.intern(symbol.as_ref());
+        self.consts.last_mut().unwrap().insert(name);
+    }
+
+    /// Whether the innermost definition of `symbol` is const.
+    pub fn is_const<T: AsRef<str>>(&self, symbol: T) -> bool {
+        let name = match self.names.get(symbol.as_ref()) {
+            Some(name) => name,
+            None => return false,
+        };
+
+        self.symbols.iter().zip(self.consts.iter()).rev()
+            .find(|&(table, _)| table.contains_key(&name))
+            .map_or(false, |(_, consts)| consts.contains(&name))
+    }
+
+    /// Remember the fields of struct `name`, the index of a field is its position.
+    pub fn push_struct<T: AsRef<str>>(&mut self, name: T, fields: Vec<String>) -> Result<(), &Vec<String>> {
+        let s = name.as_ref();
+        if self.structs.contains_key(s) {
+            return Err(self.structs.get(s).unwrap());
+        }
+
+        trace!("struct added: `{}`", s);
+
+        self.structs.insert(s.to_owned(), fields);
+        Ok(())
+    }
+
+    pub fn struct_fields<T: AsRef<str>>(&self, name: T) -> Option<&Vec<String>> {
+        self.structs.get(name.as_ref())
+    }
+
+    /// Index of `field` in struct `name`, `None` if either is unknown.
+    pub fn field_index<T: AsRef<str>, F: AsRef<str>>(&self, name: T, field: F) -> Option<usize> {
+        self.struct_fields(name)?.iter().position(|x| x == field.as_ref())
+    }
+}
+
+/// One line per scope, outermost first, e.g. `1 "f": a, b`.
+impl<V, S: fmt::Debug> fmt::Debug for SymbolManager<V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (level, table) in self.symbols.iter().enumerate() {
+            let mut names: Vec<&str> = table.keys().map(|&x| self.names.resolve(x)).collect();
+            names.sort();
+
+            match level {
+                0 => writeln!(f, "0 global: {}", names.join(", "))?,
+                _ => writeln!(f, "{} {:?}: {}", level, self.scopes[level - 1], names.join(", "))?,
+            }
+        }
+
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+
+    use parser::symbol_manager::*;
+
+    #[test]
+    fn test_push_symbol() {
+        let symbols = Rc::new(RefCell::new(SymbolManager::new()));
+        assert_eq!(symbols.borrow_mut().push_symbol("a", 1), Ok(()));
+        assert_eq!(symbols.borrow_mut().push_symbol("a", 2), Err(&1));
+
+        {
+            let _guard = ScopeGuard::new(symbols.clone(), "f");
+            assert_eq!(symbols.borrow_mut().push_symbol("a", 3), Ok(()));
+            assert_eq!(symbols.borrow().lookup("a"), Some(&3));
+        }
+
+        assert_eq!(symbols.borrow().lookup("a"), Some(&1));
+    }
+
+    #[test]
+    fn test_debug() {
+        let symbols = Rc::new(RefCell::new(SymbolManager::new()));
+        symbols.borrow_mut().push_symbol("g", 0).unwrap();
+
+        {
+            let _guard = ScopeGuard::new(symbols.clone(), "f".to_owned());
+            symbols.borrow_mut().push_symbol("b", 1).unwrap();
+            symbols.borrow_mut().push_symbol("a", 2).unwrap();
+
+            let _guard = ScopeGuard::new(symbols.clone(), "".to_owned());
+            assert_eq!(format!("{:?}", symbols.borrow()), "0 global: g\n1 \"f\": a, b\n2 \"\": \n");
+        }
+
+        assert_eq!(format!("{:?}", symbols.borrow()), "0 global: g\n");
+    }
+
+    #[test]
+    fn test_many_symbols() {
+        let symbols = Rc::new(RefCell::new(SymbolManager::new()));
+        for i in 0..10000 {
+            symbols.borrow_mut().push_symbol(format!("g{}", i), i).unwrap();
+        }
+
+        let _guard = ScopeGuard::new(symbols.clone(), "f");
+        for i in 0..10000 {
+            symbols.borrow_mut().push_symbol(format!("l{}", i), -i).unwrap();
+        }
+        symbols.borrow_mut().push_symbol("g0", -1).unwrap();
+
+        let symbols = symbols.borrow();
+        assert_eq!(symbols.symbols().iter().map(|x| x.len()).collect::<Vec<_>>(), vec![10000, 10001]);
+        assert_eq!(symbols.lookup("g9999"), Some(&9999));
+        assert_eq!(symbols.lookup("l9999"), Some(&-9999));
+        assert_eq!(symbols.lookup("g0"), Some(&-1));
+        assert_eq!(symbols.lookup("g10000"), None);
+    }
+
+    #[test]
+    fn test_struct_fields() {
+        let mut symbols: SymbolManager<i32, ()> = SymbolManager::new();
+        let fields = vec!["x".to_owned(), "y".to_owned()];
+        assert_eq!(symbols.push_struct("Point", fields.clone()), Ok(()));
+        assert_eq!(symbols.push_struct("Point", vec![]), Err(&fields));
+
+        assert_eq!(symbols.field_index("Point", "x"), Some(0));
+        assert_eq!(symbols.field_index("Point", "y"), Some(1));
+        assert_eq!(symbols.field_index("Point", "z"), None);
+        assert_eq!(symbols.field_index("Line", "x"), None);
+    }
+
+    #[test]
+    fn test_lookup_current_scope() {
+        let symbols = Rc::new(RefCell::new(SymbolManager::new()));
+        symbols.borrow_mut().push_symbol("a", 1).unwrap();
+        assert_eq!(symbols.borrow().lookup_current_scope("a"), Some(&1));
+
+        let _guard = ScopeGuard::new(symbols.clone(), "f");
+        assert_eq!(symbols.borrow().lookup_current_scope("a"), None);
+        assert_eq!(symbols.borrow().lookup("a"), Some(&1));
+
+        symbols.borrow_mut().push_symbol("b", 2).unwrap();
+        assert_eq!(symbols.borrow().lookup_current_scope("b"), Some(&2));
+    }
+
+    #[test]
+    fn test_interned_names() {
+        let symbols = Rc::new(RefCell::new(SymbolManager::new()));
+        symbols.borrow_mut().push_symbol("a", 1).unwrap();
+        let a = symbols.borrow().names.get("a").unwrap();
+
+        for i in 0..100 {
+            let _guard = ScopeGuard::new(symbols.clone(), "f");
+            symbols.borrow_mut().push_symbol("a".to_owned(), i).unwrap();
+            assert_eq!(symbols.borrow().lookup("a"), Some(&i));
+
+            // every scope shares the one copy of `a`
+            assert_eq!(symbols.borrow().symbols()[1].keys().collect::<Vec<_>>(), vec![&a]);
+        }
+
+        assert_eq!(symbols.borrow().lookup("a"), Some(&1));
+        assert_eq!(symbols.borrow().lookup_current_scope("b"), None);
+    }
+
+    #[test]
+    fn test_const() {
+        let symbols = Rc::new(RefCell::new(SymbolManager::new()));
+        symbols.borrow_mut().push_symbol("a", 1).unwrap();
+        symbols.borrow_mut().set_const("a");
+        assert!(symbols.borrow().is_const("a"));
+
+        {
+            // a shadowing definition isn't const
+            let _guard = ScopeGuard::new(symbols.clone(), "f");
+            symbols.borrow_mut().push_symbol("a", 2).unwrap();
+            assert!(!symbols.borrow().is_const("a"));
+        }
+
+        assert!(symbols.borrow().is_const("a"));
+        assert!(!symbols.borrow().is_const("b"));
+    }
+}
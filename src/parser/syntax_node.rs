@@ -1,27 +1,53 @@
 
-use token::Token;
+use token::{Numbers, Token, Type};
 
-use id_tree::Tree;
+use id_tree::{NodeId, Tree};
 
+use std::fmt;
 use std::rc::Rc;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum SyntaxType {
     Terminal(Rc<Token>),
     SyntaxTree,
     StructDefine,
+    EnumDefine,
+    TypeDefine,
+    Enumerator,
     VariableDefine,
+    ArrayDeclare,
+    ArrayIndex,
+    PointerDeclare,
+    AddressOf,
+    Dereference,
+    PrefixIncDec,
+    PostfixIncDec,
+    MemberAccess,
+    Cast,
+    Sizeof,
+    UnsignedType,
+    TypedefName,
+    ConstType,
     Expr,
     BooleanExpr,
+    TernaryExpr,
     ExprOpt,
     StmtBlock,
+    EmptyStmt,
     AssignStmt,
     IfStmt,
     ElseStmt,
     ReturnStmt,
     BreakStmt,
+    ContinueStmt,
+    LabeledStmt,
+    GotoStmt,
     WhileLoop,
     ForLoop,
+    DoWhileStmt,
+    SwitchStmt,
+    CaseStmt,
+    DefaultStmt,
     FuncDefine,
     FuncDeclare,
     FuncParam,
@@ -50,4 +76,227 @@ impl SyntaxType {
             _ => None,
         }
     }
-}
\ No newline at end of file
+}
+
+/// The kind of a node, a terminal shows its token instead, e.g. `Identifier("f", NoType)`.
+impl fmt::Display for SyntaxType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SyntaxType::Terminal(ref tok) => write!(f, "{:?}", tok),
+            ref node => write!(f, "{:?}", node),
+        }
+    }
+}
+
+/// Render the tree one node per line, children indented by two spaces.
+pub fn to_pretty_string(tree: &SyntaxTree) -> String {
+    let mut s = String::new();
+    if let Some(root) = tree.root_node_id() {
+        pretty_subtree(tree, root, 0, &mut s);
+    }
+
+    s
+}
+
+fn pretty_subtree(tree: &SyntaxTree, id: &NodeId, indentation: usize, s: &mut String) {
+    let node = tree.get(id).unwrap();
+    s.push_str(&format!("{:width$}{}\n", "", node.data(), width = indentation * 2));
+
+    for child in node.children() {
+        pretty_subtree(tree, child, indentation + 1, s);
+    }
+}
+
+/// Graphviz digraph of the tree, nodes are numbered in pre-order and terminals are boxes.
+pub fn to_dot(tree: &SyntaxTree) -> String {
+    let mut s = "digraph ast {\n".to_owned();
+    if let Some(root) = tree.root_node_id() {
+        dot_subtree(tree, root, &mut 0, &mut s);
+    }
+    s.push_str("}\n");
+
+    s
+}
+
+// returns the number given to `id`.
+fn dot_subtree(tree: &SyntaxTree, id: &NodeId, count: &mut usize, s: &mut String) -> usize {
+    let node = tree.get(id).unwrap();
+    let index = *count;
+    *count += 1;
+
+    let label = format!("{}", node.data()).replace('\\', "\\\\").replace('"', "\\\"");
+    let shape = match *node.data() {
+        SyntaxType::Terminal(_) => "box",
+        _ => "ellipse",
+    };
+    s.push_str(&format!("    n{} [label=\"{}\", shape={}];\n", index, label, shape));
+
+    for child in node.children() {
+        let child_index = dot_subtree(tree, child, count, s);
+        s.push_str(&format!("    n{} -> n{};\n", index, child_index));
+    }
+
+    index
+}
+
+/// The tree as JSON, every node is `{"kind": ..., "children": [...]}` except terminals,
+/// which are `{"kind": "Terminal", "token": {"kind": ..., "value": ...}}`. identifier
+/// and number tokens have a `"type"` too, `null` for an identifier not resolved yet.
+pub fn to_json(tree: &SyntaxTree) -> String {
+    let mut s = String::new();
+    match tree.root_node_id() {
+        Some(root) => json_subtree(tree, root, &mut s),
+        None => s.push_str("null"),
+    }
+
+    s
+}
+
+fn json_subtree(tree: &SyntaxTree, id: &NodeId, s: &mut String) {
+    let node = tree.get(id).unwrap();
+    match *node.data() {
+        SyntaxType::Terminal(ref tok) => {
+            s.push_str(&format!("{{\"kind\":\"Terminal\",\"token\":{}}}", json_token(tok)));
+        },
+        ref data => {
+            s.push_str(&format!("{{\"kind\":{},\"children\":[", json_string(&format!("{:?}", data))));
+            for (i, child) in node.children().iter().enumerate() {
+                if i != 0 { s.push(','); }
+                json_subtree(tree, child, s);
+            }
+            s.push_str("]}");
+        },
+    }
+}
+
+// punctuation tokens have no value, the quotes of a string literal aren't part of it.
+fn json_token(tok: &Token) -> String {
+    let (kind, value, ty) = match *tok {
+        Token::Identifier(ref name, ref t) => {
+            let ty = match *t {
+                Type::NoType => "null".to_owned(),
+                ref t => json_string(&t.to_string()),
+            };
+            ("Identifier".to_owned(), Some(json_string(name)), Some(ty))
+        },
+        Token::Number(ref n) => {
+            let (value, ty) = match *n {
+                Numbers::SignedInt(n) => (n.to_string(), Type::SignedInt),
+                Numbers::Float(n) => (json_float(n as f64, n.to_string()), Type::Float),
+                Numbers::Double(n) => (json_float(n, n.to_string()), Type::Double),
+            };
+            ("Number".to_owned(), Some(value), Some(json_string(&ty.to_string())))
+        },
+        Token::KeyWord(ref k) => ("KeyWord".to_owned(), Some(json_string(&format!("{:?}", k))), None),
+        Token::Operator(ref o) => ("Operator".to_owned(), Some(json_string(&format!("{:?}", o))), None),
+        Token::Bracket(ref b) => ("Bracket".to_owned(), Some(json_string(&format!("{:?}", b))), None),
+        Token::LiteralStr(ref v) => ("LiteralStr".to_owned(), Some(json_string(&v[1..v.len() - 1])), None),
+        Token::CharLiteral(c) => ("CharLiteral".to_owned(), Some(json_string(&c.to_string())), None),
+        Token::Comment(ref v) => ("Comment".to_owned(), Some(json_string(v)), None),
+        Token::Preprocessor(ref v) => ("Preprocessor".to_owned(), Some(json_string(v)), None),
+        ref t => (format!("{:?}", t), None, None),
+    };
+
+    let mut s = format!("{{\"kind\":{}", json_string(&kind));
+    if let Some(value) = value {
+        s.push_str(&format!(",\"value\":{}", value));
+    }
+    if let Some(ty) = ty {
+        s.push_str(&format!(",\"type\":{}", ty));
+    }
+    s.push('}');
+
+    s
+}
+
+// JSON has no infinity or NaN.
+fn json_float(n: f64, repr: String) -> String {
+    if n.is_finite() { repr } else { "null".to_owned() }
+}
+
+fn json_string(v: &str) -> String {
+    let mut s = "\"".to_owned();
+    for c in v.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\t' => s.push_str("\\t"),
+            c if (c as u32) < 0x20 => s.push_str(&format!("\\u{:04x}", c as u32)),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+
+    s
+}
+
+#[cfg(test)]
+mod test {
+
+    use parser::*;
+    use parser::recursive_descent::*;
+    use parser::syntax_node::*;
+    use lexer::*;
+    use token::*;
+
+    #[test]
+    fn test_pretty_string() {
+        let src = "int f() { return 1; }";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        assert_eq!(to_pretty_string(parser.syntax_tree()), "\
+SyntaxTree
+  FuncDefine
+    KeyWord(Int)
+    Identifier(\"f\", NoType)
+    ReturnStmt
+      Number(SignedInt(1))
+");
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let src = "int f() { return 1; }";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let dot = to_dot(parser.syntax_tree());
+        assert!(dot.starts_with("digraph"));
+        assert!(dot.contains("n1 [label=\"FuncDefine\", shape=ellipse];"));
+        assert!(dot.contains("n3 [label=\"Identifier(\\\"f\\\", NoType)\", shape=box];"));
+        assert!(dot.contains("n4 [label=\"ReturnStmt\", shape=ellipse];"));
+        assert!(dot.contains("n1 -> n4;"));
+        assert!(dot.contains("n4 -> n5;"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let src = "int f() { return 1; }";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        assert_eq!(to_json(parser.syntax_tree()),
+                   r#"{"kind":"SyntaxTree","children":[{"kind":"FuncDefine","children":["#.to_owned() +
+                   r#"{"kind":"Terminal","token":{"kind":"KeyWord","value":"Int"}},"# +
+                   r#"{"kind":"Terminal","token":{"kind":"Identifier","value":"f","type":null}},"# +
+                   r#"{"kind":"ReturnStmt","children":[{"kind":"Terminal","token":{"kind":"Number","value":1,"type":"int"}}]}"# +
+                   r#"]}]}"#);
+
+        assert_eq!(json_token(&Token::Identifier("p".to_owned(), Type::Ptr(Box::new(Type::Double)))),
+                   r#"{"kind":"Identifier","value":"p","type":"double*"}"#);
+        assert_eq!(json_token(&Token::Number(Numbers::Double(0.5))),
+                   r#"{"kind":"Number","value":0.5,"type":"double"}"#);
+        assert_eq!(json_token(&Token::Number(Numbers::Double(1.0 / 0.0))),
+                   r#"{"kind":"Number","value":null,"type":"double"}"#);
+        assert_eq!(json_token(&Token::LiteralStr("\"a\\n\"".to_owned())),
+                   r#"{"kind":"LiteralStr","value":"a\\n"}"#);
+        assert_eq!(json_token(&Token::Operator(Operators::LessEqual)),
+                   r#"{"kind":"Operator","value":"LessEqual"}"#);
+        assert_eq!(json_token(&Token::Semicolon), r#"{"kind":"Semicolon"}"#);
+
+        assert_eq!(json_string("\"a\\b\"\n\u{1}"), r#""\"a\\b\"\n\u0001""#);
+    }
+}
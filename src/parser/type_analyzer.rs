@@ -1,58 +1,1521 @@
 
 use token::*;
 use token::Token::*;
+use lexer::Span;
 use parser::syntax_node::*;
+use parser::symbol_manager::*;
 
 use id_tree::*;
 
-// use std::rc::Rc;
-use std::collections::HashMap;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::cmp::Ordering;
 
+type AnalyzeResult<T> = Result<T, Diagnostic>;
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// where the node the diagnostic is about starts, `None` if the parser
+    /// didn't keep it, see `TypeAnalyzer::with_spans`.
+    pub span: Option<Span>,
+}
+
+/// A message which isn't placed yet, it takes the span of the node it's
+/// returned from.
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Diagnostic {
+        Diagnostic {
+            message: message,
+            span: None,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Diagnostic {
+    fn from(message: &str) -> Diagnostic {
+        message.to_owned().into()
+    }
+}
+
+/// Checks the types of a parsed tree, filling the `Type` of every identifier
+/// token with the one it was declared with.
 pub struct TypeAnalyzer<'t> {
-    ast: &'t SyntaxTree,
+    ast: &'t mut SyntaxTree,
     cache: HashMap<NodeId, Type>,
+    symbols: Rc<RefCell<SymbolManager<Type, String>>>,
+    // field types of every struct, by index. the names are in `symbols`.
+    structs: HashMap<String, Vec<Type>>,
+    // the type every typedef name stands for.
+    typedefs: HashMap<String, Type>,
+    // declared return type of the function being checked.
+    return_type: Type,
+    // number of loops around the statement being checked.
+    loop_depth: usize,
+    // case values of the switches around the statement being checked, innermost last.
+    switch_cases: Vec<HashSet<isize>>,
+    diagnostics: Vec<Diagnostic>,
+    // source position of the terminals and statements, see `with_spans`.
+    spans: HashMap<NodeId, Span>,
 }
 
 impl<'t> TypeAnalyzer<'t> {
-    pub fn new(ast: &'t SyntaxTree) -> TypeAnalyzer<'t> {
+    pub fn new(ast: &'t mut SyntaxTree) -> TypeAnalyzer<'t> {
         TypeAnalyzer {
             ast: ast,
             cache: HashMap::new(),
+            symbols: Rc::new(RefCell::new(SymbolManager::new())),
+            structs: HashMap::new(),
+            typedefs: HashMap::new(),
+            return_type: Type::NoType,
+            loop_depth: 0,
+            switch_cases: vec![],
+            diagnostics: vec![],
+            spans: HashMap::new(),
+        }
+    }
+
+    /// Like `new`, the diagnostics are placed with `spans`, which are the
+    /// parser's `spans()`.
+    pub fn with_spans(ast: &'t mut SyntaxTree, spans: HashMap<NodeId, Span>) -> TypeAnalyzer<'t> {
+        TypeAnalyzer {
+            spans: spans,
+            ..TypeAnalyzer::new(ast)
         }
     }
 
+    /// Walk the whole tree, resolving the type of every expression. An error stops
+    /// the statement it's found in only, so all of them are returned.
+    pub fn run(&mut self) -> Vec<Diagnostic> {
+        let root_id = self.ast.root_node_id().unwrap().clone();
+
+        // functions first, so calls resolve regardless of the definition order.
+        // structs are defined before they're used, so they go in the same pass.
+        let mut defined = HashSet::new();
+        for id in self.children_ids(&root_id) {
+            match self.data(&id) {
+                &SyntaxType::StructDefine => {
+                    if let Err(diagnostic) = self.define_struct(&id) {
+                        self.report(diagnostic, &id);
+                    }
+                },
+                &SyntaxType::EnumDefine => self.define_enum(&id),
+                &SyntaxType::TypeDefine => {
+                    let ids = self.children_ids(&id);
+                    let t = self.declared_type(&ids[0]);
+                    let (name, t) = self.typedef_type(&ids[1], t);
+                    self.typedefs.insert(name, t);
+                },
+                &SyntaxType::FuncDefine |
+                &SyntaxType::FuncDeclare => {
+                    let ident = self.children_ids(&id)[1].clone();
+                    let name = self.ident_name(&ident);
+                    let t = self.function_type(&id);
+                    self.annotate(&ident, &t);
+
+                    let is_define = *self.data(&id) == SyntaxType::FuncDefine;
+                    let redefined = is_define && !defined.insert(name.clone());
+
+                    // a prototype may be repeated, and followed by the definition.
+                    let prev = self.symbols.borrow().lookup_current_scope(&name).cloned();
+                    let result = match prev {
+                        Some(ref prev) if *prev == t && !redefined => Ok(()),
+                        _ => self.push_symbol(&name, t),
+                    };
+
+                    if let Err(diagnostic) = result {
+                        self.report(diagnostic, &ident);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        self.check_children(&root_id);
+        mem::replace(&mut self.diagnostics, vec![])
+    }
+
+    /// Type of an expression node resolved by `run`, `NoType` if unknown.
     pub fn type_of(&self, node: &NodeId) -> Type {
         if let Some(t) = self.cache.get(node) {
             return t.clone();
         }
 
-        match self.data(node) {
-            &SyntaxType::Terminal(ref ptr) => {
-                match **ptr {
-                    Identifier(_, _) => self.type_of_ident(node),
+        Type::NoType
+    }
+
+    fn check_node(&mut self, id: &NodeId) -> AnalyzeResult<()> {
+        match self.data(id) {
+            &SyntaxType::FuncDefine |
+            &SyntaxType::FuncDeclare => self.check_func(id),
+            &SyntaxType::VariableDefine => self.check_variable_define(id),
+            &SyntaxType::ReturnStmt => self.check_return(id),
+            &SyntaxType::StmtBlock => {
+                let _symbol_guard = self.scope_guard("");
+                self.check_children(id);
+                Ok(())
+            },
+            &SyntaxType::IfStmt => {
+                self.check_children(id);
+                self.check_condition(id)
+            },
+            &SyntaxType::WhileLoop |
+            &SyntaxType::ForLoop |
+            &SyntaxType::DoWhileStmt => {
+                self.loop_depth += 1;
+                self.check_children(id);
+                self.loop_depth -= 1;
+                self.check_condition(id)
+            },
+            // a label isn't a variable, only its statement is checked.
+            &SyntaxType::LabeledStmt => {
+                let stmt = self.children_ids(id)[1].clone();
+                self.check_stmt(&stmt);
+                Ok(())
+            },
+            &SyntaxType::GotoStmt => Ok(()),
+            &SyntaxType::SwitchStmt => {
+                self.switch_cases.push(HashSet::new());
+                self.check_children(id);
+                self.switch_cases.pop();
+
+                match self.type_of(&self.children_ids(id)[0]) {
+                    Type::NoType => Ok(()),
+                    ref t if t.is_integer() => Ok(()),
+                    _ => Err("switch quantity is not an integer".into()),
+                }
+            },
+            &SyntaxType::CaseStmt => {
+                let ids = self.children_ids(id);
+                if let Err(diagnostic) = self.check_case(&ids[0]) {
+                    self.report(diagnostic, id);
+                }
+                self.check_stmt(&ids[1]);
+                Ok(())
+            },
+            &SyntaxType::DefaultStmt => {
+                if self.switch_cases.is_empty() {
+                    self.report("`default` label not within a switch statement".into(), id);
+                }
+                let stmt = self.children_ids(id)[0].clone();
+                self.check_stmt(&stmt);
+                Ok(())
+            },
+            &SyntaxType::BreakStmt if self.loop_depth == 0 && self.switch_cases.is_empty() =>
+                Err("`break` statement not within a loop or switch".into()),
+            &SyntaxType::ContinueStmt if self.loop_depth == 0 =>
+                Err("`continue` statement not within a loop".into()),
+            &SyntaxType::StructDefine |
+            &SyntaxType::EnumDefine |
+            &SyntaxType::TypeDefine => Ok(()),
+            &SyntaxType::Terminal(_) |
+            &SyntaxType::Expr |
+            &SyntaxType::BooleanExpr |
+            &SyntaxType::TernaryExpr |
+            &SyntaxType::AssignStmt |
+            &SyntaxType::ArrayIndex |
+            &SyntaxType::AddressOf |
+            &SyntaxType::Dereference |
+            &SyntaxType::PrefixIncDec |
+            &SyntaxType::PostfixIncDec |
+            &SyntaxType::MemberAccess |
+            &SyntaxType::Cast |
+            &SyntaxType::Sizeof |
+            &SyntaxType::FuncCall => self.infer(id).map(|_| ()),
+            _ => {
+                self.check_children(id);
+                Ok(())
+            },
+        }
+    }
+
+    fn check_children(&mut self, id: &NodeId) {
+        for child in self.children_ids(id) {
+            self.check_stmt(&child);
+        }
+    }
+
+    // an error is reported and the next statement is checked.
+    fn check_stmt(&mut self, id: &NodeId) {
+        if let Err(diagnostic) = self.check_node(id) {
+            self.report(diagnostic, id);
+        }
+    }
+
+    // the condition of an `if` or a loop is compared with 0, so it's a scalar.
+    // it's inferred with the children already, a `for` may leave it out.
+    fn check_condition(&self, id: &NodeId) -> AnalyzeResult<()> {
+        let ids = self.children_ids(id);
+        let cond = match self.data(id) {
+            &SyntaxType::DoWhileStmt => Some(ids[1].clone()),
+            &SyntaxType::ForLoop => self.children_ids(&ids[1]).first().cloned(),
+            _ => Some(ids[0].clone()),
+        };
+
+        match cond.map(|x| self.type_of(&x)) {
+            Some(Type::Void) |
+            Some(Type::Struct(_)) |
+            Some(Type::Func(_, _)) => Err("condition is not scalar".into()),
+            _ => Ok(()),
+        }
+    }
+
+    // a case value is an integer constant, once in its switch.
+    fn check_case(&mut self, id: &NodeId) -> AnalyzeResult<()> {
+        let value = self.case_value(id);
+        let values = match self.switch_cases.last_mut() {
+            Some(values) => values,
+            None => return Err("`case` label not within a switch statement".into()),
+        };
+
+        match value {
+            Some(value) if values.insert(value) => Ok(()),
+            Some(value) => Err(format!("duplicate case value `{}`", value).into()),
+            None => Err("case label is not an integer constant".into()),
+        }
+    }
+
+    // an integer literal, which may be negated.
+    fn case_value(&self, id: &NodeId) -> Option<isize> {
+        if let Some(tok) = self.token(id) {
+            return match *tok {
+                Number(Numbers::SignedInt(n)) => Some(n),
+                _ => None,
+            };
+        }
+
+        let ids = self.children_ids(id);
+        match (self.data(id), ids.len()) {
+            (&SyntaxType::Expr, 2) if self.token(&ids[0]).map_or(false, |t| *t == Operator(Operators::Minus)) =>
+                self.case_value(&ids[1]).map(|n| -n),
+            _ => None,
+        }
+    }
+
+    // `diagnostic` is about `id`, unless it's placed at a node under it already.
+    fn report(&mut self, diagnostic: Diagnostic, id: &NodeId) {
+        let diagnostic = self.locate(diagnostic, id);
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn locate(&self, mut diagnostic: Diagnostic, id: &NodeId) -> Diagnostic {
+        if diagnostic.span.is_none() {
+            diagnostic.span = self.span(id);
+        }
+
+        diagnostic
+    }
+
+    // span of `id`, or of the first node under it which has one.
+    fn span(&self, id: &NodeId) -> Option<Span> {
+        match self.spans.get(id) {
+            Some(&span) => Some(span),
+            None => self.children_ids(id).iter().filter_map(|x| self.span(x)).next(),
+        }
+    }
+
+    // children are the return type, the name, the FuncParams and then the body,
+    // which a FuncDeclare doesn't have.
+    // the function itself is already declared by `run`.
+    fn check_func(&mut self, id: &NodeId) -> AnalyzeResult<()> {
+        let ids = self.children_ids(id);
+        let name = self.ident_name(&ids[1]);
+        let params = self.func_params(id);
+
+        let _symbol_guard = self.scope_guard(&name);
+        for param in &params {
+            let childs = self.children_ids(param);
+            let param_name = self.ident_name(&childs[1]);
+            let t = self.declared_type(&childs[0]);
+            self.annotate(&childs[1], &t);
+            if let Err(diagnostic) = self.push_symbol(&param_name, t) {
+                self.report(diagnostic, &childs[1]);
+            }
+
+            if *self.data(&childs[0]) == SyntaxType::ConstType {
+                self.symbols.borrow_mut().set_const(&param_name);
+            }
+        }
+
+        if let Type::Func(_, ret) = self.function_type(id) {
+            self.return_type = *ret;
+        }
+
+        for stmt in ids.iter().skip(2 + params.len()) {
+            self.check_stmt(stmt);
+        }
+
+        let is_define = *self.data(id) == SyntaxType::FuncDefine;
+        if is_define && self.return_type != Type::Void && self.stmts_complete(&ids[2 + params.len()..]) {
+            return Err(format!("control reaches end of non-void function `{}`", name).into());
+        }
+
+        Ok(())
+    }
+
+    fn check_return(&mut self, id: &NodeId) -> AnalyzeResult<()> {
+        let ret = self.return_type.clone();
+
+        match self.children_ids(id).first() {
+            Some(expr) => {
+                let t = self.infer(expr)?;
+
+                if ret == Type::Void {
+                    return Err("return with a value in function returning void".into());
+                }
+
+                if !assignable(&ret, &t) {
+                    return Err(format!("cannot return {} from function returning {}", t, ret).into());
+                }
+            },
+            None => {
+                if ret != Type::Void {
+                    return Err(format!("return without a value in function returning {}", ret).into());
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    // whether the end of the statements may be reached. a statement after one
+    // which doesn't complete is reached only through a label on it.
+    fn stmts_complete(&self, ids: &[NodeId]) -> bool {
+        let mut reached = true;
+        for id in ids {
+            match self.data(id) {
+                &SyntaxType::LabeledStmt |
+                &SyntaxType::CaseStmt |
+                &SyntaxType::DefaultStmt => reached = true,
+                _ => {},
+            }
+
+            reached = reached && self.completes(id);
+        }
+
+        reached
+    }
+
+    // whether the statement after this one may be reached from it. a loop only
+    // doesn't when its condition is a nonzero literal and it has no `break`, a
+    // switch when its last case doesn't and it has a `default` and no `break`.
+    fn completes(&self, id: &NodeId) -> bool {
+        let ids = self.children_ids(id);
+
+        match self.data(id) {
+            &SyntaxType::ReturnStmt |
+            &SyntaxType::GotoStmt |
+            &SyntaxType::BreakStmt |
+            &SyntaxType::ContinueStmt => false,
+            &SyntaxType::StmtBlock => self.stmts_complete(&ids),
+            &SyntaxType::IfStmt if ids.len() > 2 =>
+                self.completes(&ids[1]) || self.stmts_complete(&self.children_ids(&ids[2])),
+            &SyntaxType::LabeledStmt |
+            &SyntaxType::CaseStmt => self.completes(&ids[1]),
+            &SyntaxType::DefaultStmt => self.completes(&ids[0]),
+            &SyntaxType::WhileLoop => !self.always_true(&ids[0]) || self.has_break(&ids[1]),
+            &SyntaxType::DoWhileStmt => !self.always_true(&ids[1]) || self.has_break(&ids[0]),
+            &SyntaxType::ForLoop => {
+                let cond = self.children_ids(&ids[1]);
+                !cond.first().map_or(true, |x| self.always_true(x)) || self.has_break(&ids[3])
+            },
+            &SyntaxType::SwitchStmt => {
+                let has_default = self.ast.traverse_pre_order(&ids[1]).unwrap()
+                    .any(|node| *node.data() == SyntaxType::DefaultStmt);
+                !has_default || self.has_break(&ids[1]) || self.completes(&ids[1])
+            },
+            _ => true,
+        }
+    }
+
+    fn always_true(&self, id: &NodeId) -> bool {
+        match self.token(id) {
+            Some(tok) => match *tok {
+                Number(Numbers::SignedInt(n)) => n != 0,
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    // a `break` leaving the loop or switch `id` is the body of, not an inner one.
+    fn has_break(&self, id: &NodeId) -> bool {
+        match self.data(id) {
+            &SyntaxType::BreakStmt => true,
+            &SyntaxType::WhileLoop |
+            &SyntaxType::ForLoop |
+            &SyntaxType::DoWhileStmt |
+            &SyntaxType::SwitchStmt => false,
+            _ => self.children_ids(id).iter().any(|x| self.has_break(x)),
+        }
+    }
+
+    fn func_params(&self, id: &NodeId) -> Vec<NodeId> {
+        self.children_ids(id)
+            .into_iter()
+            .skip(2)
+            .take_while(|x| *self.data(x) == SyntaxType::FuncParam)
+            .collect()
+    }
+
+    fn function_type(&self, id: &NodeId) -> Type {
+        let ret = self.declared_type(&self.children_ids(id)[0]);
+        let params = self.func_params(id)
+            .iter()
+            .map(|x| self.declared_type(&self.children_ids(x)[0]))
+            .collect();
+
+        Type::Func(params, Box::new(ret))
+    }
+
+    fn check_variable_define(&mut self, id: &NodeId) -> AnalyzeResult<()> {
+        let ids = self.children_ids(id);
+        let var_type = self.declared_type(&ids[0]);
+        let is_const = *self.data(&ids[0]) == SyntaxType::ConstType;
+
+        for var in ids.iter().skip(1) {
+            let (name, _) = self.check_declarator(var, var_type.clone())?;
+
+            // `const int *p` points to a const, `p` itself may be assigned.
+            if is_const && *self.data(var) != SyntaxType::PointerDeclare {
+                self.symbols.borrow_mut().set_const(&name);
+            }
+        }
+
+        Ok(())
+    }
+
+    // the name a typedef declares and the type it stands for. unlike a variable's,
+    // the type may be an incomplete struct.
+    fn typedef_type(&mut self, id: &NodeId, t: Type) -> (String, Type) {
+        let ids = self.children_ids(id);
+
+        match self.data(id) {
+            &SyntaxType::PointerDeclare => self.typedef_type(&ids[0], Type::Ptr(Box::new(t))),
+            &SyntaxType::ArrayDeclare => {
+                let size = match *self.token(&ids[1]).unwrap() {
+                    Number(Numbers::SignedInt(n)) if n > 0 => n as usize,
+                    _ => 0,
+                };
+
+                let t = Type::Array(Box::new(t), size);
+                self.annotate(&ids[0], &t);
+                (self.ident_name(&ids[0]), t)
+            },
+            _ => {
+                self.annotate(id, &t);
+                (self.ident_name(id), t)
+            },
+        }
+    }
+
+    // every enumerator is an `int`, so is a value given to it.
+    fn define_enum(&mut self, id: &NodeId) {
+        for enumerator in self.children_ids(id) {
+            if *self.data(&enumerator) != SyntaxType::Enumerator { continue; }
+
+            let childs = self.children_ids(&enumerator);
+            let name = self.ident_name(&childs[0]);
+            self.annotate(&childs[0], &Type::SignedInt);
+
+            let result = match childs.get(1).map(|x| self.infer(x)) {
+                Some(Ok(ref t)) if !t.is_integer() && *t != Type::NoType =>
+                    Err(format!("enumerator value of `{}` is not an integer", name).into()),
+                Some(Err(message)) => Err(message),
+                _ => self.push_symbol(&name, Type::SignedInt),
+            };
+
+            if let Err(diagnostic) = result {
+                self.report(diagnostic, &childs[0]);
+            }
+        }
+    }
+
+    // the name is optional, the fields are VariableDefines. they're declared in a
+    // scope of their own, which catches a field declared twice.
+    fn define_struct(&mut self, id: &NodeId) -> AnalyzeResult<()> {
+        let ids = self.children_ids(id);
+        let (name, fields) = match ids.first().and_then(|x| self.token(x)) {
+            Some(_) => (Some(self.ident_name(&ids[0])), &ids[1..]),
+            None => (None, &ids[..]),
+        };
+
+        let mut names = vec![];
+        let mut types = vec![];
+        {
+            let _symbol_guard = self.scope_guard(name.clone().unwrap_or_default());
+            for field in fields {
+                let childs = self.children_ids(field);
+                let t = self.declared_type(&childs[0]);
+                for var in childs.iter().skip(1) {
+                    let (field_name, field_type) = self.check_declarator(var, t.clone())?;
+                    names.push(field_name);
+                    types.push(field_type);
+                }
+            }
+        }
+
+        if let Some(name) = name {
+            let t = Type::Struct(name.clone());
+            self.annotate(&ids[0], &t);
+
+            if self.symbols.borrow_mut().push_struct(&name, names).is_err() {
+                return Err(format!("redefinition of `{}`", t).into());
+            }
+            self.structs.insert(name, types);
+        }
+
+        Ok(())
+    }
+
+    // `int a = expr` is an AssignStmt holding the identifier and the initializer,
+    // `int a[n]` an ArrayDeclare holding the identifier and the size, and `int *a`
+    // a PointerDeclare holding the rest of the declarator.
+    // returns the declared name and its type.
+    fn check_declarator(&mut self, id: &NodeId, t: Type) -> AnalyzeResult<(String, Type)> {
+        let ids = self.children_ids(id);
+
+        let (name, t) = match self.data(id) {
+            &SyntaxType::AssignStmt => {
+                let name = self.ident_name(&ids[0]);
+                self.check_complete(&name, &t)?;
+                self.push_symbol(&name, t.clone())?;
+                self.check_assign(id)?;
+                (name, t)
+            },
+            &SyntaxType::ArrayDeclare => {
+                let name = self.ident_name(&ids[0]);
+                let size = match *self.token(&ids[1]).unwrap() {
+                    Number(Numbers::SignedInt(n)) if n > 0 => n as usize,
+                    _ => return Err(format!("size of array `{}` is not a positive integer", name).into()),
+                };
+
+                self.check_complete(&name, &t)?;
+                let t = Type::Array(Box::new(t), size);
+                self.annotate(&ids[0], &t);
+                self.push_symbol(&name, t.clone())?;
+                (name, t)
+            },
+            &SyntaxType::PointerDeclare => self.check_declarator(&ids[0], Type::Ptr(Box::new(t)))?,
+            _ => {
+                let name = self.ident_name(id);
+                self.check_complete(&name, &t)?;
+                self.annotate(id, &t);
+                self.push_symbol(&name, t.clone())?;
+                (name, t)
+            },
+        };
+
+        Ok((name, t))
+    }
+
+    // only a pointer may be declared to a struct which isn't defined (yet).
+    fn check_complete(&self, name: &str, t: &Type) -> AnalyzeResult<()> {
+        if let Type::Struct(ref s) = *t {
+            if !self.structs.contains_key(s) {
+                return Err(format!("variable `{}` has incomplete type {}", name, t).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    // `s.a` needs a struct, `p->a` a pointer to one.
+    fn member_type(&mut self, id: &NodeId) -> AnalyzeResult<Type> {
+        let ids = self.children_ids(id);
+        let base = self.infer(&ids[0])?;
+        let field = self.ident_name(&ids[2]);
+        let is_arrow = *self.token(&ids[1]).unwrap() == Arrow;
+
+        let st = match (is_arrow, base) {
+            (_, Type::NoType) => return Ok(Type::NoType),
+            (false, t) => t,
+            (true, Type::Ptr(t)) => *t,
+            (true, t) => return Err(format!("member reference type {} is not a pointer", t).into()),
+        };
+
+        let name = match st {
+            Type::Struct(name) => name,
+            Type::NoType => return Ok(Type::NoType),
+            t => return Err(format!("request for member `{}` in a value of type {}", field, t).into()),
+        };
+
+        let index = self.symbols.borrow().field_index(&name, &field);
+        let t = match index {
+            Some(i) => self.structs[&name][i].clone(),
+            None => return Err(format!("`struct {}` has no member named `{}`", name, field).into()),
+        };
+
+        self.annotate(&ids[2], &t);
+        Ok(t)
+    }
+
+    // a const variable is written by its initializer only, and so are the fields
+    // of a const struct.
+    fn check_writable(&self, id: &NodeId, action: &str) -> AnalyzeResult<()> {
+        let ids = self.children_ids(id);
+
+        match self.data(id) {
+            &SyntaxType::MemberAccess if *self.token(&ids[1]).unwrap() == Dot => {
+                self.check_writable(&ids[0], action)
+            },
+            &SyntaxType::Terminal(_) => {
+                match self.data(id).symbol() {
+                    Some(name) if self.symbols.borrow().is_const(name) =>
+                        Err(format!("{} of read-only variable `{}`", action, name).into()),
+                    _ => Ok(()),
+                }
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn check_assign(&mut self, id: &NodeId) -> AnalyzeResult<Type> {
+        let ids = self.children_ids(id);
+        let lhs = self.infer(&ids[0])?;
+        let rhs = self.infer(&ids[1])?;
+
+        if !assignable(&lhs, &rhs) {
+            return Err(format!("cannot assign {} to {}", rhs, lhs).into());
+        }
+
+        Ok(lhs)
+    }
+
+    // type of an expression, checking the sub expressions on the way. an error
+    // is placed at the innermost expression it's found in.
+    fn infer(&mut self, id: &NodeId) -> AnalyzeResult<Type> {
+        let result = self.infer_node(id);
+        result.map_err(|e| self.locate(e, id))
+    }
+
+    fn infer_node(&mut self, id: &NodeId) -> AnalyzeResult<Type> {
+        let t = match self.data(id) {
+            &SyntaxType::Terminal(ref tok) => {
+                match **tok {
+                    Identifier(ref name, _) => match self.lookup(name) {
+                        Some(t) => t,
+                        None => return Err(format!("undeclared identifier `{}`", name).into()),
+                    },
+                    Number(Numbers::SignedInt(_)) => Type::SignedInt,
+                    Number(Numbers::Float(_)) => Type::Float,
+                    Number(Numbers::Double(_)) => Type::Double,
+                    _ => Type::NoType,
+                }
+            },
+            &SyntaxType::Expr => {
+                let mut t = None;
+                let mut divides = false;
+                for child in self.children_ids(id) {
+                    if self.is_operator(&child) {
+                        divides = match *self.token(&child).unwrap() {
+                            Operator(Operators::Division) | Operator(Operators::Mod) => true,
+                            _ => false,
+                        };
+                        continue;
+                    }
+
+                    // only a literal divisor is known, a variable may be anything.
+                    if divides && self.is_zero_literal(&child) {
+                        return Err("division by zero".into());
+                    }
+
+                    let operand = self.infer(&child)?;
+                    t = Some(match t {
+                        Some(lhs) => arith_type(&lhs, &operand),
+                        None => operand,
+                    });
+                }
+
+                t.unwrap_or(Type::NoType)
+            },
+            &SyntaxType::BooleanExpr => {
+                for child in self.children_ids(id) {
+                    if !self.is_operator(&child) { self.infer(&child)?; }
+                }
+
+                Type::SignedInt
+            },
+            &SyntaxType::TernaryExpr => {
+                let ids = self.children_ids(id);
+                self.infer(&ids[0])?;
+                let lhs = self.infer(&ids[1])?;
+                let rhs = self.infer(&ids[2])?;
+
+                if lhs == rhs { lhs } else { arith_type(&lhs, &rhs) }
+            },
+            &SyntaxType::AssignStmt => {
+                self.check_writable(&self.children_ids(id)[0], "assignment")?;
+                self.check_assign(id)?
+            },
+            &SyntaxType::PrefixIncDec |
+            &SyntaxType::PostfixIncDec => {
+                let ids = self.children_ids(id);
+                let (op, operand) = match self.data(id) {
+                    &SyntaxType::PrefixIncDec => (&ids[0], &ids[1]),
+                    _ => (&ids[1], &ids[0]),
+                };
+                let action = match *self.token(op).unwrap() {
+                    Operator(Operators::DoubleAdd) => "increment",
+                    _ => "decrement",
+                };
+
+                self.check_writable(operand, action)?;
+                match self.infer(operand)? {
+                    t @ Type::Ptr(_) => t,
+                    ref t if *t == Type::NoType || t.is_integer() || t.is_floating() => t.clone(),
+                    t => return Err(format!("wrong type argument to {}: {}", action, t).into()),
+                }
+            },
+            &SyntaxType::AddressOf => {
+                match self.infer(&self.children_ids(id)[0])? {
+                    Type::NoType => Type::NoType,
+                    t => Type::Ptr(Box::new(t)),
+                }
+            },
+            &SyntaxType::Dereference => {
+                match self.infer(&self.children_ids(id)[0])? {
+                    Type::Ptr(t) | Type::Array(t, _) => *t,
+                    Type::NoType => Type::NoType,
+                    t => return Err(format!("cannot dereference a value of type {}", t).into()),
+                }
+            },
+            &SyntaxType::MemberAccess => self.member_type(id)?,
+            // numbers convert between each other, pointers between each other.
+            &SyntaxType::Cast => {
+                let ids = self.children_ids(id);
+                let to = self.declared_type(&ids[0]);
+                let from = self.infer(&ids[1])?;
+
+                if !castable(&to, &from) {
+                    return Err(format!("cannot cast {} to {}", from, to).into());
+                }
+
+                to
+            },
+            // of a type, or of an expression's type, which is never evaluated.
+            &SyntaxType::Sizeof => {
+                let operand = self.children_ids(id).remove(0);
+                let t = match *self.data(&operand) {
+                    SyntaxType::Expr => self.infer(&operand)?,
+                    _ => self.declared_type(&operand),
+                };
+
+                match t {
+                    Type::Void | Type::Func(_, _) =>
+                        return Err(format!("invalid application of sizeof to {}", t).into()),
+                    Type::Struct(ref name) if !self.structs.contains_key(name) =>
+                        return Err(format!("invalid application of sizeof to incomplete type {}", t).into()),
+                    _ => {},
+                }
+
+                Type::UnsignedLong
+            },
+            &SyntaxType::ArrayIndex => {
+                let ids = self.children_ids(id);
+                let array = self.infer(&ids[0])?;
+                let index = self.infer(&ids[1])?;
+
+                if index != Type::NoType && !index.is_integer() {
+                    return Err("array subscript is not an integer".into());
+                }
+
+                match array {
+                    Type::Array(t, _) | Type::Ptr(t) => *t,
+                    Type::NoType => Type::NoType,
+                    t => return Err(format!("subscripted value of type {} is not an array", t).into()),
+                }
+            },
+            // the callee isn't looked up as an identifier, it may be defined later.
+            &SyntaxType::FuncCall => {
+                let ids = self.children_ids(id);
+                for arg in ids.iter().skip(1) {
+                    self.infer(&self.children_ids(arg)[0])?;
+                }
+
+                let name = self.ident_name(&ids[0]);
+                match self.lookup(&name) {
+                    Some(Type::Func(params, ret)) => {
+                        self.annotate(&ids[0], &Type::Func(params.clone(), ret.clone()));
+
+                        if params.len() != ids.len() - 1 {
+                            return Err(format!("{} expects {} arguments, got {}",
+                                               name, params.len(), ids.len() - 1).into());
+                        }
+
+                        *ret
+                    },
                     _ => Type::NoType,
                 }
             },
             _ => Type::NoType,
+        };
+
+        self.annotate(id, &t);
+        self.cache.insert(id.clone(), t.clone());
+        Ok(t)
+    }
+
+    // store the type in the node's token, if it's an identifier.
+    fn annotate(&mut self, id: &NodeId, t: &Type) {
+        let tok = match self.token(id) {
+            Some(tok) => match *tok {
+                Identifier(ref name, _) => Identifier(name.clone(), t.clone()),
+                _ => return,
+            },
+            None => return,
+        };
+
+        self.ast.get_mut(id).unwrap().replace_data(SyntaxType::Terminal(Rc::new(tok)));
+    }
+
+    fn declared_type(&self, id: &NodeId) -> Type {
+        if *self.data(id) == SyntaxType::ConstType {
+            return self.declared_type(&self.children_ids(id)[0]);
+        }
+
+        if *self.data(id) == SyntaxType::UnsignedType {
+            let width = self.declared_type(&self.children_ids(id)[0]);
+            return width.to_unsigned().unwrap_or(Type::NoType);
+        }
+
+        if *self.data(id) == SyntaxType::TypedefName {
+            let name = self.ident_name(&self.children_ids(id)[0]);
+            return self.typedefs.get(&name).cloned().unwrap_or(Type::NoType);
+        }
+
+        match *self.token(id).unwrap() {
+            KeyWord(ref k) => k.to_type().unwrap_or(Type::NoType),
+            Identifier(ref name, _) => Type::Struct(name.clone()),
+            _ => Type::NoType,
         }
     }
 
-    fn type_of_ident(&self, _id: &NodeId) -> Type {
-        Type::NoType
+    fn is_operator(&self, id: &NodeId) -> bool {
+        match self.token(id) {
+            Some(tok) => match *tok {
+                Operator(_) => true,
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    fn is_zero_literal(&self, id: &NodeId) -> bool {
+        match self.token(id) {
+            Some(tok) => *tok == Number(Numbers::SignedInt(0)),
+            None => false,
+        }
     }
 
-    // #[inline]
-    // fn token(&self, node_id: &NodeId) -> Option<Rc<Token>> {
-    //     self.data(node_id).token()
-    // }
+    fn ident_name(&self, id: &NodeId) -> String {
+        self.data(id).symbol().unwrap().to_owned()
+    }
+
+    fn lookup<T: AsRef<str>>(&self, name: T) -> Option<Type> {
+        self.symbols.borrow().lookup(name).cloned()
+    }
+
+    // shadowing a name from an outer scope is fine, declaring it twice in one isn't.
+    fn push_symbol<T: AsRef<str>>(&self, name: T, t: Type) -> AnalyzeResult<()> {
+        let name = name.as_ref();
+        if self.symbols.borrow_mut().push_symbol(name, t).is_err() {
+            return Err(format!("redeclaration of `{}`", name).into());
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn token(&self, node_id: &NodeId) -> Option<Rc<Token>> {
+        self.data(node_id).token()
+    }
 
     #[inline]
     fn data(&self, node_id: &NodeId) -> &SyntaxType {
         self.ast.get(node_id).unwrap().data()
     }
 
-    // #[inline]
-    // fn children_ids(&self, node_id: &NodeId) -> Vec<&NodeId> {
-    //     self.ast.children_ids(&node_id).unwrap().collect()
-    // }
-}
\ No newline at end of file
+    #[inline]
+    fn children_ids(&self, node_id: &NodeId) -> Vec<NodeId> {
+        self.ast.children_ids(&node_id).unwrap().cloned().collect()
+    }
+
+    #[inline]
+    fn scope_guard<T: AsRef<str>>(&self, scope: T) -> ScopeGuard<Type, String> {
+        ScopeGuard::new(self.symbols.clone(), scope.as_ref().to_owned())
+    }
+}
+
+// the usual arithmetic conversions, for the types we have.
+fn arith_type(lhs: &Type, rhs: &Type) -> Type {
+    match (lhs, rhs) {
+        (&Type::Double, _) | (_, &Type::Double) => Type::Double,
+        (&Type::Float, _) | (_, &Type::Float) => Type::Float,
+        (l, r) if l.is_integer() && r.is_integer() => {
+            // both sides are promoted to int first, the higher rank wins and
+            // unsigned wins a tie.
+            let (l, r) = (int_promote(l), int_promote(r));
+            match int_rank(&l).cmp(&int_rank(&r)) {
+                Ordering::Greater => l,
+                Ordering::Less => r,
+                Ordering::Equal => if r.is_unsigned() { r } else { l },
+            }
+        },
+        _ => Type::NoType,
+    }
+}
+
+fn int_rank(t: &Type) -> u8 {
+    match *t {
+        Type::SignedChar | Type::UnsignedChar => 1,
+        Type::SignedShort | Type::UnsignedShort => 2,
+        Type::SignedInt | Type::UnsignedInt => 3,
+        _ => 4,
+    }
+}
+
+fn int_promote(t: &Type) -> Type {
+    if int_rank(t) < 3 { Type::SignedInt } else { t.clone() }
+}
+
+fn castable(to: &Type, from: &Type) -> bool {
+    let is_number = |t: &Type| t.is_integer() || t.is_floating();
+
+    match (to, from) {
+        (&Type::NoType, _) | (_, &Type::NoType) => true,
+        (&Type::Ptr(_), &Type::Ptr(_)) => true,
+        (t, f) => is_number(t) && is_number(f),
+    }
+}
+
+// a floating value can't be assigned to an integer, the rest converts implicitly.
+fn assignable(to: &Type, from: &Type) -> bool {
+    match (to, from) {
+        (&Type::NoType, _) | (_, &Type::NoType) => true,
+        (t, f) if t.is_integer() => f.is_integer(),
+        (t, f) if t.is_floating() => f.is_integer() || f.is_floating(),
+        // an array converts to a pointer to its first element.
+        (&Type::Ptr(ref t), &Type::Array(ref f, _)) => t == f,
+        (t, f) => t == f,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use parser::*;
+    use parser::recursive_descent::*;
+    use parser::type_analyzer::*;
+    use lexer::*;
+
+    macro_rules! analyze {
+        ($src: expr) => {{
+            let mut parser = RecursiveDescentParser::new(SimpleLexer::new($src.as_bytes()));
+            parser.run().unwrap();
+
+            let mut analyzer = TypeAnalyzer::new(parser.syntax_tree_mut());
+            analyzer.run().into_iter().map(|d| d.message).collect::<Vec<String>>()
+        }};
+    }
+
+    #[test]
+    fn test_assign_mismatch() {
+        let tests = vec!["int f(int a) { int b; b = a + 1; return b; }",
+                         "double f(int a) { double b = a; b = b * 2; return b; }",
+                         "float f() { float b; b = 1.5; return b; }",
+                         "int f(int a) { int b = a > 1 ? a : 1; return b; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "int f(int a) { int b; b = a * 1.5; return b; }";
+        assert_eq!(analyze!(src), vec!["cannot assign double to int"]);
+
+        let src = "int f() { int b = 2.0f; return b; }";
+        assert_eq!(analyze!(src), vec!["cannot assign float to int"]);
+
+        let src = "double g() { return 1.0; } int f() { int b; { b = g(); } return b; }";
+        assert_eq!(analyze!(src), vec!["cannot assign double to int"]);
+    }
+
+    #[test]
+    fn test_array() {
+        let tests = vec!["int f(int i) { int a[4], b; a[i] = 1; b = a[i + 1] * 2; return a[a[0]]; }",
+                         "double f() { double d[2]; d[0] = 1; return d[1]; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "int f() { int a[2]; return a[1.5]; }";
+        assert_eq!(analyze!(src), vec!["array subscript is not an integer"]);
+
+        let src = "int f(int b) { return b[0]; }";
+        assert_eq!(analyze!(src), vec!["subscripted value of type int is not an array"]);
+
+        let src = "int f() { int a[2]; a = 1; return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign int to int[2]"]);
+
+        let src = "int f() { int a[0]; return 0; }";
+        assert_eq!(analyze!(src), vec!["size of array `a` is not a positive integer"]);
+    }
+
+    #[test]
+    fn test_pointer() {
+        let tests = vec!["int f() { int a, *p = &a, **pp = &p; *p = 1; **pp = 2; return *p + a; }",
+                         "int f() { int a[2], *p; p = a; p = &a[1]; *p = 3; return p[0]; }",
+                         "int f() { double d, *p = &d; return 0; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "int f() { int a, *p; p = a; return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign int to int*"]);
+
+        let src = "int f() { int a; double *p = &a; return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign int* to double*"]);
+
+        let src = "int f(int a) { return *a; }";
+        assert_eq!(analyze!(src), vec!["cannot dereference a value of type int"]);
+
+        let src = "int f() { int a, *p = &a; *p = 1.5; return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign double to int"]);
+    }
+
+    #[test]
+    fn test_struct() {
+        let tests = vec!["struct P { int x; double y; };
+                          double f() { struct P p, *q = &p; p.x = 1; q->y = 2.5; return p.x + q->y; }",
+                         "struct N { int v; struct N *next; };
+                          int f() { struct N a, b; a.next = &b; b.v = 3; return a.next->v; }",
+                         "struct P { int x; }; int f() { struct P ps[2]; ps[1].x = 1; return ps[1].x; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "struct P { int x; }; int f() { struct P p; return p.z; }";
+        assert_eq!(analyze!(src), vec!["`struct P` has no member named `z`"]);
+
+        let src = "struct P { int x; }; int f() { struct P p; return p->x; }";
+        assert_eq!(analyze!(src), vec!["member reference type struct P is not a pointer"]);
+
+        let src = "int f(int a) { return a.x; }";
+        assert_eq!(analyze!(src), vec!["request for member `x` in a value of type int"]);
+
+        let src = "int f() { struct Q q; return 0; }";
+        assert_eq!(analyze!(src), vec!["variable `q` has incomplete type struct Q"]);
+
+        let src = "struct P { int x; }; int f() { struct P p; double d; p.x = d; return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign double to int"]);
+    }
+
+    #[test]
+    fn test_unsigned() {
+        let src = "int f(unsigned a) { unsigned long *p = &a; return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign unsigned int* to unsigned long*"]);
+
+        // the usual arithmetic conversions
+        let src = "int f(unsigned a, unsigned short s, long l, unsigned char c) {
+                       int *p; p = a + 1; p = s * s; p = a - l; p = c; return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign unsigned int to int*",
+                                       "cannot assign int to int*",
+                                       "cannot assign long to int*",
+                                       "cannot assign unsigned char to int*"]);
+    }
+
+    #[test]
+    fn test_enum() {
+        let tests = vec!["enum Color { RED, GREEN = 3, BLUE }; int f() { int c = GREEN; return c + BLUE; }",
+                         "enum E { A, B = A + 1 }; int f() { enum E e; e = B; return e; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "enum E { A = 1.5 };";
+        assert_eq!(analyze!(src), vec!["enumerator value of `A` is not an integer"]);
+
+        let src = "enum E { A = B };";
+        assert_eq!(analyze!(src), vec!["undeclared identifier `B`"]);
+    }
+
+    #[test]
+    fn test_const() {
+        let tests = vec!["const int a = 1; int f() { int b = a + 1; return b; }",
+                         "int f(const int a) { int b; b = a; return b; }",
+                         "int f() { const int *p; int a; p = &a; return *p; }",
+                         "int f() { const int a = 1; { int a; a = 2; } return a; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), Vec::<String>::new());
+        }
+
+        let tests = vec![("int f() { const int a = 1; a = 2; return a; }",
+                          "assignment of read-only variable `a`"),
+                         ("int f(const int a) { a = 2; return a; }",
+                          "assignment of read-only variable `a`"),
+                         ("struct S { int x; }; int f() { const struct S s; s.x = 1; return s.x; }",
+                          "assignment of read-only variable `s`")];
+        for (test, message) in tests {
+            assert_eq!(analyze!(test), vec![message.to_owned()]);
+        }
+    }
+
+    #[test]
+    fn test_loop_control() {
+        let tests = vec!["int f(int a) { while (a) { if (a > 1) break; a = a - 1; } return a; }",
+                         "int f(int a) { for (;;) { { continue; } } return a; }",
+                         "int f(int a) { do { while (a) break; continue; } while (a); return a; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), Vec::<String>::new());
+        }
+
+        let tests = vec![("int f(int a) { if (a) break; return a; }",
+                          "`break` statement not within a loop or switch"),
+                         ("int f(int a) { while (a) a = a - 1; continue; return a; }",
+                          "`continue` statement not within a loop")];
+        for (test, message) in tests {
+            assert_eq!(analyze!(test), vec![message.to_owned()]);
+        }
+    }
+
+    #[test]
+    fn test_inc_dec() {
+        let tests = vec!["int f(int a) { a++; return --a; }",
+                         "int f(double d) { int a; int *p = &a; d--; p++; return 0; }",
+                         "struct S { int a; }; int f() { struct S s; s.a = 1; ++s.a; return s.a++; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), Vec::<String>::new());
+        }
+
+        let tests = vec![("int f() { const int a = 1; a++; return a; }",
+                          "increment of read-only variable `a`"),
+                         ("int f() { const int a = 1; return --a; }",
+                          "decrement of read-only variable `a`"),
+                         ("struct S { int a; }; int f() { struct S s; s++; return 0; }",
+                          "wrong type argument to increment: struct S")];
+        for (test, message) in tests {
+            assert_eq!(analyze!(test), vec![message.to_owned()]);
+        }
+    }
+
+    #[test]
+    fn test_switch() {
+        let tests = vec!["int f(int a) { switch (a) { case 1: return 1; case -1: break; default: a = 0; } return a; }",
+                         "int f(int a) { while (a) switch (a) { case 1: continue; default: break; } return a; }",
+                         "int f(int a) { switch (a) { case 1: switch (a) { case 1: break; } } return a; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), Vec::<String>::new());
+        }
+
+        let tests = vec![("int f(int a) { switch (a) { case 1: case 1: break; } return a; }",
+                          "duplicate case value `1`"),
+                         ("int f(int a) { switch (a) { case a: break; } return a; }",
+                          "case label is not an integer constant"),
+                         ("int f(int a) { case 1: return a; }",
+                          "`case` label not within a switch statement"),
+                         ("int f(int a) { default: return a; }",
+                          "`default` label not within a switch statement"),
+                         ("int f(double d) { switch (d) { default: break; } return 0; }",
+                          "switch quantity is not an integer"),
+                         ("int f(int a) { switch (a) { case 1: continue; } return a; }",
+                          "`continue` statement not within a loop")];
+        for (test, message) in tests {
+            assert_eq!(analyze!(test), vec![message.to_owned()]);
+        }
+    }
+
+    #[test]
+    fn test_label() {
+        let test = "int f(int a) { again: a = a - 1; if (a > 0) goto again; return a; }";
+        assert_eq!(analyze!(test), Vec::<String>::new());
+
+        let test = "int f(int a) { out: return b; }";
+        assert_eq!(analyze!(test), vec!["undeclared identifier `b`".to_owned()]);
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let tests = vec!["int f(int a) { int b = 0; return a / b; }",
+                         "int f(int a) { return 0 / a + a % 10; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), Vec::<String>::new());
+        }
+
+        let tests = vec!["int f(int a) { return a / 0; }",
+                         "int f(int a) { return a + a % 0; }",
+                         "int f(int a) { a = (a + 1) / 0; return a; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), vec!["division by zero".to_owned()]);
+        }
+    }
+
+    #[test]
+    fn test_condition() {
+        let tests = vec!["int f(int a) { int *p = &a; if (p) return 1; return 0; }",
+                         "int f(double d) { while (d) d = d - 1; return 0; }",
+                         "int f() { for (;;) return 1; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), Vec::<String>::new());
+        }
+
+        let tests = vec!["void g() { return; } int f() { if (g()) return 1; return 0; }",
+                         "struct S { int a; }; int f() { struct S s; while (s) s.a = 1; return 0; }",
+                         "void g() { return; } int f() { do g(); while (g()); return 0; }",
+                         "void g() { return; } int f() { for (; g(); ) g(); return 0; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), vec!["condition is not scalar".to_owned()]);
+        }
+    }
+
+    #[test]
+    fn test_type_define() {
+        let tests = vec!["typedef long Big; Big f(int a) { Big b = a; return b * 2; }",
+                         "typedef struct N N; struct N { int v; N *next; }; int f() { N a, b; a.next = &b; return a.next->v; }",
+                         "typedef int Pair[2]; int f() { Pair p; p[1] = 2; return p[1] + sizeof(Pair); }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        // the alias is the type it stands for
+        let src = "typedef unsigned char Byte; typedef Byte *Bytes; int f() { int *p; Bytes b; p = b; return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign unsigned char* to int*"]);
+    }
+
+    #[test]
+    fn test_sizeof() {
+        let tests = vec!["int f(int a) { int b[4]; return sizeof(int) + sizeof a + sizeof b[1]; }",
+                         "struct S { int x; }; int f() { struct S s; return sizeof(struct S) + sizeof s.x; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        // an unsigned long
+        let src = "int f() { int *p; p = sizeof(char); return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign unsigned long to int*"]);
+
+        let src = "int f() { return sizeof(void); }";
+        assert_eq!(analyze!(src), vec!["invalid application of sizeof to void"]);
+
+        let src = "int f() { return sizeof(struct Q); }";
+        assert_eq!(analyze!(src), vec!["invalid application of sizeof to incomplete type struct Q"]);
+
+        let src = "int f() { return sizeof x; }";
+        assert_eq!(analyze!(src), vec!["undeclared identifier `x`"]);
+    }
+
+    #[test]
+    fn test_cast() {
+        let tests = vec!["int f(double d) { int a; a = (int)d; return a; }",
+                         "double f(int a, int b) { return (double)a / b; }",
+                         "int f(int a) { double d = (double)(a + 1); return (int)-d; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "int f(double d) { int *p = (int)d; return 0; }";
+        assert_eq!(analyze!(src), vec!["cannot assign int to int*"]);
+
+        let src = "struct S { int a; }; int f() { struct S s; return (int)s; }";
+        assert_eq!(analyze!(src), vec!["cannot cast struct S to int"]);
+
+        let src = "int f() { int a, *p = &a; return (double)p; }";
+        assert_eq!(analyze!(src), vec!["cannot cast int* to double"]);
+    }
+
+    #[test]
+    fn test_call_argument_count() {
+        let tests = vec!["int f(int a, int b) { return a + b; } int g() { return f(1, 2); }",
+                         "int g() { return f(1); } int f(int a) { return a; }",
+                         "int f(int a); int g() { return f(1); }",
+                         "int fib(int n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "int f(int a, int b) { return a + b; } int g() { return f(1, 2, 3); }";
+        assert_eq!(analyze!(src), vec!["f expects 2 arguments, got 3"]);
+
+        let src = "int g() { return f(); } int f(int a) { return a; }";
+        assert_eq!(analyze!(src), vec!["f expects 1 arguments, got 0"]);
+
+        let src = "void f() {} int g() { f(1); return 0; }";
+        assert_eq!(analyze!(src), vec!["f expects 0 arguments, got 1"]);
+    }
+
+    #[test]
+    fn test_redeclaration() {
+        let tests = vec!["int a; int f(int b) { int a = b; { int a; a = 1; } return a; }",
+                         "int f(int a) { int b; { int b = a; { double b; } } return b; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "int f() { int a; int a; return 0; }";
+        assert_eq!(analyze!(src), vec!["redeclaration of `a`"]);
+
+        let src = "int f() { int a = 1, b, a; return a + b; }";
+        assert_eq!(analyze!(src), vec!["redeclaration of `a`"]);
+
+        let src = "int f(int a) { { int c; double c; } int a; return a; }";
+        assert_eq!(analyze!(src), vec!["redeclaration of `c`", "redeclaration of `a`"]);
+    }
+
+    #[test]
+    fn test_return_type() {
+        let tests = vec!["int f() { return 1; }",
+                         "double f() { return 1; }",
+                         "void f() { return; }",
+                         "void f() { }",
+                         "int f(int a) { if (a) { return 1; } return 0; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "int f() { return; }";
+        assert_eq!(analyze!(src), vec!["return without a value in function returning int"]);
+
+        let src = "void f() { return 1; }";
+        assert_eq!(analyze!(src), vec!["return with a value in function returning void"]);
+
+        let src = "int f() { return 1.5; }";
+        assert_eq!(analyze!(src), vec!["cannot return double from function returning int"]);
+
+        let src = "int f(int a) { a = 1; }";
+        assert_eq!(analyze!(src), vec!["control reaches end of non-void function `f`"]);
+    }
+
+    #[test]
+    fn test_missing_return() {
+        let tests = vec!["int f(int a) { if (a) return 1; else return 0; }",
+                         "int f(int a) { if (a) { return 1; } else if (a > 1) return 2; else { a = 1; return a; } }",
+                         "int f(int a) { while (1) { if (a) return a; } }",
+                         "int f(int a) { for (;;) { while (a) break; } }",
+                         "int f(int a) { do { a = a - 1; } while (1); }",
+                         "int f(int a) { switch (a) { case 1: return 1; default: return 0; } }",
+                         "int f(int a) { goto out; out: return a; }",
+                         "int f(int a) { again: if (a) return a; goto again; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), Vec::<String>::new());
+        }
+
+        let tests = vec!["int f(int a) { if (a > 0) return 1; }",
+                         "int f(int a) { if (a) return 1; else a = 2; }",
+                         "int f(int a) { while (a) return a; }",
+                         "int f(int a) { while (1) { if (a) break; } }",
+                         "int f(int a) { for (;;) { switch (a) { default: break; } break; } }",
+                         "int f(int a) { switch (a) { case 1: return 1; } }",
+                         "int f(int a) { switch (a) { case 1: break; default: return 0; } }",
+                         "int f(int a) { return a; out: a = 1; }"];
+        for test in tests {
+            assert_eq!(analyze!(test), vec!["control reaches end of non-void function `f`".to_owned()]);
+        }
+    }
+
+    #[test]
+    fn test_identifier_types() {
+        let src = "int f(int a) { double b = a; return f(a); }";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+        assert!(TypeAnalyzer::new(parser.syntax_tree_mut()).run().is_empty());
+
+        let tree = parser.syntax_tree();
+        let types: Vec<(String, Type)> = tree.traverse_pre_order(tree.root_node_id().unwrap())
+            .unwrap()
+            .filter_map(|node| match node.data().token() {
+                Some(tok) => match *tok {
+                    Token::Identifier(ref name, ref t) => Some((name.clone(), t.clone())),
+                    _ => None,
+                },
+                None => None,
+            })
+            .collect();
+
+        let func = Type::Func(vec![Type::SignedInt], Box::new(Type::SignedInt));
+        assert_eq!(types, vec![("f".to_owned(), func.clone()),
+                               ("a".to_owned(), Type::SignedInt),
+                               ("b".to_owned(), Type::Double),
+                               ("a".to_owned(), Type::SignedInt),
+                               ("f".to_owned(), func),
+                               ("a".to_owned(), Type::SignedInt)]);
+    }
+
+    #[test]
+    fn test_multiple_diagnostics() {
+        let src = "
+int f(int a)
+{
+    int b;
+    b = 1.5;
+
+    return z;
+}
+
+int g()
+{
+    return f(1, 2);
+}
+";
+        assert_eq!(analyze!(src), vec!["cannot assign double to int",
+                                       "undeclared identifier `z`",
+                                       "f expects 1 arguments, got 2"]);
+    }
+
+    #[test]
+    fn test_diagnostic_spans() {
+        let src = "
+int f(int a)
+{
+    int b;
+    b = 1.5;
+
+    return a + z;
+}
+
+void g()
+{
+    return;
+    return 1;
+}
+";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let spans = parser.spans().clone();
+        let diagnostics: Vec<(String, Option<(usize, usize)>)> =
+            TypeAnalyzer::with_spans(parser.syntax_tree_mut(), spans).run().into_iter()
+                .map(|d| (d.message, d.span.map(|s| (s.line, s.column))))
+                .collect();
+
+        assert_eq!(diagnostics, vec![("cannot assign double to int".to_owned(), Some((5, 5))),
+                                     ("undeclared identifier `z`".to_owned(), Some((7, 16))),
+                                     ("return with a value in function returning void".to_owned(), Some((13, 5)))]);
+
+        // without the parser's spans
+        assert!(TypeAnalyzer::new(parser.syntax_tree_mut()).run().iter().all(|d| d.span.is_none()));
+    }
+
+    #[test]
+    fn test_undeclared_identifier() {
+        let tests = vec!["int c; int f(int a, int b) { return a + b + c; }",
+                         "int f(int a) { int b; { int c = a; b = c; } return b; }",
+                         "int f(int a) { if (a) { int a = 1; return a; } return a; }"];
+        for test in tests {
+            assert!(analyze!(test).is_empty());
+        }
+
+        let src = "int f(int a, int b) { return a + b * z; }";
+        assert_eq!(analyze!(src), vec!["undeclared identifier `z`"]);
+
+        // out of the block `c` was declared in
+        let src = "int f(int a) { { int c; c = a; } return c; }";
+        assert_eq!(analyze!(src), vec!["undeclared identifier `c`"]);
+
+        // parameters belong to their own function
+        let src = "int g(int p) { return p; } int f() { return p; }";
+        assert_eq!(analyze!(src), vec!["undeclared identifier `p`"]);
+
+        let src = "int f() { x = 1; return 0; }";
+        assert_eq!(analyze!(src), vec!["undeclared identifier `x`"]);
+    }
+}
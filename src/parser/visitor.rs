@@ -0,0 +1,313 @@
+
+use token::Token;
+use parser::syntax_node::*;
+
+use id_tree::NodeId;
+
+/// A pass over the syntax tree. Every `visit_*` method walks the node's children
+/// by default, so an implementation overrides only the nodes it cares about and
+/// calls `walk_children` where it still wants to descend.
+pub trait Visitor {
+    fn visit_terminal(&mut self, _tree: &SyntaxTree, _id: &NodeId, _token: &Token) {}
+
+    fn visit_syntax_tree(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_struct_define(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_enum_define(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_enumerator(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_type_define(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_variable_define(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_array_declare(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_array_index(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_pointer_declare(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_address_of(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_dereference(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_prefix_inc_dec(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_postfix_inc_dec(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_member_access(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_cast(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_sizeof(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_unsigned_type(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_typedef_name(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_const_type(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_expr(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_boolean_expr(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_ternary_expr(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_expr_opt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_stmt_block(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_empty_stmt(&mut self, _tree: &SyntaxTree, _id: &NodeId) {}
+
+    fn visit_assign_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_if_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_else_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_return_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_break_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_continue_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_labeled_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_goto_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_while_loop(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_for_loop(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_do_while_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_switch_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_case_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_default_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_func_define(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_func_declare(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_func_param(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_func_arg(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+
+    fn visit_func_call(&mut self, tree: &SyntaxTree, id: &NodeId) {
+        walk_children(self, tree, id);
+    }
+}
+
+/// Dispatch `id` to the `visit_*` method of its kind.
+pub fn walk<V: Visitor + ?Sized>(visitor: &mut V, tree: &SyntaxTree, id: &NodeId) {
+    match *tree.get(id).unwrap().data() {
+        SyntaxType::Terminal(ref tok) => visitor.visit_terminal(tree, id, tok),
+        SyntaxType::SyntaxTree => visitor.visit_syntax_tree(tree, id),
+        SyntaxType::StructDefine => visitor.visit_struct_define(tree, id),
+        SyntaxType::EnumDefine => visitor.visit_enum_define(tree, id),
+        SyntaxType::Enumerator => visitor.visit_enumerator(tree, id),
+        SyntaxType::TypeDefine => visitor.visit_type_define(tree, id),
+        SyntaxType::VariableDefine => visitor.visit_variable_define(tree, id),
+        SyntaxType::ArrayDeclare => visitor.visit_array_declare(tree, id),
+        SyntaxType::ArrayIndex => visitor.visit_array_index(tree, id),
+        SyntaxType::PointerDeclare => visitor.visit_pointer_declare(tree, id),
+        SyntaxType::AddressOf => visitor.visit_address_of(tree, id),
+        SyntaxType::Dereference => visitor.visit_dereference(tree, id),
+        SyntaxType::PrefixIncDec => visitor.visit_prefix_inc_dec(tree, id),
+        SyntaxType::PostfixIncDec => visitor.visit_postfix_inc_dec(tree, id),
+        SyntaxType::MemberAccess => visitor.visit_member_access(tree, id),
+        SyntaxType::Cast => visitor.visit_cast(tree, id),
+        SyntaxType::Sizeof => visitor.visit_sizeof(tree, id),
+        SyntaxType::UnsignedType => visitor.visit_unsigned_type(tree, id),
+        SyntaxType::TypedefName => visitor.visit_typedef_name(tree, id),
+        SyntaxType::ConstType => visitor.visit_const_type(tree, id),
+        SyntaxType::Expr => visitor.visit_expr(tree, id),
+        SyntaxType::BooleanExpr => visitor.visit_boolean_expr(tree, id),
+        SyntaxType::TernaryExpr => visitor.visit_ternary_expr(tree, id),
+        SyntaxType::ExprOpt => visitor.visit_expr_opt(tree, id),
+        SyntaxType::StmtBlock => visitor.visit_stmt_block(tree, id),
+        SyntaxType::EmptyStmt => visitor.visit_empty_stmt(tree, id),
+        SyntaxType::AssignStmt => visitor.visit_assign_stmt(tree, id),
+        SyntaxType::IfStmt => visitor.visit_if_stmt(tree, id),
+        SyntaxType::ElseStmt => visitor.visit_else_stmt(tree, id),
+        SyntaxType::ReturnStmt => visitor.visit_return_stmt(tree, id),
+        SyntaxType::BreakStmt => visitor.visit_break_stmt(tree, id),
+        SyntaxType::ContinueStmt => visitor.visit_continue_stmt(tree, id),
+        SyntaxType::LabeledStmt => visitor.visit_labeled_stmt(tree, id),
+        SyntaxType::GotoStmt => visitor.visit_goto_stmt(tree, id),
+        SyntaxType::WhileLoop => visitor.visit_while_loop(tree, id),
+        SyntaxType::ForLoop => visitor.visit_for_loop(tree, id),
+        SyntaxType::DoWhileStmt => visitor.visit_do_while_stmt(tree, id),
+        SyntaxType::SwitchStmt => visitor.visit_switch_stmt(tree, id),
+        SyntaxType::CaseStmt => visitor.visit_case_stmt(tree, id),
+        SyntaxType::DefaultStmt => visitor.visit_default_stmt(tree, id),
+        SyntaxType::FuncDefine => visitor.visit_func_define(tree, id),
+        SyntaxType::FuncDeclare => visitor.visit_func_declare(tree, id),
+        SyntaxType::FuncParam => visitor.visit_func_param(tree, id),
+        SyntaxType::FuncArg => visitor.visit_func_arg(tree, id),
+        SyntaxType::FuncCall => visitor.visit_func_call(tree, id),
+    }
+}
+
+/// `walk` every child of `id`, in order.
+pub fn walk_children<V: Visitor + ?Sized>(visitor: &mut V, tree: &SyntaxTree, id: &NodeId) {
+    for child in tree.children_ids(id).unwrap() {
+        walk(visitor, tree, child);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use parser::*;
+    use parser::recursive_descent::*;
+    use parser::syntax_node::*;
+    use parser::visitor::*;
+    use lexer::*;
+    use token::*;
+
+    use id_tree::NodeId;
+
+    struct ReturnCounter {
+        returns: usize,
+        identifiers: Vec<String>,
+    }
+
+    impl Visitor for ReturnCounter {
+        fn visit_return_stmt(&mut self, tree: &SyntaxTree, id: &NodeId) {
+            self.returns += 1;
+            walk_children(self, tree, id);
+        }
+
+        // parameters are skipped, only their uses are collected.
+        fn visit_func_param(&mut self, _tree: &SyntaxTree, _id: &NodeId) {}
+
+        fn visit_terminal(&mut self, _tree: &SyntaxTree, _id: &NodeId, token: &Token) {
+            if let Token::Identifier(ref name, _) = *token {
+                self.identifiers.push(name.clone());
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let src = "
+int abs(int a)
+{
+    if (a < 0) {
+        return -a;
+    } else {
+        return a;
+    }
+}
+
+void f() { return; }
+
+int g(int b)
+{
+    while (b) {
+        if (b > 10) return abs(b);
+        b = b - 1;
+    }
+
+    return b;
+}
+";
+        let mut parser = RecursiveDescentParser::new(SimpleLexer::new(src.as_bytes()));
+        parser.run().unwrap();
+
+        let mut counter = ReturnCounter { returns: 0, identifiers: vec![] };
+        let tree = parser.syntax_tree();
+        walk(&mut counter, tree, tree.root_node_id().unwrap());
+
+        assert_eq!(counter.returns, 5);
+        assert_eq!(counter.identifiers, vec!["abs", "a", "a", "a", "f", "g", "b", "b", "abs", "b", "b", "b", "b"]);
+    }
+}
@@ -44,16 +44,21 @@ pub enum KeyWords {
 #[derive(Clone, Debug, PartialEq)]
 pub enum Type {
     NoType,
+    SignedChar,
+    UnsignedChar,
     SignedShort,
     UnsignedShort,
     SignedInt,
     UnsignedInt,
+    SignedLong,
+    UnsignedLong,
     Float,
     Double,
     Void,
-    Class,
+    Struct(String),
     Func(Vec<Type>, Box<Type>),
     Ptr(Box<Type>),
+    Array(Box<Type>, usize),
 }
 
 impl KeyWords {
@@ -75,22 +80,98 @@ impl KeyWords {
 
     pub fn to_type(&self) -> Option<Type> {
         match *self {
+            KeyWords::Char => Some(Type::SignedChar),
+            KeyWords::Short => Some(Type::SignedShort),
             KeyWords::Int => Some(Type::SignedInt),
+            KeyWords::Long => Some(Type::SignedLong),
+            KeyWords::Float => Some(Type::Float),
+            KeyWords::Double => Some(Type::Double),
+            KeyWords::Void => Some(Type::Void),
             _ => None,
         }
     }
 }
 
+impl Type {
+    pub fn is_integer(&self) -> bool {
+        match *self {
+            Type::SignedChar | Type::UnsignedChar |
+            Type::SignedShort | Type::UnsignedShort |
+            Type::SignedInt | Type::UnsignedInt |
+            Type::SignedLong | Type::UnsignedLong => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        match *self {
+            Type::UnsignedChar | Type::UnsignedShort |
+            Type::UnsignedInt | Type::UnsignedLong => true,
+            _ => false,
+        }
+    }
+
+    /// The unsigned type of the same width, `None` if the type isn't an integer.
+    pub fn to_unsigned(&self) -> Option<Type> {
+        match *self {
+            Type::SignedChar | Type::UnsignedChar => Some(Type::UnsignedChar),
+            Type::SignedShort | Type::UnsignedShort => Some(Type::UnsignedShort),
+            Type::SignedInt | Type::UnsignedInt => Some(Type::UnsignedInt),
+            Type::SignedLong | Type::UnsignedLong => Some(Type::UnsignedLong),
+            _ => None,
+        }
+    }
+
+    pub fn is_floating(&self) -> bool {
+        match *self {
+            Type::Float | Type::Double => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            &Type::NoType => write!(f, "<unknown>"),
+            &Type::SignedChar => write!(f, "char"),
+            &Type::UnsignedChar => write!(f, "unsigned char"),
+            &Type::SignedShort => write!(f, "short"),
+            &Type::UnsignedShort => write!(f, "unsigned short"),
+            &Type::SignedInt => write!(f, "int"),
+            &Type::UnsignedInt => write!(f, "unsigned int"),
+            &Type::SignedLong => write!(f, "long"),
+            &Type::UnsignedLong => write!(f, "unsigned long"),
+            &Type::Float => write!(f, "float"),
+            &Type::Double => write!(f, "double"),
+            &Type::Void => write!(f, "void"),
+            &Type::Struct(ref name) => write!(f, "struct {}", name),
+            &Type::Ptr(ref t) => write!(f, "{}*", t),
+            &Type::Array(ref t, n) => write!(f, "{}[{}]", t, n),
+            &Type::Func(ref args, ref ret) => {
+                write!(f, "{}(", ret)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 { write!(f, ", ")?; }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            },
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Operators {
     Add,
     Assign,
     AddEqual,
     And,
+    AndEqual,
     Arrow,
     DoubleAdd,
     DoubleMinus,
     Division,
+    DivisionEqual,
     Equal,
     Greater,
     GreaterEqual,
@@ -101,11 +182,20 @@ pub enum Operators {
     LogicOr,
     Minus,
     MinusEqual,
+    Mod,
+    ModEqual,
     Mul,
+    MulEqual,
     Not,
     NotEqual,
     Or,
+    OrEqual,
+    ShiftLeft,
+    ShiftLeftEqual,
+    ShiftRight,
+    ShiftRightEqual,
     Xor,
+    XorEqual,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -126,8 +216,41 @@ pub enum Numbers {
 }
 
 impl Numbers {
+    /// Panics when `s` is not a valid number literal, see `try_from_str`.
     pub fn from_str<T: AsRef<str>>(s: T) -> Numbers {
-        Numbers::SignedInt(s.as_ref().parse::<isize>().unwrap())
+        Numbers::try_from_str(s).unwrap()
+    }
+
+    pub fn try_from_str<T: AsRef<str>>(s: T) -> Option<Numbers> {
+        let s = s.as_ref();
+
+        if s.starts_with("0x") || s.starts_with("0X") {
+            return Numbers::from_str_radix(&s[2..], 16);
+        }
+
+        if s.starts_with("0b") || s.starts_with("0B") {
+            return Numbers::from_str_radix(&s[2..], 2);
+        }
+
+        // `f` suffix means single precision float
+        if s.ends_with('f') || s.ends_with('F') {
+            return s[..s.len() - 1].parse::<f32>().ok().map(Numbers::Float);
+        }
+
+        if s.contains(|c| c == '.' || c == 'e' || c == 'E') {
+            return s.parse::<f64>().ok().map(Numbers::Double);
+        }
+
+        // leading zero means octal
+        if s.len() > 1 && s.starts_with('0') {
+            return Numbers::from_str_radix(&s[1..], 8);
+        }
+
+        s.parse::<isize>().ok().map(Numbers::SignedInt)
+    }
+
+    pub fn from_str_radix<T: AsRef<str>>(s: T, radix: u32) -> Option<Numbers> {
+        isize::from_str_radix(s.as_ref(), radix).ok().map(Numbers::SignedInt)
     }
 }
 
@@ -136,6 +259,8 @@ pub enum Token {
     Arrow,
     Asterisk,
     Bracket(Brackets),
+    CharLiteral(char),
+    Colon,
     Comment(String),
     Comma,
     Dot,
@@ -144,6 +269,7 @@ pub enum Token {
     Number(Numbers),
     Operator(Operators),
     Preprocessor(String),
+    Question,
     Space,
     Semicolon,
     Identifier(String, Type),
@@ -254,8 +380,11 @@ impl Display for Token {
             &Token::Semicolon => write!(f, "semicolon:\t ';'"),
             &Token::Asterisk => write!(f, "asterisk:\t '*'"),
             &Token::Comma => write!(f, "comma:\t\t ','"),
+            &Token::Colon => write!(f, "colon:\t\t ':'"),
+            &Token::Question => write!(f, "question:\t '?'"),
             &Token::Dot => write!(f, "dot:\t\t '.'"),
             &Token::LiteralStr(ref s) => write!(f, "literal:\t {}", s),
+            &Token::CharLiteral(c) => write!(f, "char:\t\t {:?}", c),
             &Token::Bracket(ref b) => write!(f, "bracket:\t {:?}", b),
             &Token::Number(ref n) => write!(f, "number:\t\t {:?}", n),
             &Token::Comment(ref s) => write!(f, "comment:\t {}", s),
@@ -274,7 +403,34 @@ fn test_keywords() {
     assert!(!is_keywords("bool"));
 }
 
+#[test]
+fn test_numbers() {
+    assert_eq!(Numbers::from_str("42"), Numbers::SignedInt(42));
+    assert_eq!(Numbers::from_str("3.14"), Numbers::Double(3.14));
+    assert_eq!(Numbers::from_str("2.0f"), Numbers::Float(2.0));
+    assert_eq!(Numbers::from_str("1e9"), Numbers::Double(1e9));
+    assert_eq!(Numbers::from_str("1.5E-3"), Numbers::Double(1.5e-3));
+    assert_eq!(Numbers::from_str("0xFF"), Numbers::SignedInt(255));
+    assert_eq!(Numbers::from_str("0x1e"), Numbers::SignedInt(30));
+    assert_eq!(Numbers::from_str("0755"), Numbers::SignedInt(493));
+    assert_eq!(Numbers::from_str("0b1010"), Numbers::SignedInt(10));
+    assert_eq!(Numbers::from_str("0"), Numbers::SignedInt(0));
+    assert_eq!(Numbers::from_str("0.5"), Numbers::Double(0.5));
+    assert_eq!(Numbers::try_from_str("09"), None);
+    assert_eq!(Numbers::try_from_str("1.2.3"), None);
+}
+
 #[test]
 fn test_type() {
     assert!(KeyWords::Void.is_type());
+    assert_eq!(KeyWords::Double.to_type(), Some(Type::Double));
+    assert_eq!(KeyWords::Char.to_type(), Some(Type::SignedChar));
+    assert!(Type::SignedLong.is_integer());
+    assert!(Type::UnsignedLong.is_integer() && Type::UnsignedLong.is_unsigned());
+    assert_eq!(Type::SignedChar.to_unsigned(), Some(Type::UnsignedChar));
+    assert_eq!(Type::Double.to_unsigned(), None);
+    assert_eq!(format!("{}", Type::Func(vec![Type::SignedInt, Type::Float], Box::new(Type::Void))),
+               "void(int, float)");
+    assert_eq!(format!("{}", Type::Array(Box::new(Type::Double), 4)), "double[4]");
+    assert_eq!(format!("{}", Type::Ptr(Box::new(Type::Struct("S".to_owned())))), "struct S*");
 }